@@ -0,0 +1,48 @@
+use std::{net::IpAddr, path::Path};
+
+use maxminddb::{geoip2, MaxMindDbError, Mmap, Reader};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsnInfo {
+    pub asn: u32,
+    pub org: String,
+}
+
+impl AsnInfo {
+    pub fn label(&self) -> String {
+        format!("AS{} {}", self.asn, self.org)
+    }
+}
+
+pub fn open_asn_db(path: &Path) -> Result<Reader<Mmap>, MaxMindDbError> {
+    // SAFETY: the ASN database must not be modified or truncated while it is
+    // mapped, or lookups read invalid memory. Replace it with a rename rather
+    // than rewriting it in place.
+    unsafe { Reader::open_mmap(path) }
+}
+
+pub fn lookup_asn(reader: &Reader<Mmap>, ip: IpAddr) -> Option<AsnInfo> {
+    let result = reader.lookup(ip).ok()?;
+    let record: geoip2::Asn = result.decode().ok()??;
+
+    Some(AsnInfo {
+        asn: record.autonomous_system_number?,
+        org: record.autonomous_system_organization.unwrap_or("Unknown").to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asn_label() {
+        let info = AsnInfo { asn: 15169, org: String::from("Google LLC") };
+        assert_eq!(info.label(), "AS15169 Google LLC");
+    }
+
+    #[test]
+    fn test_open_asn_db_missing_file() {
+        assert!(open_asn_db(Path::new("/nonexistent/GeoLite2-ASN.mmdb")).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use ipnetwork::IpNetwork;
+
+#[derive(Debug, Default)]
+pub struct Blacklist {
+    pub ips: HashSet<IpAddr>,
+    pub networks: Vec<IpNetwork>,
+    pub errors: Vec<String>,
+}
+
+impl Blacklist {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.ips.contains(&ip) || self.networks.iter().any(|n| n.contains(ip))
+    }
+
+    fn load_file(&mut self, path: &Path) {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                self.errors.push(format!("{}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        for (i, line) in content.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            if entry.contains('/') {
+                match entry.parse::<IpNetwork>() {
+                    Ok(net) => self.networks.push(net),
+                    Err(e) => self.errors.push(format!("{}:{}: invalid CIDR '{}': {}", path.display(), i + 1, entry, e)),
+                }
+            } else {
+                match entry.parse::<IpAddr>() {
+                    Ok(ip) => { self.ips.insert(ip); }
+                    Err(e) => self.errors.push(format!("{}:{}: invalid IP '{}': {}", path.display(), i + 1, entry, e)),
+                }
+            }
+        }
+    }
+}
+
+pub fn load_blacklist(paths: &[PathBuf]) -> Blacklist {
+    let mut blacklist = Blacklist::default();
+
+    for path in paths {
+        if path.is_dir() {
+            let entries = match fs::read_dir(path) {
+                Ok(e) => e,
+                Err(e) => {
+                    blacklist.errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("txt") | Some("cidr")))
+                .collect();
+            files.sort();
+
+            for file in files {
+                blacklist.load_file(&file);
+            }
+        } else {
+            blacklist.load_file(path);
+        }
+    }
+
+    blacklist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("netmonrs-blacklist-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_blacklist_single_file() {
+        let dir = temp_dir("single");
+        let file = dir.join("list.txt");
+        fs::write(&file, "# comment\n10.0.0.1\n\n2001:db8::1 # inline\n").unwrap();
+
+        let bl = load_blacklist(&[file]);
+        assert!(bl.contains("10.0.0.1".parse().unwrap()));
+        assert!(bl.contains("2001:db8::1".parse().unwrap()));
+        assert!(!bl.contains("10.0.0.2".parse().unwrap()));
+        assert!(bl.errors.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_blacklist_directory_mixed_entries() {
+        let dir = temp_dir("dir");
+        fs::write(dir.join("a.txt"), "1.1.1.1\nnot-an-ip\n").unwrap();
+        fs::write(dir.join("b.cidr"), "192.168.0.0/16\n10.0.0.0/99\n").unwrap();
+        fs::write(dir.join("ignored.md"), "8.8.8.8\n").unwrap();
+        fs::create_dir(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested").join("c.txt"), "9.9.9.9\n").unwrap();
+
+        let bl = load_blacklist(std::slice::from_ref(&dir));
+        assert!(bl.contains("1.1.1.1".parse().unwrap()));
+        assert!(bl.contains("192.168.42.7".parse().unwrap()));
+        assert!(!bl.contains("8.8.8.8".parse().unwrap()));
+        assert!(!bl.contains("9.9.9.9".parse().unwrap()));
+
+        assert_eq!(bl.errors.len(), 2);
+        assert!(bl.errors[0].contains("a.txt:2"));
+        assert!(bl.errors[1].contains("b.cidr:2"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_blacklist_merges_multiple_paths() {
+        let dir = temp_dir("multi");
+        let first = dir.join("first.txt");
+        let second = dir.join("second.txt");
+        fs::write(&first, "1.1.1.1\n").unwrap();
+        fs::write(&second, "2.2.2.2\n1.1.1.1\n").unwrap();
+
+        let bl = load_blacklist(&[first, second, dir.join("missing.txt")]);
+        assert_eq!(bl.ips.len(), 2);
+        assert_eq!(bl.errors.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::Path,
+};
+
+/// A missing file is treated as an empty bookmark set.
+pub fn load_bookmarks(path: &Path) -> io::Result<HashSet<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            let ips: Vec<String> = serde_json::from_str(&content).map_err(io::Error::other)?;
+            Ok(ips.into_iter().collect())
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save_bookmarks(path: &Path, bookmarks: &HashSet<String>) -> io::Result<()> {
+    let mut ips: Vec<&String> = bookmarks.iter().collect();
+    ips.sort();
+    let json = serde_json::to_string_pretty(&ips).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_round_trip() {
+        let path = std::env::temp_dir().join(format!("netmonrs-bookmarks-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(load_bookmarks(&path).unwrap().is_empty());
+
+        let bookmarks: HashSet<String> = ["93.184.216.34", "2001:db8::1"].iter().map(|s| s.to_string()).collect();
+        save_bookmarks(&path, &bookmarks).unwrap();
+        assert_eq!(load_bookmarks(&path).unwrap(), bookmarks);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(load_bookmarks(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
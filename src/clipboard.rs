@@ -0,0 +1,43 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Copies `text` to the system clipboard, trying `arboard` (with the
+/// `clipboard` feature) before falling back to `pbcopy` and `xclip`.
+pub fn copy(text: &str) -> Result<(), String> {
+    #[cfg(feature = "clipboard")]
+    if let Ok(mut clipboard) = arboard::Clipboard::new()
+        && clipboard.set_text(text).is_ok() {
+        return Ok(());
+    }
+
+    let commands: [(&str, &[&str]); 2] = [
+        ("pbcopy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+    ];
+    for (program, args) in commands {
+        if pipe_to(program, args, text).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(String::from("no clipboard available"))
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> std::io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("{} exited with {}", program, status)))
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_REMOTE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Frames larger than this are treated as a protocol error.
+const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a monitoring client reports to the collector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteSnapshot {
+    pub hostname: String,
+    pub target: String,
+    pub timestamp: String,
+    pub active_connections: Vec<String>,
+    pub history_count: usize,
+    pub seen_ips_count: usize,
+}
+
+/// Writes a 4-byte little-endian length prefix followed by `payload`.
+pub fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Returns `None` when the peer closed the connection between frames.
+pub fn read_frame(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is too large", len)));
+    }
+    let mut payload = vec![0u8; len as usize];
+    r.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// The latest snapshot from every `(hostname, target)` pair.
+#[derive(Debug, Default)]
+pub struct Collector {
+    snapshots: BTreeMap<(String, String), RemoteSnapshot>,
+}
+
+impl Collector {
+    pub fn merge(&mut self, snapshot: RemoteSnapshot) {
+        self.snapshots.insert((snapshot.hostname.clone(), snapshot.target.clone()), snapshot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn total_active(&self) -> usize {
+        self.snapshots.values().map(|s| s.active_connections.len()).sum()
+    }
+
+    pub fn summary_line(&self, snapshot: &RemoteSnapshot) -> String {
+        format!(
+            "[{}] {}/{}: {} active, {} seen ({} clients, {} active total)",
+            Local::now().format("%H:%M:%S"),
+            snapshot.hostname,
+            snapshot.target,
+            snapshot.active_connections.len(),
+            snapshot.seen_ips_count,
+            self.len(),
+            self.total_active(),
+        )
+    }
+}
+
+/// Runs `netmonrs collect`, printing a line for every snapshot received.
+pub fn run(listen: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("Collecting on {}", listener.local_addr()?);
+    serve(listener, Arc::new(Mutex::new(Collector::default())))
+}
+
+/// Errors from `accept()`, e.g. running out of file descriptors, only skip
+/// that connection.
+pub fn serve(listener: TcpListener, collector: Arc<Mutex<Collector>>) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("accept failed: {}", e);
+                continue;
+            }
+        };
+        let collector = Arc::clone(&collector);
+        thread::spawn(move || {
+            let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+            if let Err(e) = handle_client(stream, &collector) {
+                eprintln!("{}: {}", peer, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_client(mut stream: TcpStream, collector: &Mutex<Collector>) -> io::Result<()> {
+    while let Some(frame) = read_frame(&mut stream)? {
+        let snapshot: RemoteSnapshot = serde_json::from_slice(&frame)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let line = {
+            let mut collector = collector.lock().unwrap_or_else(|e| e.into_inner());
+            collector.merge(snapshot.clone());
+            collector.summary_line(&snapshot)
+        };
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Sends snapshots to a collector from a background thread, reconnecting
+/// after errors. Snapshots are dropped while a send is still in progress.
+pub struct RemoteReporter {
+    tx: mpsc::SyncSender<RemoteSnapshot>,
+    hostname: String,
+}
+
+impl RemoteReporter {
+    pub fn spawn(addr: String) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<RemoteSnapshot>(1);
+        thread::spawn(move || {
+            let mut stream: Option<TcpStream> = None;
+            for snapshot in rx {
+                let Ok(json) = serde_json::to_vec(&snapshot) else { continue };
+                if stream.is_none() {
+                    stream = TcpStream::connect(&addr).ok();
+                    if let Some(s) = &stream {
+                        let _ = s.set_write_timeout(Some(WRITE_TIMEOUT));
+                    }
+                }
+                if let Some(s) = stream.as_mut()
+                    && write_frame(s, &json).is_err() {
+                    stream = None;
+                }
+            }
+        });
+        Self { tx, hostname: local_hostname() }
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn send(&self, snapshot: RemoteSnapshot) {
+        let _ = self.tx.try_send(snapshot);
+    }
+}
+
+pub fn local_hostname() -> String {
+    dns_lookup::get_hostname().unwrap_or_else(|_| String::from("unknown"))
+}
+
+/// Sends a single snapshot and waits until it is written, for `--once`
+/// where a `RemoteReporter` thread wouldn't get to run before exit.
+pub fn send_now(addr: &str, snapshot: &RemoteSnapshot) -> io::Result<()> {
+    let json = serde_json::to_vec(snapshot).map_err(io::Error::other)?;
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    write_frame(&mut stream, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, time::Instant};
+
+    fn snapshot(hostname: &str, target: &str, active: usize) -> RemoteSnapshot {
+        RemoteSnapshot {
+            hostname: hostname.to_string(),
+            target: target.to_string(),
+            timestamp: String::from("2024-01-01T12:00:00+00:00"),
+            active_connections: (0..active).map(|i| format!("10.0.0.{}:443", i)).collect(),
+            history_count: 0,
+            seen_ips_count: active,
+        }
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"{\"a\":1}").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+        assert_eq!(&buf[..4], &7u32.to_le_bytes());
+
+        let mut r = Cursor::new(buf);
+        assert_eq!(read_frame(&mut r).unwrap(), Some(b"{\"a\":1}".to_vec()));
+        assert_eq!(read_frame(&mut r).unwrap(), Some(Vec::new()));
+        assert_eq!(read_frame(&mut r).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_frame_rejects_bad_input() {
+        let mut huge = Cursor::new((MAX_FRAME_LEN + 1).to_le_bytes().to_vec());
+        assert_eq!(read_frame(&mut huge).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut truncated = Cursor::new([5, 0, 0, 0, b'a'].to_vec());
+        assert_eq!(read_frame(&mut truncated).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_collector_merges_by_host_and_target() {
+        let mut collector = Collector::default();
+        collector.merge(snapshot("web1", "nginx", 3));
+        collector.merge(snapshot("web2", "nginx", 2));
+        collector.merge(snapshot("web1", "redis", 1));
+        collector.merge(snapshot("web1", "nginx", 5));
+        assert_eq!(collector.len(), 3);
+        assert_eq!(collector.total_active(), 8);
+        assert!(collector.summary_line(&snapshot("web1", "nginx", 5)).ends_with("web1/nginx: 5 active, 5 seen (3 clients, 8 active total)"));
+    }
+
+    #[test]
+    fn test_reporter_delivers_to_collector() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let collector = Arc::new(Mutex::new(Collector::default()));
+        let server = Arc::clone(&collector);
+        thread::spawn(move || serve(listener, server));
+
+        let reporter = RemoteReporter::spawn(addr);
+        reporter.send(snapshot(reporter.hostname(), "nginx", 2));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while collector.lock().unwrap().len() == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(collector.lock().unwrap().total_active(), 2);
+    }
+
+    #[test]
+    fn test_send_now_delivers_before_returning() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        send_now(&addr, &snapshot("web1", "nginx", 1)).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let frame = read_frame(&mut stream).unwrap().unwrap();
+        let received: RemoteSnapshot = serde_json::from_slice(&frame).unwrap();
+        assert_eq!(received, snapshot("web1", "nginx", 1));
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    env, fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Args;
+
+/// Args that only make sense on the command line.
+const CLI_ONLY: &[&str] = &["target", "profile", "save_profile"];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub asn_db: Option<PathBuf>,
+    pub show_asn: Option<bool>,
+    #[cfg(feature = "pcap-output")]
+    pub capture_file: Option<PathBuf>,
+    pub tee: Option<PathBuf>,
+    pub tee_max_mb: Option<u64>,
+    pub syslog: Option<bool>,
+    pub exec_on_connect: Option<String>,
+    pub exec_on_disconnect: Option<String>,
+    pub exec_on_startup: Option<String>,
+    pub exec_on_exit: Option<String>,
+    pub exec_timeout_ms: Option<u64>,
+    pub no_timeline: Option<bool>,
+    pub glob: Option<bool>,
+    pub fuzzy: Option<bool>,
+    pub exclude_process: Option<Vec<String>>,
+    pub quiet: Option<bool>,
+    pub once: Option<bool>,
+    pub diff: Option<bool>,
+    pub blacklist_file: Option<Vec<PathBuf>>,
+    pub tags_file: Option<PathBuf>,
+    pub max_history: Option<usize>,
+    pub max_history_age: Option<u64>,
+    pub min_age: Option<u64>,
+    pub min_age_invert: Option<bool>,
+    pub snapshot_interval: Option<u64>,
+    pub watch_count_file: Option<PathBuf>,
+    pub rate_file: Option<PathBuf>,
+    pub rate_file_format: Option<String>,
+    pub remote_monitor: Option<String>,
+    pub remote_interval: Option<u64>,
+    pub log_file: Option<PathBuf>,
+    pub history_grep: Option<String>,
+    pub show_unix_sockets: Option<bool>,
+    pub show_listening: Option<bool>,
+    pub alert_regex: Option<String>,
+    pub notify_rate_limit: Option<u64>,
+    pub lock: Option<bool>,
+    pub count: Option<usize>,
+    pub timeout: Option<u64>,
+    pub auto_layout: Option<bool>,
+    pub top_n: Option<usize>,
+    pub tail: Option<usize>,
+    pub no_port_colors: Option<bool>,
+    pub truncate_ip: Option<usize>,
+    pub columns: Option<String>,
+    pub resolve: Option<bool>,
+    pub report_after: Option<u64>,
+    pub report_loop: Option<bool>,
+    pub min_port: Option<u16>,
+    pub max_port: Option<u16>,
+    pub exclude_self: Option<bool>,
+    pub sample_rate: Option<u64>,
+    pub input_file: Option<PathBuf>,
+    pub mock: Option<bool>,
+    pub mock_seed: Option<u64>,
+    pub no_sudo: Option<bool>,
+    pub no_history: Option<bool>,
+    pub retry_on_error: Option<u32>,
+    pub lsof_timeout: Option<u64>,
+    pub lsof_cmd: Option<String>,
+    pub lsof_args: Option<String>,
+    pub alert_on_failures: Option<u32>,
+    pub column_layout: Option<String>,
+    pub border_style: Option<String>,
+    pub ipv6_display: Option<String>,
+    pub strip_ipv4_mapped: Option<bool>,
+    pub mask_ips: Option<bool>,
+    pub save_bookmarks: Option<PathBuf>,
+    pub all_time_record: Option<PathBuf>,
+    pub watch_pid_file: Option<PathBuf>,
+    pub pid_wait: Option<u64>,
+    pub geoip_db: Option<PathBuf>,
+    pub color_new_connections: Option<u64>,
+    pub color_by_age: Option<bool>,
+    pub color_seen: Option<bool>,
+    pub color_by_subnet: Option<u8>,
+    pub color_palette: Option<String>,
+    pub warn_private_to_public: Option<bool>,
+    pub allow_public_ip: Option<Vec<IpAddr>>,
+    pub linger_secs: Option<u64>,
+    pub export_on_exit: Option<bool>,
+    pub export_path: Option<PathBuf>,
+    pub event_queue_depth: Option<u64>,
+    pub watch_interval_jitter: Option<u64>,
+    pub seed: Option<u64>,
+    pub show_fd_count: Option<bool>,
+    pub alert_fd_count: Option<u32>,
+    pub interface_stats: Option<bool>,
+    pub watch_connections_delta: Option<usize>,
+    pub format_history: Option<String>,
+    pub history_format: Option<String>,
+    pub format_status: Option<String>,
+    pub hide_status_bar: Option<bool>,
+    pub replay: Option<PathBuf>,
+    pub netns: Option<PathBuf>,
+    pub container: Option<String>,
+    pub resolve_ports: Option<bool>,
+    pub tab: Option<Vec<String>>,
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("netmonrs"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+pub fn profile_path(name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid profile name '{}'", name)));
+    }
+    let dir = config_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?;
+    Ok(dir.join("profiles").join(format!("{}.toml", name)))
+}
+
+pub fn load_profile(name: &str) -> io::Result<Config> {
+    let path = profile_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    parse_config(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+/// Writes the options set in `args` to the named profile.
+pub fn save_profile(name: &str, args: &Args) -> io::Result<PathBuf> {
+    let path = profile_path(name)?;
+    let content = toml::to_string(&config_from_args(args)?).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+fn config_from_args(args: &Args) -> io::Result<Config> {
+    let toml::Value::Table(mut table) = toml::Value::try_from(args).map_err(io::Error::other)? else {
+        unreachable!("Args serializes to a table");
+    };
+    table.retain(|key, value| {
+        !CLI_ONLY.contains(&key)
+            && value.as_bool() != Some(false)
+            && value.as_array().is_none_or(|a| !a.is_empty())
+    });
+    toml::Value::Table(table).try_into().map_err(io::Error::other)
+}
+
+/// Options set in `overlay` replace those in `base`.
+pub fn merge_config(base: Config, overlay: Config) -> Config {
+    let (Ok(toml::Value::Table(mut base)), Ok(toml::Value::Table(overlay))) =
+        (toml::Value::try_from(base), toml::Value::try_from(overlay)) else {
+        unreachable!("Config serializes to a table");
+    };
+    base.extend(overlay);
+    toml::Value::Table(base).try_into().expect("merged tables hold only Config keys")
+}
+
+pub fn parse_config(content: &str) -> Result<Config, String> {
+    toml::from_str(content).map_err(|e| e.to_string())
+}
+
+pub fn load_config(path: &Path) -> Result<Option<Config>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_config(&content)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+// Values given on the command line always win over the config file.
+pub fn merge_into_args(args: &mut Args, config: Config) {
+    fn flag(cli: &mut bool, cfg: Option<bool>) {
+        *cli = *cli || cfg.unwrap_or(false);
+    }
+    fn opt<T>(cli: &mut Option<T>, cfg: Option<T>) {
+        if cli.is_none() {
+            *cli = cfg;
+        }
+    }
+
+    opt(&mut args.asn_db, config.asn_db);
+    flag(&mut args.show_asn, config.show_asn);
+    #[cfg(feature = "pcap-output")]
+    opt(&mut args.capture_file, config.capture_file);
+    opt(&mut args.tee, config.tee);
+    opt(&mut args.tee_max_mb, config.tee_max_mb);
+    flag(&mut args.syslog, config.syslog);
+    opt(&mut args.exec_on_connect, config.exec_on_connect);
+    opt(&mut args.exec_on_disconnect, config.exec_on_disconnect);
+    opt(&mut args.exec_on_startup, config.exec_on_startup);
+    opt(&mut args.exec_on_exit, config.exec_on_exit);
+    opt(&mut args.exec_timeout_ms, config.exec_timeout_ms);
+    flag(&mut args.no_timeline, config.no_timeline);
+    flag(&mut args.glob, config.glob);
+    flag(&mut args.fuzzy, config.fuzzy);
+    flag(&mut args.quiet, config.quiet);
+    flag(&mut args.once, config.once);
+    flag(&mut args.diff, config.diff);
+    if args.blacklist_file.is_empty() {
+        args.blacklist_file = config.blacklist_file.unwrap_or_default();
+    }
+    opt(&mut args.tags_file, config.tags_file);
+    opt(&mut args.max_history, config.max_history);
+    opt(&mut args.max_history_age, config.max_history_age);
+    opt(&mut args.min_age, config.min_age);
+    flag(&mut args.min_age_invert, config.min_age_invert);
+    opt(&mut args.snapshot_interval, config.snapshot_interval);
+    opt(&mut args.watch_count_file, config.watch_count_file);
+    opt(&mut args.rate_file, config.rate_file);
+    opt(&mut args.rate_file_format, config.rate_file_format);
+    opt(&mut args.remote_monitor, config.remote_monitor);
+    opt(&mut args.remote_interval, config.remote_interval);
+    opt(&mut args.log_file, config.log_file);
+    opt(&mut args.history_grep, config.history_grep);
+    flag(&mut args.show_unix_sockets, config.show_unix_sockets);
+    flag(&mut args.show_listening, config.show_listening);
+    opt(&mut args.alert_regex, config.alert_regex);
+    opt(&mut args.notify_rate_limit, config.notify_rate_limit);
+    flag(&mut args.lock, config.lock);
+    opt(&mut args.count, config.count);
+    opt(&mut args.timeout, config.timeout);
+    flag(&mut args.auto_layout, config.auto_layout);
+    opt(&mut args.top_n, config.top_n);
+    opt(&mut args.tail, config.tail);
+    flag(&mut args.no_port_colors, config.no_port_colors);
+    opt(&mut args.truncate_ip, config.truncate_ip);
+    opt(&mut args.columns, config.columns);
+    flag(&mut args.resolve, config.resolve);
+    opt(&mut args.report_after, config.report_after);
+    flag(&mut args.report_loop, config.report_loop);
+    opt(&mut args.min_port, config.min_port);
+    opt(&mut args.max_port, config.max_port);
+    flag(&mut args.exclude_self, config.exclude_self);
+    opt(&mut args.sample_rate, config.sample_rate);
+    opt(&mut args.input_file, config.input_file);
+    flag(&mut args.mock, config.mock);
+    opt(&mut args.mock_seed, config.mock_seed);
+    flag(&mut args.no_sudo, config.no_sudo);
+    flag(&mut args.no_history, config.no_history);
+    opt(&mut args.retry_on_error, config.retry_on_error);
+    opt(&mut args.lsof_timeout, config.lsof_timeout);
+    opt(&mut args.lsof_cmd, config.lsof_cmd);
+    opt(&mut args.lsof_args, config.lsof_args);
+    opt(&mut args.alert_on_failures, config.alert_on_failures);
+    opt(&mut args.column_layout, config.column_layout);
+    opt(&mut args.border_style, config.border_style);
+    opt(&mut args.ipv6_display, config.ipv6_display);
+    flag(&mut args.strip_ipv4_mapped, config.strip_ipv4_mapped);
+    flag(&mut args.mask_ips, config.mask_ips);
+    opt(&mut args.save_bookmarks, config.save_bookmarks);
+    opt(&mut args.all_time_record, config.all_time_record);
+    opt(&mut args.watch_pid_file, config.watch_pid_file);
+    opt(&mut args.pid_wait, config.pid_wait);
+    opt(&mut args.geoip_db, config.geoip_db);
+    opt(&mut args.color_new_connections, config.color_new_connections);
+    flag(&mut args.color_by_age, config.color_by_age);
+    flag(&mut args.color_seen, config.color_seen);
+    opt(&mut args.color_by_subnet, config.color_by_subnet);
+    opt(&mut args.color_palette, config.color_palette);
+    flag(&mut args.warn_private_to_public, config.warn_private_to_public);
+    opt(&mut args.linger_secs, config.linger_secs);
+    flag(&mut args.export_on_exit, config.export_on_exit);
+    opt(&mut args.export_path, config.export_path);
+    opt(&mut args.event_queue_depth, config.event_queue_depth);
+    opt(&mut args.watch_interval_jitter, config.watch_interval_jitter);
+    opt(&mut args.seed, config.seed);
+    flag(&mut args.show_fd_count, config.show_fd_count);
+    opt(&mut args.alert_fd_count, config.alert_fd_count);
+    flag(&mut args.interface_stats, config.interface_stats);
+    opt(&mut args.watch_connections_delta, config.watch_connections_delta);
+    opt(&mut args.format_history, config.format_history);
+    opt(&mut args.history_format, config.history_format);
+    opt(&mut args.format_status, config.format_status);
+    flag(&mut args.hide_status_bar, config.hide_status_bar);
+    opt(&mut args.replay, config.replay);
+    opt(&mut args.netns, config.netns);
+    opt(&mut args.container, config.container);
+    flag(&mut args.resolve_ports, config.resolve_ports);
+    if args.tab.is_empty() {
+        args.tab = config.tab.unwrap_or_default();
+    }
+    if args.exclude_process.is_empty() {
+        args.exclude_process = config.exclude_process.unwrap_or_default();
+    }
+    if args.allow_public_ip.is_empty() {
+        args.allow_public_ip = config.allow_public_ip.unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const SAMPLE: &str = r#"
+asn_db = "/usr/share/GeoIP/GeoLite2-ASN.mmdb"
+show_asn = true
+exec_timeout_ms = 500
+blacklist_file = ["/etc/netmonrs/blacklist.d"]
+tags_file = "/etc/netmonrs/tags.toml"
+"#;
+
+    #[test]
+    fn test_parse_sample_config() {
+        let config = parse_config(SAMPLE).unwrap();
+        assert_eq!(config.asn_db, Some(PathBuf::from("/usr/share/GeoIP/GeoLite2-ASN.mmdb")));
+        assert_eq!(config.show_asn, Some(true));
+        assert_eq!(config.exec_timeout_ms, Some(500));
+        assert_eq!(config.blacklist_file, Some(vec![PathBuf::from("/etc/netmonrs/blacklist.d")]));
+        assert_eq!(config.tags_file, Some(PathBuf::from("/etc/netmonrs/tags.toml")));
+        assert_eq!(config.syslog, None);
+    }
+
+    #[test]
+    fn test_parse_config_unknown_key() {
+        let err = parse_config("show_asm = true\n").unwrap_err();
+        assert!(err.contains("show_asm"));
+    }
+
+    #[test]
+    fn test_cli_takes_priority() {
+        let mut args = Args::parse_from(["netmonrs", "--exec-timeout-ms", "100", "--tags-file", "cli.toml", "nginx"]);
+        merge_into_args(&mut args, parse_config(SAMPLE).unwrap());
+
+        assert_eq!(args.exec_timeout_ms, Some(100));
+        assert_eq!(args.tags_file, Some(PathBuf::from("cli.toml")));
+        assert_eq!(args.asn_db, Some(PathBuf::from("/usr/share/GeoIP/GeoLite2-ASN.mmdb")));
+        assert!(args.show_asn);
+        assert_eq!(args.blacklist_file, vec![PathBuf::from("/etc/netmonrs/blacklist.d")]);
+    }
+
+    #[test]
+    fn test_merge_config_overlay_wins() {
+        let base = parse_config(SAMPLE).unwrap();
+        let overlay = parse_config("exec_timeout_ms = 250\nno_sudo = true\n").unwrap();
+        let merged = merge_config(base, overlay);
+        assert_eq!(merged.exec_timeout_ms, Some(250));
+        assert_eq!(merged.no_sudo, Some(true));
+        assert_eq!(merged.show_asn, Some(true));
+        assert_eq!(merged.tags_file, Some(PathBuf::from("/etc/netmonrs/tags.toml")));
+    }
+
+    #[test]
+    fn test_profile_precedence() {
+        let mut args = Args::parse_from(["netmonrs", "--exec-timeout-ms", "100", "nginx"]);
+        let profile = parse_config("exec_timeout_ms = 250\nsample_rate = 5\n").unwrap();
+        merge_into_args(&mut args, merge_config(parse_config(SAMPLE).unwrap(), profile));
+        assert_eq!(args.exec_timeout_ms, Some(100));
+        assert_eq!(args.sample_rate, Some(5));
+        assert!(args.show_asn);
+    }
+
+    #[test]
+    fn test_config_from_args_round_trip() {
+        let args = Args::parse_from(["netmonrs", "--no-sudo", "--top-n", "5", "--tab", "redis", "--profile", "dev", "nginx"]);
+        let config = config_from_args(&args).unwrap();
+        assert_eq!(config.no_sudo, Some(true));
+        assert_eq!(config.top_n, Some(5));
+        assert_eq!(config.tab, Some(vec![String::from("redis")]));
+        assert_eq!(config.show_asn, None);
+        assert_eq!(config.blacklist_file, None);
+
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(parse_config(&content).unwrap().top_n, Some(5));
+    }
+
+    #[test]
+    fn test_profile_name_validation() {
+        assert!(profile_path("../etc").is_err());
+        assert!(profile_path("").is_err());
+    }
+
+    #[test]
+    fn test_load_config_missing_file() {
+        assert!(load_config(Path::new("/nonexistent/netmonrs/config.toml")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes `count` to a sibling temp file and renames it over `path`, so
+/// readers never see a partially written number.
+pub fn atomic_write_count(path: &Path, count: usize) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    {
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(format!("{}\n", count).as_bytes())?;
+        f.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Removes the `--watch-count-file` when the app goes away.
+#[derive(Debug)]
+pub struct CleanupGuard {
+    path: PathBuf,
+}
+
+impl CleanupGuard {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("netmonrs-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_atomic_write_count_replaces_file() {
+        let path = test_path("count");
+        let link = test_path("count-link");
+        let _ = fs::remove_file(&link);
+
+        atomic_write_count(&path, 12).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12\n");
+
+        // A rename swaps in a new inode; writing in place would change the linked file too.
+        fs::hard_link(&path, &link).unwrap();
+        atomic_write_count(&path, 7).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "7\n");
+        assert_eq!(fs::read_to_string(&link).unwrap(), "12\n");
+        assert!(!tmp_path(&path).exists());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&link).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_guard_removes_file() {
+        let path = test_path("count-guard");
+        atomic_write_count(&path, 1).unwrap();
+        let guard = CleanupGuard::new(path.clone());
+        assert!(path.exists());
+        drop(guard);
+        assert!(!path.exists());
+    }
+}
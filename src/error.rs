@@ -0,0 +1,97 @@
+use std::{fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum MonitorError {
+    PgrepFailed(io::Error),
+    PgrepNoPid(String),
+    /// The process didn't appear within `--pid-wait`.
+    ProcessWaitTimeout(String),
+    PidFileMissing(PathBuf),
+    LsofFailed(io::Error),
+    ParseError { line: String, reason: String },
+    /// The monitor thread panicked and is about to start over.
+    MonitorPanicked,
+}
+
+impl MonitorError {
+    /// Whether the previous active connection list is no longer meaningful.
+    pub fn clears_active(&self) -> bool {
+        matches!(self, MonitorError::PgrepNoPid(_) | MonitorError::PidFileMissing(_) | MonitorError::LsofFailed(_))
+    }
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorError::PgrepFailed(e) => write!(f, "PGREP Error: {}", e),
+            MonitorError::PgrepNoPid(target) => write!(f, "Waiting for process '{}'...", target),
+            MonitorError::ProcessWaitTimeout(target) => write!(f, "Timed out waiting for process '{}'", target),
+            MonitorError::PidFileMissing(path) => write!(f, "PID file not found: {}", path.display()),
+            MonitorError::LsofFailed(e) => write!(f, "LSOF Error: {}", e),
+            MonitorError::ParseError { line, reason } => write!(f, "Parse Error: {} in '{}'", reason, line),
+            MonitorError::MonitorPanicked => write!(f, "Monitor thread panicked, restarting"),
+        }
+    }
+}
+
+impl std::error::Error for MonitorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MonitorError::PgrepFailed(e) | MonitorError::LsofFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_pgrep_failed() {
+        let err = MonitorError::PgrepFailed(io::Error::new(io::ErrorKind::NotFound, "pgrep not found"));
+        assert_eq!(err.to_string(), "PGREP Error: pgrep not found");
+        assert!(!err.clears_active());
+    }
+
+    #[test]
+    fn test_display_pgrep_no_pid() {
+        let err = MonitorError::PgrepNoPid(String::from("nginx"));
+        assert_eq!(err.to_string(), "Waiting for process 'nginx'...");
+        assert!(err.clears_active());
+    }
+
+    #[test]
+    fn test_display_process_wait_timeout() {
+        let err = MonitorError::ProcessWaitTimeout(String::from("nginx"));
+        assert_eq!(err.to_string(), "Timed out waiting for process 'nginx'");
+        assert!(!err.clears_active());
+    }
+
+    #[test]
+    fn test_display_pid_file_missing() {
+        let err = MonitorError::PidFileMissing(PathBuf::from("/var/run/nginx.pid"));
+        assert_eq!(err.to_string(), "PID file not found: /var/run/nginx.pid");
+        assert!(err.clears_active());
+    }
+
+    #[test]
+    fn test_display_lsof_failed() {
+        let err = MonitorError::LsofFailed(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert_eq!(err.to_string(), "LSOF Error: denied");
+        assert!(err.clears_active());
+    }
+
+    #[test]
+    fn test_display_parse_error() {
+        let err = MonitorError::ParseError { line: String::from("x 1 TCP a->b"), reason: String::from("invalid address") };
+        assert_eq!(err.to_string(), "Parse Error: invalid address in 'x 1 TCP a->b'");
+        assert!(!err.clears_active());
+    }
+
+    #[test]
+    fn test_display_monitor_panicked() {
+        assert_eq!(MonitorError::MonitorPanicked.to_string(), "Monitor thread panicked, restarting");
+        assert!(!MonitorError::MonitorPanicked.clears_active());
+    }
+}
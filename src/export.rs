@@ -0,0 +1,56 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::HistoryEntry;
+
+pub fn export_filename(now: DateTime<Local>) -> String {
+    format!("netmonrs_export_{}.csv", now.format("%Y%m%d_%H%M%S"))
+}
+
+/// Writes the history as `timestamp,entry` rows and returns the file path.
+pub fn export_to_csv(dir: &Path, history: &[HistoryEntry], now: DateTime<Local>) -> io::Result<PathBuf> {
+    let path = dir.join(export_filename(now));
+    let mut writer = csv::Writer::from_path(&path)?;
+    writer.write_record(["timestamp", "entry"])?;
+    for (ts, entry) in history {
+        writer.write_record([ts.to_rfc3339().as_str(), entry.as_str()])?;
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::fs;
+
+    #[test]
+    fn test_export_filename() {
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(export_filename(now), "netmonrs_export_20240101_120000.csv");
+    }
+
+    #[test]
+    fn test_export_to_csv_quotes_entries() {
+        let dir = std::env::temp_dir().join(format!("netmonrs-export-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let history = vec![
+            (now, String::from("93.184.216.34")),
+            (now, String::from("10.0.0.1 (db, \"primary\")")),
+        ];
+
+        let path = export_to_csv(&dir, &history, now).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[1][1], "10.0.0.1 (db, \"primary\")");
+        assert_eq!(&rows[0][0], now.to_rfc3339());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
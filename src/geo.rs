@@ -0,0 +1,85 @@
+use std::{collections::HashMap, net::IpAddr, path::Path};
+
+use maxminddb::{geoip2, MaxMindDbError, Mmap, Reader};
+
+use crate::ConnectionEntry;
+
+pub const UNKNOWN_COUNTRY: &str = "🌐 Unknown";
+
+#[derive(Debug)]
+pub struct GeoGroup<'a> {
+    pub country: String,
+    pub connections: Vec<&'a ConnectionEntry>,
+}
+
+pub fn open_geoip_db(path: &Path) -> Result<Reader<Mmap>, MaxMindDbError> {
+    // SAFETY: the map is only sound while the file stays as it is. The GeoIP
+    // database must not be modified or truncated while netmonrs runs; update
+    // it by writing a new file and renaming it over the old one.
+    unsafe { Reader::open_mmap(path) }
+}
+
+/// Returns the country as a display label, e.g. `🇺🇸 United States`.
+pub fn lookup_country(reader: &Reader<Mmap>, ip: IpAddr) -> Option<String> {
+    let result = reader.lookup(ip).ok()?;
+    let record: geoip2::Country = result.decode().ok()??;
+    let name = record.country.names.english?;
+    Some(match record.country.iso_code.and_then(flag_emoji) {
+        Some(flag) => format!("{} {}", flag, name),
+        None => name.to_string(),
+    })
+}
+
+fn flag_emoji(iso_code: &str) -> Option<String> {
+    if iso_code.len() != 2 || !iso_code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    iso_code.to_ascii_uppercase().chars()
+        .map(|c| char::from_u32(0x1F1E6 + (c as u32 - 'A' as u32)))
+        .collect()
+}
+
+pub fn group_by_country<'a>(connections: &'a [ConnectionEntry], geo_cache: &HashMap<IpAddr, String>) -> Vec<GeoGroup<'a>> {
+    let mut groups: Vec<GeoGroup> = Vec::new();
+    for conn in connections {
+        let country = geo_cache.get(&conn.ip).map(String::as_str).unwrap_or(UNKNOWN_COUNTRY);
+        match groups.iter_mut().find(|g| g.country == country) {
+            Some(group) => group.connections.push(conn),
+            None => groups.push(GeoGroup { country: country.to_string(), connections: vec![conn] }),
+        }
+    }
+    groups.sort_by(|a, b| b.connections.len().cmp(&a.connections.len()).then_with(|| a.country.cmp(&b.country)));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_conn;
+
+    #[test]
+    fn test_flag_emoji() {
+        assert_eq!(flag_emoji("US").as_deref(), Some("🇺🇸"));
+        assert_eq!(flag_emoji("de").as_deref(), Some("🇩🇪"));
+        assert_eq!(flag_emoji("USA"), None);
+        assert_eq!(flag_emoji("1A"), None);
+    }
+
+    #[test]
+    fn test_group_by_country() {
+        let conns = vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443), test_conn("3.3.3.3", 443), test_conn("4.4.4.4", 443)];
+        let mut geo = HashMap::new();
+        geo.insert("1.1.1.1".parse().unwrap(), String::from("🇩🇪 Germany"));
+        geo.insert("2.2.2.2".parse().unwrap(), String::from("🇺🇸 United States"));
+        geo.insert("3.3.3.3".parse().unwrap(), String::from("🇺🇸 United States"));
+
+        let groups = group_by_country(&conns, &geo);
+        let summary: Vec<(&str, usize)> = groups.iter().map(|g| (g.country.as_str(), g.connections.len())).collect();
+        assert_eq!(summary, vec![("🇺🇸 United States", 2), ("🇩🇪 Germany", 1), (UNKNOWN_COUNTRY, 1)]);
+    }
+
+    #[test]
+    fn test_open_geoip_db_missing_file() {
+        assert!(open_geoip_db(Path::new("/nonexistent/GeoLite2-Country.mmdb")).is_err());
+    }
+}
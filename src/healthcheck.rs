@@ -0,0 +1,150 @@
+use std::{
+    fmt, fs,
+    net::{IpAddr, Ipv4Addr},
+    process::{Command, Stdio},
+};
+
+use crate::error::MonitorError;
+
+/// One line of the `netmonrs check` checklist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), ok: true, detail: detail.into() }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), ok: false, detail: detail.into() }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", if self.ok { "✓" } else { "✗" }, self.name, self.detail)
+    }
+}
+
+pub fn check_pgrep() -> CheckResult {
+    check_command("pgrep available", "pgrep", &["-V"])
+}
+
+pub fn check_lsof() -> CheckResult {
+    check_command("lsof available", "lsof", &["-v"])
+}
+
+/// `find` is the monitor's own process lookup, so `check` and the TUI agree
+/// on what the target matches.
+pub fn check_process(target: &str, find: impl FnOnce() -> Result<Vec<(String, String)>, MonitorError>) -> CheckResult {
+    let name = format!("process '{}' running", target);
+    match find() {
+        Ok(pids) if pids.is_empty() => CheckResult::fail(name, "no matching process"),
+        Ok(pids) => {
+            let pids: Vec<&str> = pids.iter().map(|(pid, _)| pid.as_str()).collect();
+            CheckResult::pass(name, format!("PID {}", pids.join(", ")))
+        }
+        Err(e) => CheckResult::fail(name, e.to_string()),
+    }
+}
+
+/// Connections can be read when `sudo lsof` works without a password
+/// prompt, or when `/proc/net/tcp` is readable for `--no-sudo`.
+pub fn check_connection_access() -> CheckResult {
+    const NAME: &str = "connection access";
+    let sudo = Command::new("sudo")
+        .args(["-n", "lsof", "-v"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if sudo.is_ok_and(|s| s.success()) {
+        return CheckResult::pass(NAME, "sudo lsof works");
+    }
+    match fs::read_to_string("/proc/net/tcp") {
+        Ok(_) => CheckResult::pass(NAME, "sudo lsof failed, but /proc/net/tcp is readable (use --no-sudo)"),
+        Err(e) => CheckResult::fail(NAME, format!("sudo lsof failed and /proc/net/tcp is unreadable: {}", e)),
+    }
+}
+
+/// Reverse-resolves the loopback address, the same kind of lookup the
+/// hostname column uses.
+pub fn check_dns() -> CheckResult {
+    const NAME: &str = "DNS resolution";
+    match dns_lookup::lookup_addr(&IpAddr::V4(Ipv4Addr::LOCALHOST)) {
+        Ok(host) => CheckResult::pass(NAME, format!("127.0.0.1 resolves to {}", host)),
+        Err(e) => CheckResult::fail(NAME, e.to_string()),
+    }
+}
+
+pub fn run_checks(target: &str, find: impl FnOnce() -> Result<Vec<(String, String)>, MonitorError>) -> Vec<CheckResult> {
+    vec![check_pgrep(), check_lsof(), check_process(target, find), check_connection_access(), check_dns()]
+}
+
+/// Prints the checklist and returns the exit code: 0 if everything passed.
+pub fn run(target: &str, find: impl FnOnce() -> Result<Vec<(String, String)>, MonitorError>) -> i32 {
+    let results = run_checks(target, find);
+    for result in &results {
+        println!("{}", result);
+    }
+    exit_code(&results)
+}
+
+fn exit_code(results: &[CheckResult]) -> i32 {
+    if results.iter().all(|r| r.ok) { 0 } else { 1 }
+}
+
+fn check_command(name: &str, program: &str, args: &[&str]) -> CheckResult {
+    match Command::new(program).args(args).stdin(Stdio::null()).output() {
+        Ok(out) if out.status.success() => {
+            // lsof prints its version banner on stderr, with the number on a later line.
+            let text = if out.stdout.is_empty() { &out.stderr } else { &out.stdout };
+            let version = String::from_utf8_lossy(text)
+                .lines()
+                .map(str::trim)
+                .find(|l| l.chars().any(|c| c.is_ascii_digit()))
+                .unwrap_or(program)
+                .to_string();
+            CheckResult::pass(name, version)
+        }
+        Ok(out) => CheckResult::fail(name, format!("{} exited with {}", program, out.status)),
+        Err(e) => CheckResult::fail(name, format!("{}: {}", program, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_result_display() {
+        assert_eq!(CheckResult::pass("lsof available", "lsof 4.95").to_string(), "✓ lsof available: lsof 4.95");
+        assert_eq!(CheckResult::fail("DNS resolution", "timed out").to_string(), "✗ DNS resolution: timed out");
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let pass = CheckResult::pass("a", "");
+        assert_eq!(exit_code(&[pass.clone(), pass.clone()]), 0);
+        assert_eq!(exit_code(&[pass, CheckResult::fail("b", "")]), 1);
+    }
+
+    #[test]
+    fn test_check_process() {
+        let found = check_process("nginx.*worker", || Ok(vec![(String::from("12"), String::from("nginx: worker process"))]));
+        assert_eq!(found.to_string(), "✓ process 'nginx.*worker' running: PID 12");
+        let missing = check_process("nginx", || Ok(Vec::new()));
+        assert_eq!(missing.to_string(), "✗ process 'nginx' running: no matching process");
+    }
+
+    #[test]
+    fn test_check_command_missing_program() {
+        let result = check_command("x available", "netmonrs-no-such-program", &[]);
+        assert!(!result.ok);
+        assert!(result.detail.starts_with("netmonrs-no-such-program: "));
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    io::{self, Read},
+    process::{Child, Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+/// How long a timed-out command gets to exit after SIGTERM before SIGKILL.
+const KILL_GRACE: Duration = Duration::from_millis(200);
+
+pub fn render_command(template: &str, ip: &str, port: u16, proto: &str) -> String {
+    template
+        .replace("{ip}", ip)
+        .replace("{port}", &port.to_string())
+        .replace("{proto}", proto)
+}
+
+/// Fills in `--exec-on-startup` and `--exec-on-exit` templates.
+pub fn render_lifecycle_command(template: &str, target: &str, pid: &str) -> String {
+    template
+        .replace("{target}", target)
+        .replace("{pid}", pid)
+}
+
+/// Runs the `--exec-on-exit` command when dropped, so it also fires when
+/// the UI exits with an error. The second field is `--exec-timeout-ms`.
+pub struct ExitHook(pub Option<String>, pub Option<Duration>);
+
+impl Drop for ExitHook {
+    fn drop(&mut self) {
+        if let Some(cmd) = self.0.take()
+            && let Err(e) = run_hook(&cmd, self.1) {
+            eprintln!("--exec-on-exit failed: {}", e);
+        }
+    }
+}
+
+pub fn run_hook(cmd: &str, timeout: Option<Duration>) -> io::Result<Output> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    run_with_timeout(command, timeout)
+}
+
+pub fn spawn_hook(cmd: String, timeout: Option<Duration>) {
+    thread::spawn(move || {
+        let _ = run_hook(&cmd, timeout);
+    });
+}
+
+pub fn run_with_timeout(mut cmd: Command, timeout: Option<Duration>) -> io::Result<Output> {
+    // Its own process group, so a timeout also reaches whatever the command
+    // started, e.g. the lsof behind `sudo`.
+    #[cfg(unix)]
+    if timeout.is_some() {
+        cmd.process_group(0);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let Some(timeout) = timeout else { return child.wait_with_output() };
+
+    // Drain the pipes while waiting, or a child with a lot of output blocks
+    // on a full pipe and looks hung.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            kill_tree(&mut child);
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "command timed out"));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// SIGTERM first: sudo can't relay SIGKILL to the root-owned command it
+/// runs, but it does relay SIGTERM. Whatever is left gets SIGKILL.
+#[cfg(unix)]
+fn kill_tree(child: &mut Child) {
+    let pgid = -(child.id() as libc::pid_t);
+    // SAFETY: kill() only sends a signal; the group is the one created for this child.
+    unsafe { libc::kill(pgid, libc::SIGTERM) };
+    let deadline = Instant::now() + KILL_GRACE;
+    while Instant::now() < deadline && matches!(child.try_wait(), Ok(None)) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    // SAFETY: as above. Grandchildren may outlive the direct child, so the
+    // group is killed even if it has exited.
+    unsafe { libc::kill(pgid, libc::SIGKILL) };
+    let _ = child.wait();
+}
+
+#[cfg(not(unix))]
+fn kill_tree(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_command_placeholders() {
+        let cmd = render_command("notify {proto} {ip}:{port} {ip}", "93.184.216.34", 443, "TCP");
+        assert_eq!(cmd, "notify TCP 93.184.216.34:443 93.184.216.34");
+    }
+
+    #[test]
+    fn test_render_lifecycle_command() {
+        let cmd = render_lifecycle_command("notify {target} started as {pid} ({target})", "nginx", "1234,5678");
+        assert_eq!(cmd, "notify nginx started as 1234,5678 (nginx)");
+        assert_eq!(render_lifecycle_command("echo {pid}", "nginx", ""), "echo ");
+    }
+
+    #[test]
+    fn test_exit_hook_runs_on_drop() {
+        let path = std::env::temp_dir().join(format!("netmonrs-exit-hook-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let hook = ExitHook(Some(format!("touch {}", path.display())), None);
+        assert!(!path.exists());
+        drop(hook);
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        drop(ExitHook(None, None));
+    }
+
+    #[test]
+    fn test_run_hook_echo_ip() {
+        let cmd = render_command("echo {ip}", "10.0.0.1", 80, "TCP");
+        let out = run_hook(&cmd, Some(Duration::from_secs(5))).unwrap();
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "10.0.0.1\n");
+    }
+
+    #[test]
+    fn test_run_with_timeout_large_output() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("head -c 1000000 /dev/zero");
+        let out = run_with_timeout(cmd, Some(Duration::from_secs(5))).unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout.len(), 1_000_000);
+    }
+
+    #[test]
+    fn test_run_hook_timeout_kills_process() {
+        let start = Instant::now();
+        let err = run_hook("sleep 10", Some(Duration::from_millis(100))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Gone or a zombie waiting to be reaped by init.
+    #[cfg(target_os = "linux")]
+    fn process_gone(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map_or(true, |stat| stat.rsplit(')').next().is_some_and(|rest| rest.trim_start().starts_with('Z')))
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_run_hook_timeout_kills_grandchild() {
+        let path = std::env::temp_dir().join(format!("netmonrs-grandchild-{}", std::process::id()));
+        let cmd = format!("sleep 30 & echo $! > {}; wait", path.display());
+        let start = Instant::now();
+        let err = run_hook(&cmd, Some(Duration::from_millis(300))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let pid = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !process_gone(pid.trim()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(process_gone(pid.trim()), "grandchild {} is still running", pid.trim());
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    io,
+    time::Instant,
+};
+
+/// Cumulative byte counters of one network interface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceRate {
+    pub name: String,
+    pub rx_per_sec: f64,
+    pub tx_per_sec: f64,
+}
+
+/// Parses `/proc/net/dev`: two header lines, then `name: rx_bytes ... tx_bytes ...`
+/// where tx_bytes is the ninth counter.
+pub fn parse_proc_net_dev(content: &str) -> HashMap<String, InterfaceStats> {
+    content.lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let counters: Vec<u64> = counters.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()?;
+            let stats = InterfaceStats { rx_bytes: *counters.first()?, tx_bytes: *counters.get(8)? };
+            Some((name.trim().to_string(), stats))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_proc_net_dev() -> io::Result<HashMap<String, InterfaceStats>> {
+    Ok(parse_proc_net_dev(&std::fs::read_to_string("/proc/net/dev")?))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_proc_net_dev() -> io::Result<HashMap<String, InterfaceStats>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Turns successive `/proc/net/dev` readings into per-second rates.
+#[derive(Debug, Default)]
+pub struct InterfaceTracker {
+    prev: Option<(Instant, HashMap<String, InterfaceStats>)>,
+}
+
+impl InterfaceTracker {
+    /// Rates since the previous reading, sorted by interface name. The first
+    /// reading has nothing to diff against and yields none; interfaces that
+    /// just appeared are skipped, and counters that went backwards count as 0.
+    pub fn update(&mut self, now: Instant, current: HashMap<String, InterfaceStats>) -> Vec<InterfaceRate> {
+        let mut rates = Vec::new();
+        if let Some((then, prev)) = &self.prev {
+            let elapsed = now.duration_since(*then).as_secs_f64();
+            if elapsed > 0.0 {
+                for (name, stats) in &current {
+                    let Some(old) = prev.get(name) else { continue };
+                    rates.push(InterfaceRate {
+                        name: name.clone(),
+                        rx_per_sec: stats.rx_bytes.saturating_sub(old.rx_bytes) as f64 / elapsed,
+                        tx_per_sec: stats.tx_bytes.saturating_sub(old.tx_bytes) as f64 / elapsed,
+                    });
+                }
+            }
+        }
+        rates.sort_by(|a, b| a.name.cmp(&b.name));
+        self.prev = Some((now, current));
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const PROC_NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     789    0    0    0     0          0         0   123456     789    0    0    0     0       0          0
+  eth0: 98765432  65432    0    2    0     0          0        10 12345678   23456    0    0    0     0       0          0
+wlp2s0:       0       0    0    0    0     0          0         0        0       0    0    0    0     0       0          0
+";
+
+    #[test]
+    fn test_parse_proc_net_dev() {
+        let stats = parse_proc_net_dev(PROC_NET_DEV);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats["lo"], InterfaceStats { rx_bytes: 123456, tx_bytes: 123456 });
+        assert_eq!(stats["eth0"], InterfaceStats { rx_bytes: 98765432, tx_bytes: 12345678 });
+        assert_eq!(stats["wlp2s0"], InterfaceStats::default());
+    }
+
+    #[test]
+    fn test_parse_proc_net_dev_skips_malformed_lines() {
+        let content = "header\nheader\n  eth0: 1 2 3\n  eth1: 10 0 0 0 0 0 0 0 20 0 0 0 0 0 0 0\ngarbage\n";
+        let stats = parse_proc_net_dev(content);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats["eth1"], InterfaceStats { rx_bytes: 10, tx_bytes: 20 });
+    }
+
+    #[test]
+    fn test_interface_tracker_rates() {
+        let reading = |rx, tx| HashMap::from([(String::from("eth0"), InterfaceStats { rx_bytes: rx, tx_bytes: tx })]);
+        let start = Instant::now();
+        let mut tracker = InterfaceTracker::default();
+        assert!(tracker.update(start, reading(1000, 500)).is_empty());
+
+        let rates = tracker.update(start + Duration::from_secs(2), reading(5000, 1500));
+        assert_eq!(rates, vec![InterfaceRate { name: String::from("eth0"), rx_per_sec: 2000.0, tx_per_sec: 500.0 }]);
+
+        let rates = tracker.update(start + Duration::from_secs(3), reading(0, 0));
+        assert_eq!((rates[0].rx_per_sec, rates[0].tx_per_sec), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The host's own addresses, re-detected every `REFRESH_INTERVAL` so DHCP
+/// renewals and interface changes are picked up.
+#[derive(Debug)]
+pub struct LocalIps {
+    ips: HashSet<IpAddr>,
+    refreshed: Instant,
+}
+
+impl LocalIps {
+    pub fn detect() -> Self {
+        Self { ips: detect_local_ips(), refreshed: Instant::now() }
+    }
+
+    pub fn refresh_if_stale(&mut self, now: Instant) {
+        if now.duration_since(self.refreshed) >= REFRESH_INTERVAL {
+            self.ips = detect_local_ips();
+            self.refreshed = now;
+        }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        ip.is_loopback() || self.ips.contains(&ip)
+    }
+}
+
+/// Whether `ip` is routable on the public internet, like the unstable
+/// `IpAddr::is_global`. IPv4-mapped IPv6 addresses are judged as IPv4.
+pub fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_global_v4(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_global_v4(v4),
+            None => is_global_v6(v6),
+        },
+    }
+}
+
+fn is_global_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || a == 0
+        || a >= 224
+        // Shared address space (carrier-grade NAT).
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking.
+        || (a == 198 && (b == 18 || b == 19)))
+}
+
+fn is_global_v6(ip: Ipv6Addr) -> bool {
+    let [first, second, ..] = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10).
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        // Documentation (2001:db8::/32).
+        || (first == 0x2001 && second == 0x0db8))
+}
+
+fn detect_local_ips() -> HashSet<IpAddr> {
+    let mut ips: HashSet<IpAddr> = Command::new("ip")
+        .args(["-o", "addr"])
+        .output()
+        .map(|out| parse_ip_addr(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or_default();
+    // No packets are sent; connecting only picks the outgoing interface.
+    for (bind, remote) in [("0.0.0.0:0", "8.8.8.8:80"), ("[::]:0", "[2001:4860:4860::8888]:80")] {
+        if let Ok(socket) = UdpSocket::bind(bind)
+            && socket.connect(remote).is_ok()
+            && let Ok(addr) = socket.local_addr() {
+            ips.insert(addr.ip());
+        }
+    }
+    ips
+}
+
+/// Parses `ip -o addr` lines like `2: eth0    inet 10.0.0.5/24 brd ...`.
+fn parse_ip_addr(output: &str) -> HashSet<IpAddr> {
+    output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            fields.find(|f| *f == "inet" || *f == "inet6")?;
+            let addr = fields.next()?.split('/').next()?;
+            addr.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip_addr() {
+        let out = "\
+1: lo    inet 127.0.0.1/8 scope host lo\\       valid_lft forever preferred_lft forever
+1: lo    inet6 ::1/128 scope host \\       valid_lft forever preferred_lft forever
+2: eth0    inet 10.0.0.5/24 brd 10.0.0.255 scope global eth0\\       valid_lft forever preferred_lft forever
+2: eth0    inet6 fe80::1/64 scope link \\       valid_lft forever preferred_lft forever
+";
+        let ips = parse_ip_addr(out);
+        assert_eq!(ips.len(), 4);
+        assert!(ips.contains(&"10.0.0.5".parse().unwrap()));
+        assert!(ips.contains(&"fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_is_global_v4() {
+        assert!(is_global("93.184.216.34".parse().unwrap()));
+        assert!(is_global("8.8.8.8".parse().unwrap()));
+        for ip in ["10.1.2.3", "172.16.0.1", "192.168.1.1", "127.0.0.1", "169.254.1.1", "100.64.0.1", "192.0.2.1", "198.18.0.1", "0.0.0.0", "239.0.0.1", "255.255.255.255"] {
+            assert!(!is_global(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_is_global_v6() {
+        assert!(is_global("2606:4700::1111".parse().unwrap()));
+        assert!(is_global("::ffff:93.184.216.34".parse().unwrap()));
+        for ip in ["::1", "::", "fd00::1", "fe80::1", "ff02::1", "2001:db8::1", "::ffff:10.0.0.1"] {
+            assert!(!is_global(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_contains_loopback_and_detected() {
+        let local = LocalIps { ips: HashSet::from(["10.0.0.5".parse().unwrap()]), refreshed: Instant::now() };
+        assert!(local.contains("127.0.0.2".parse().unwrap()));
+        assert!(local.contains("::1".parse().unwrap()));
+        assert!(local.contains("10.0.0.5".parse().unwrap()));
+        assert!(!local.contains("93.184.216.34".parse().unwrap()));
+    }
+}
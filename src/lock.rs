@@ -0,0 +1,64 @@
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use crate::snapshot::sanitize_target;
+
+pub struct InstanceLock {
+    file: File,
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+pub fn lock_path(target: &str) -> PathBuf {
+    let name = format!("netmonrs-{}.lock", sanitize_target(target));
+    match env::var_os("XDG_RUNTIME_DIR").filter(|d| cfg!(target_os = "linux") && !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join(name),
+        None => PathBuf::from("/tmp").join(name),
+    }
+}
+
+/// Returns `Ok(None)` when another process already holds the lock.
+pub fn acquire(path: &Path) -> io::Result<Option<InstanceLock>> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    let rc = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if rc == 0 {
+        return Ok(Some(InstanceLock { file }));
+    }
+
+    let err = io::Error::last_os_error();
+    if err.kind() == io::ErrorKind::WouldBlock {
+        Ok(None)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let path = env::temp_dir().join(format!("netmonrs-lock-test-{}.lock", std::process::id()));
+
+        let first = acquire(&path).unwrap();
+        assert!(first.is_some());
+        assert!(acquire(&path).unwrap().is_none());
+
+        drop(first);
+        assert!(acquire(&path).unwrap().is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Local};
+use regex::Regex;
+
+use crate::HistoryEntry;
+
+pub fn format_log_line((ts, entry): &HistoryEntry) -> String {
+    format!("{} {}", ts.to_rfc3339(), entry)
+}
+
+pub fn parse_log_line(line: &str) -> Option<HistoryEntry> {
+    let (ts, entry) = line.trim_end().split_once(' ')?;
+    let ts = DateTime::parse_from_rfc3339(ts).ok()?.with_timezone(&Local);
+    Some((ts, entry.to_string()))
+}
+
+pub fn load_history(path: &Path, grep: Option<&Regex>) -> io::Result<Vec<HistoryEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content.lines()
+        .filter(|line| grep.is_none_or(|re| re.is_match(line)))
+        .filter_map(parse_log_line)
+        .collect())
+}
+
+pub fn open_log(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+pub fn append_entries(file: &mut File, entries: &[HistoryEntry]) -> io::Result<()> {
+    for entry in entries {
+        writeln!(file, "{}", format_log_line(entry))?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_line_round_trip() {
+        let entry = (Local::now(), String::from("93.184.216.34 (example.com CDN)"));
+        let parsed = parse_log_line(&format_log_line(&entry)).unwrap();
+        assert_eq!(parsed.0.timestamp(), entry.0.timestamp());
+        assert_eq!(parsed.1, entry.1);
+    }
+
+    #[test]
+    fn test_load_history_with_grep() {
+        let path = std::env::temp_dir().join(format!("netmonrs-log-test-{}.log", std::process::id()));
+        let mut file = open_log(&path).unwrap();
+        let now = Local::now();
+        append_entries(&mut file, &[
+            (now, String::from("8.8.8.8")),
+            (now, String::from("1.1.1.1")),
+            (now, String::from("8.8.4.4")),
+        ]).unwrap();
+        writeln!(file, "garbage line").unwrap();
+
+        let all = load_history(&path, None).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let re = Regex::new(r"8\.8\.").unwrap();
+        let filtered = load_history(&path, Some(&re)).unwrap();
+        let ips: Vec<&str> = filtered.iter().map(|(_, ip)| ip.as_str()).collect();
+        assert_eq!(ips, vec!["8.8.8.8", "8.8.4.4"]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_history_missing_file() {
+        assert!(load_history(Path::new("/nonexistent/netmonrs.log"), None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use syslog::{Facility, Formatter3164, Logger, LoggerBackend};
+
+pub struct EventLogger {
+    /// Why syslog couldn't be opened, if it couldn't.
+    backend: Result<Logger<LoggerBackend, Formatter3164>, String>,
+    /// Whether messages syslog didn't take may go to stderr instead.
+    stderr: bool,
+}
+
+impl EventLogger {
+    pub fn init(stderr: bool) -> Self {
+        let formatter = Formatter3164 {
+            facility: Facility::LOG_DAEMON,
+            hostname: None,
+            process: String::from("netmonrs"),
+            pid: std::process::id(),
+        };
+
+        let backend = syslog::unix(formatter).map_err(|e| format!("Syslog unavailable ({})", e));
+        if stderr && let Err(e) = &backend {
+            eprintln!("{}, logging to stderr", e);
+        }
+        Self { backend, stderr }
+    }
+
+    /// Fails with the reason when syslog didn't take the message and it
+    /// couldn't go to stderr either, so the caller can show it.
+    pub fn new_connection(&mut self, remote: &str, target: &str, pid: &str, blacklisted: bool) -> Result<(), String> {
+        let msg = new_connection_message(remote, target, pid);
+        let res = match &mut self.backend {
+            Ok(logger) => (if blacklisted { logger.warning(&msg) } else { logger.info(&msg) })
+                .map_err(|e| format!("Syslog write failed ({})", e)),
+            Err(e) => Err(e.clone()),
+        };
+        match res {
+            Err(_) if self.stderr => {
+                eprintln!("{}", msg);
+                Ok(())
+            }
+            res => res,
+        }
+    }
+}
+
+pub fn new_connection_message(remote: &str, target: &str, pid: &str) -> String {
+    format!("new connection from {} to process {} (pid {})", remote, target, pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_connection_message() {
+        assert_eq!(
+            new_connection_message("93.184.216.34:443", "nginx", "5678"),
+            "new connection from 93.184.216.34:443 to process nginx (pid 5678)"
+        );
+    }
+
+    #[test]
+    fn test_failure_returned_without_stderr() {
+        let mut logger = EventLogger { backend: Err(String::from("Syslog unavailable (no socket)")), stderr: false };
+        assert_eq!(logger.new_connection("93.184.216.34:443", "nginx", "5678", false), Err(String::from("Syslog unavailable (no socket)")));
+
+        logger.stderr = true;
+        assert_eq!(logger.new_connection("93.184.216.34:443", "nginx", "5678", false), Ok(()));
+    }
+}
@@ -1,424 +1,6843 @@
+mod asn;
+mod blacklist;
+mod bookmarks;
+mod clipboard;
+mod collector;
+mod error;
+mod export;
+mod geo;
+mod config;
+mod countfile;
+mod healthcheck;
+mod hooks;
+mod ifstats;
+mod localips;
+#[cfg(unix)]
+mod lock;
+mod logfile;
+mod logging;
+mod mask;
+mod mock;
+#[cfg(any(target_os = "windows", test))]
+mod netstat;
+mod netns;
+mod parser;
+mod snapshot;
+mod tags;
+mod tee;
+mod template;
+#[cfg(feature = "pcap-output")]
+mod pcap;
+mod process;
+mod queue;
+mod ratefile;
+mod ratelimit;
+mod record;
+mod report;
+mod resolver;
+mod services;
+
 use std::{
-    collections::HashSet,
-    io::{self, Stdout},
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Stdout, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
     process::Command,
-    sync::mpsc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, OnceLock,
+    },
     thread,
-    time::{Duration, Instant},
-    net::IpAddr,
+    time::{Duration, Instant, SystemTime},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use maxminddb::{Mmap, Reader};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use ratatui::{
+    prelude::*,
+    widgets::{BarChart, Block, BorderType, Borders, List, ListItem, ListState, Padding, Tabs},
 };
+use serde::{Deserialize, Serialize};
+
+use asn::{lookup_asn, open_asn_db, AsnInfo};
+use blacklist::{load_blacklist, Blacklist};
+use collector::{RemoteReporter, RemoteSnapshot};
+use countfile::CleanupGuard;
+use error::MonitorError;
+use ifstats::{InterfaceRate, InterfaceTracker};
+use localips::LocalIps;
+use mask::{mask_ip, mask_text, MaskMode, MaskedDebug};
+use logging::EventLogger;
+use mock::MockNetwork;
+use netns::NetNamespace;
+use process::{FuzzyFinder, PgrepFinder, ProcessFinder};
+use queue::EventSender;
+use ratefile::{parse_rate_format, RateFile};
+use ratelimit::{RateLimiter, DEFAULT_NOTIFY_RATE_LIMIT};
+use record::DurationRecord;
+use services::ServiceNames;
+use snapshot::{snapshot_path, write_snapshot, AppSnapshot, AppState};
+use tags::{load_tags, TagsWatcher};
+use tee::Tee;
+use template::{FormatToken, HistoryFields, StatusToken};
+
+
+#[derive(Subcommand, Debug, Clone)]
+enum CliCommand {
+    /// Collect snapshots from remote netmonrs instances
+    Collect(CollectArgs),
+    /// Check that netmonrs can monitor a target
+    Check(CheckArgs),
+}
+
+/// `netmonrs collect`, which aggregates snapshots sent with --remote-monitor.
+#[derive(clap::Args, Debug, Clone)]
+struct CollectArgs {
+    /// Address or port to listen on
+    #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:9999")]
+    listen: String,
+}
+
+/// `netmonrs check`, which verifies the tools and permissions netmonrs needs.
+#[derive(clap::Args, Debug, Clone)]
+struct CheckArgs {
+    target: String,
+}
+
+#[derive(Parser, Debug, Clone, Serialize)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<CliCommand>,
+
+    /// Process to monitor; use `-- TARGET` for a process named like a subcommand
+    #[arg(required = true)]
+    target: Option<String>,
+
+    /// Path to a MaxMind GeoLite2-ASN database
+    #[arg(long, value_name = "PATH")]
+    asn_db: Option<PathBuf>,
+
+    /// Show the autonomous system of each active connection
+    #[arg(long)]
+    show_asn: bool,
+
+    /// Record connection open/close events as synthetic TCP packets in a pcap file
+    #[cfg(feature = "pcap-output")]
+    #[arg(long, value_name = "PCAP")]
+    capture_file: Option<PathBuf>,
+
+    /// Append the raw lsof output of every poll to FILE
+    #[arg(long, value_name = "FILE")]
+    tee: Option<PathBuf>,
+
+    /// Truncate the --tee file once it grows past N megabytes
+    #[arg(long, value_name = "N")]
+    tee_max_mb: Option<u64>,
+
+    /// Forward new connection events to the local syslog daemon
+    #[arg(long)]
+    syslog: bool,
+
+    /// Shell command to run for each new IP ({ip}, {port}, {proto} are substituted)
+    #[arg(long, value_name = "CMD")]
+    exec_on_connect: Option<String>,
+
+    /// Shell command to run when an IP disappears from the active set
+    #[arg(long, value_name = "CMD")]
+    exec_on_disconnect: Option<String>,
+
+    /// Shell command to run once before monitoring starts ({target}, {pid} are substituted)
+    #[arg(long, value_name = "CMD")]
+    exec_on_startup: Option<String>,
+
+    /// Shell command to run when netmonrs exits ({target}, {pid} are substituted)
+    #[arg(long, value_name = "CMD")]
+    exec_on_exit: Option<String>,
+
+    /// Kill hook commands that run longer than this many milliseconds
+    #[arg(long, value_name = "N")]
+    exec_timeout_ms: Option<u64>,
+
+    /// Hide the connection timeline chart
+    #[arg(long)]
+    no_timeline: bool,
+
+    /// Treat the target as a glob pattern (`*` and `?` wildcards)
+    #[arg(long)]
+    glob: bool,
+
+    /// Monitor the single process whose command line best fuzzy-matches the target
+    #[arg(long, conflicts_with = "glob")]
+    fuzzy: bool,
+
+    /// Don't monitor matched processes with this name, from `ps -o comm=` (repeatable; a glob with --glob)
+    #[arg(long = "exclude-process", value_name = "NAME")]
+    exclude_process: Vec<String>,
+
+    /// Print only new connection events to stdout instead of running the TUI
+    #[arg(long)]
+    quiet: bool,
+
+    /// Print a single snapshot of active connections and exit
+    #[arg(long)]
+    once: bool,
+
+    /// Print only added (+) and removed (-) connections between polls instead of running the TUI
+    #[arg(long)]
+    diff: bool,
+
+    /// File or directory of blacklisted IPs/CIDR ranges (repeatable)
+    #[arg(long, value_name = "PATH")]
+    blacklist_file: Vec<PathBuf>,
+
+    /// TOML file with a [tags] table mapping IPs to labels
+    #[arg(long, value_name = "PATH")]
+    tags_file: Option<PathBuf>,
+
+    /// Keep at most this many history entries
+    #[arg(long, value_name = "N")]
+    max_history: Option<usize>,
+
+    /// Drop history entries older than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    max_history_age: Option<u64>,
+
+    /// Only add connections to the history if they lasted at least this many seconds
+    #[arg(long, value_name = "SECS")]
+    min_age: Option<u64>,
+
+    /// With --min-age, only add connections that lasted less than SECS instead
+    #[arg(long, requires = "min_age")]
+    min_age_invert: bool,
+
+    /// Write a JSON state snapshot to /tmp/netmonrs-<target>-snapshot.json every N seconds
+    #[arg(long, value_name = "SECS")]
+    snapshot_interval: Option<u64>,
+
+    /// Write the active connection count to this file after every poll; removed on exit
+    #[arg(long, value_name = "PATH")]
+    watch_count_file: Option<PathBuf>,
+
+    /// Append `<ts> <active> <new_per_sec> <total_unique>` to this file every second
+    #[arg(long, value_name = "PATH")]
+    rate_file: Option<PathBuf>,
+
+    /// Separator for --rate-file: tsv, csv or space [default: space]
+    #[arg(long, value_name = "FORMAT")]
+    rate_file_format: Option<String>,
+
+    /// Send snapshots to a `netmonrs collect` server at HOST:PORT
+    #[arg(long, value_name = "HOST:PORT")]
+    remote_monitor: Option<String>,
+
+    /// Seconds between --remote-monitor snapshots [default: 5]
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    remote_interval: Option<u64>,
+
+    /// Persist history to this file and preload it at startup
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Only preload history lines from --log-file that match this regex
+    #[arg(long, value_name = "PATTERN")]
+    history_grep: Option<String>,
+
+    /// Also collect UNIX domain sockets and show them in a separate pane
+    #[arg(long)]
+    show_unix_sockets: bool,
+
+    /// Show the process's LISTEN sockets at the top of the active list
+    #[arg(long)]
+    show_listening: bool,
+
+    /// Raise an alert when a new connection's display string matches this regex
+    #[arg(long, value_name = "PATTERN")]
+    alert_regex: Option<String>,
+
+    /// Raise at most N alerts or syslog messages per second, and N per IP per minute [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    notify_rate_limit: Option<u64>,
+
+    /// Refuse to start if another instance is already monitoring the same target
+    #[arg(long)]
+    lock: bool,
+
+    /// Exit after N unique IPs have been observed
+    #[arg(long, value_name = "N")]
+    count: Option<usize>,
+
+    /// Exit after monitoring for SECS seconds
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Grow the history pane when connection churn is high
+    #[arg(long)]
+    auto_layout: bool,
+
+    /// Only show the N most recently active connections
+    #[arg(long, value_name = "N")]
+    top_n: Option<usize>,
+
+    /// Only show the N most recent history entries [default: 0 = all]
+    #[arg(long, value_name = "N")]
+    tail: Option<usize>,
+
+    /// Shorten IPs in the active list to N characters [default: 0 = off]
+    #[arg(long, value_name = "N")]
+    truncate_ip: Option<usize>,
+
+    /// Comma-separated active list columns: ip, port, proto, state, age, bytes, country, asn, hostname
+    #[arg(long, value_name = "LIST")]
+    columns: Option<String>,
+
+    /// Don't color active connections by destination port
+    #[arg(long)]
+    no_port_colors: bool,
+
+    /// Resolve hostnames of active connections with reverse DNS
+    #[arg(long)]
+    resolve: bool,
+
+    /// Skip the TUI and print a summary report after SECS seconds
+    #[arg(long, value_name = "SECS")]
+    report_after: Option<u64>,
+
+    /// Keep monitoring after --report-after and print a new report every interval
+    #[arg(long)]
+    report_loop: bool,
+
+    /// Ignore connections whose remote port is below N
+    #[arg(long, value_name = "N")]
+    min_port: Option<u16>,
+
+    /// Ignore connections whose remote port is above N
+    #[arg(long, value_name = "N")]
+    max_port: Option<u16>,
+
+    /// Hide connections to this machine's own addresses
+    #[arg(long)]
+    exclude_self: bool,
+
+    /// Only record new history entries on 1 in N poll cycles [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    sample_rate: Option<u64>,
+
+    /// Read lsof output from a file instead of running lsof; re-read when it changes
+    #[arg(long, value_name = "PATH")]
+    input_file: Option<PathBuf>,
+
+    /// Show synthetic connections instead of running pgrep and lsof
+    #[arg(long, conflicts_with = "input_file")]
+    mock: bool,
+
+    /// Seed for --mock, for reproducible sequences
+    #[arg(long, value_name = "U64")]
+    mock_seed: Option<u64>,
+
+    /// Run lsof directly instead of through sudo
+    #[arg(long)]
+    no_sudo: bool,
+
+    /// Hide the history pane and don't keep a history log
+    #[arg(long)]
+    no_history: bool,
+
+    /// Retry a failed lsof call up to N times before reporting an error [default: 0]
+    #[arg(long, value_name = "N")]
+    retry_on_error: Option<u32>,
+
+    /// Kill lsof if it hasn't finished after MS milliseconds; 0 waits forever [default: 5000]
+    #[arg(long, value_name = "MS")]
+    lsof_timeout: Option<u64>,
+
+    /// lsof-compatible program to run, e.g. a wrapper script [default: lsof from PATH]
+    #[arg(long, value_name = "PATH")]
+    lsof_cmd: Option<String>,
+
+    /// Extra arguments appended after `-i -P -n -p <pid>`, split on whitespace
+    #[arg(long, value_name = "EXTRA_ARGS", allow_hyphen_values = true)]
+    lsof_args: Option<String>,
+
+    /// Warn once lsof has failed more than M polls in a row
+    #[arg(long, value_name = "M")]
+    alert_on_failures: Option<u32>,
+
+    /// Width ratio of the active and history panes, e.g. 70:30
+    #[arg(long, value_name = "LEFT:RIGHT")]
+    column_layout: Option<String>,
+
+    /// Pane border style: plain, rounded, double, thick or none [default: plain]
+    #[arg(long, value_name = "STYLE")]
+    border_style: Option<String>,
+
+    /// How IPv6 addresses are shown: compressed, expanded or mapped [default: compressed]
+    #[arg(long, value_name = "MODE")]
+    ipv6_display: Option<String>,
+
+    /// Record IPv4-mapped IPv6 addresses (::ffff:a.b.c.d) as plain IPv4
+    #[arg(long)]
+    strip_ipv4_mapped: bool,
+
+    /// Mask IP addresses in the status bar and debug output
+    #[arg(long)]
+    mask_ips: bool,
+
+    /// Load bookmarked IPs from this JSON file and save them on every change
+    #[arg(long, value_name = "PATH")]
+    save_bookmarks: Option<PathBuf>,
+
+    /// Keep the longest connection ever seen in this JSON file
+    #[arg(long, value_name = "FILE")]
+    all_time_record: Option<PathBuf>,
+
+    /// Read the PID to monitor from this file on every poll instead of running pgrep
+    #[arg(long, value_name = "PATH")]
+    watch_pid_file: Option<PathBuf>,
+
+    /// Exit if the process hasn't started after SECS seconds [default: 0 = wait forever]
+    #[arg(long, value_name = "SECS")]
+    pid_wait: Option<u64>,
+
+    /// Path to a MaxMind GeoLite2-Country database; enables the geo view (G)
+    #[arg(long, value_name = "PATH")]
+    geoip_db: Option<PathBuf>,
+
+    /// Highlight new connections in yellow for SECS seconds [default: 0 = off]
+    #[arg(long, value_name = "SECS")]
+    color_new_connections: Option<u64>,
+
+    /// Keep closed connections in the active list for SECS seconds [default: 0]
+    #[arg(long, value_name = "SECS")]
+    linger_secs: Option<u64>,
+
+    /// Color active connections by how long they've been open
+    #[arg(long)]
+    color_by_age: bool,
+
+    /// Show connections to previously seen IPs in gray and to new IPs in white
+    #[arg(long)]
+    color_seen: bool,
+
+    /// Give each /PREFIX_LEN subnet of the remote IPs its own color in the active list
+    #[arg(long, value_name = "PREFIX_LEN")]
+    color_by_subnet: Option<u8>,
+
+    /// Comma-separated colors for --color-by-subnet, e.g. red,green,blue
+    #[arg(long, value_name = "C1,C2,...")]
+    color_palette: Option<String>,
+
+    /// Mark connections to public IPs with ⚠, for processes that should only talk to private networks
+    #[arg(long)]
+    warn_private_to_public: bool,
+
+    /// Don't warn about this public IP with --warn-private-to-public (repeatable)
+    #[arg(long = "allow-public-ip", value_name = "IP")]
+    allow_public_ip: Vec<IpAddr>,
+
+    /// Export the history to CSV when quitting with q
+    #[arg(long)]
+    export_on_exit: bool,
+
+    /// Directory for --export-on-exit files [default: current directory]
+    #[arg(long, value_name = "DIR")]
+    export_path: Option<PathBuf>,
+
+    /// Maximum number of pending background events before updates are dropped [default: 10]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    event_queue_depth: Option<u64>,
+
+    /// Add a random delay of up to MS milliseconds to each poll [default: 0]
+    #[arg(long, value_name = "MS")]
+    watch_interval_jitter: Option<u64>,
+
+    /// Seed for --watch-interval-jitter, for reproducible timing
+    #[arg(long, value_name = "U64")]
+    seed: Option<u64>,
+
+    /// Show the process's total open file descriptor count in the status bar
+    #[arg(long)]
+    show_fd_count: bool,
+
+    /// Highlight the status bar when the open file descriptor count exceeds N (implies --show-fd-count)
+    #[arg(long, value_name = "N")]
+    alert_fd_count: Option<u32>,
+
+    /// Show per-interface receive/transmit rates from /proc/net/dev in the status bar (Linux)
+    #[arg(long)]
+    interface_stats: bool,
+
+    /// Warn when the active connection count changes by more than N between polls
+    #[arg(long, value_name = "N")]
+    watch_connections_delta: Option<usize>,
+
+    /// History entry template using {ts}, {ip}, {port}, {direction} and {duration} [default: "[{ts}] {ip}"]
+    #[arg(long, value_name = "TEMPLATE")]
+    format_history: Option<String>,
+
+    /// History pane layout: flat (chronological) or grouped (by IP) [default: flat]
+    #[arg(long, value_name = "FORMAT")]
+    history_format: Option<String>,
+
+    /// Status bar template using {pid}, {active_count}, {history_count}, {uptime}, {poll_count}, {rate} and {error}; without a value: "{pid} | Active: {active_count} | History: {history_count}"
+    #[arg(long, value_name = "TEMPLATE", num_args = 0..=1, require_equals = true, default_missing_value = template::DEFAULT_STATUS_TEMPLATE)]
+    format_status: Option<String>,
+
+    /// Hide the status bar; the process and error count move into the pane titles
+    #[arg(long)]
+    hide_status_bar: bool,
+
+    /// Open a snapshot written by --snapshot-interval and browse it without monitoring
+    #[arg(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+
+    /// Run lsof inside this network namespace, e.g. /proc/<container_pid>/ns/net (uses nsenter)
+    #[arg(long, value_name = "PATH")]
+    netns: Option<PathBuf>,
+
+    /// Run lsof inside the network namespace of this Docker container
+    #[arg(long, value_name = "NAME", conflicts_with = "netns")]
+    container: Option<String>,
+
+    /// Show service names for ports from /etc/services (e.g. 8080/http-alt)
+    #[arg(long)]
+    resolve_ports: bool,
+
+    /// Also monitor TARGET in its own tab (repeatable)
+    #[arg(long = "tab", value_name = "TARGET")]
+    tab: Vec<String>,
+
+    /// Load options from ~/.config/netmonrs/profiles/NAME.toml (command-line flags win)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Save the effective options to the profile NAME
+    #[arg(long, value_name = "NAME")]
+    save_profile: Option<String>,
+}
+
+impl Args {
+    /// Only empty when a subcommand was given instead of a target.
+    fn target(&self) -> &str {
+        self.target.as_deref().unwrap_or_default()
+    }
+
+    fn headless(&self) -> bool {
+        self.once || self.diff || self.report_after.is_some() || self.quiet
+    }
+}
+
+
+const MAX_DISPLAYED_PROCESSES: usize = 5;
+const TIMELINE_LEN: usize = 30;
+const DEFAULT_PAGE_SIZE: usize = 10;
+const LSOF_RETRY_DELAY: Duration = Duration::from_millis(100);
+const DEFAULT_LSOF_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_LSOF_CMD: &str = "lsof";
+const LSOF_TIMEOUT_WARNING: &str = "lsof timed out";
+const ALERT_TTL: Duration = Duration::from_secs(60);
+const FLASH_MSG_TTL: Duration = Duration::from_secs(2);
+const RECORD_MSG_TTL: Duration = Duration::from_secs(10);
+const DIFF_WINDOW: Duration = Duration::from_secs(5 * 60);
+const ERROR_FLASH_TTL: Duration = Duration::from_secs(3);
+const DEFAULT_EVENT_QUEUE_DEPTH: usize = 10;
+const EVENT_SEND_TIMEOUT: Duration = Duration::from_millis(200);
+const MONITOR_RESTART_DELAY: Duration = Duration::from_secs(1);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HEARTBEAT_STALL: Duration = Duration::from_secs(10);
+const CLOSED_MARKER: &str = "CLOSED";
+const RESTART_MARKER: &str = "RESTART";
+const SPIKE_MARKER: &str = "──";
+const HOSTNAME_TTL: Duration = Duration::from_secs(5 * 60);
+const RATE_WINDOW_SECS: i64 = 10;
+const CONN_RATE_REFRESH: Duration = Duration::from_secs(1);
+const RECENT_UNIQUE_WINDOW: Duration = Duration::from_secs(60);
+const CHURN_WINDOW: usize = 10;
+const LAYOUT_STEP_PCT: u16 = 2;
+const MIN_PANE_PCT: u16 = 20;
+const MAX_PANE_PCT: u16 = 80;
+const TIMELINE_HEIGHT: u16 = 5;
+
+
+#[derive(PartialEq)]
+enum Focus {
+    ActiveList,
+    HistoryList,
+}
+
+/// Order of the active list; `Ip` keeps the monitor's IP/port order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Ip,
+    Hostname,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Ip => SortKey::Hostname,
+            SortKey::Hostname => SortKey::Ip,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ConnectionKey {
+    local_ip: IpAddr,
+    local_port: u16,
+    ip: IpAddr,
+    port: u16,
+}
+
+impl fmt::Debug for ConnectionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionKey")
+            .field("local_ip", &format_args!("{}", mask_ip(&self.local_ip, mask::mode())))
+            .field("local_port", &self.local_port)
+            .field("ip", &format_args!("{}", mask_ip(&self.ip, mask::mode())))
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
+impl fmt::Display for ConnectionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = format_endpoint(self.local_ip, self.local_port);
+        write!(f, "{} -> {}", local, format_endpoint(self.ip, self.port))
+    }
+}
+
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ConnectionEntry {
+    local_ip: IpAddr,
+    local_port: u16,
+    ip: IpAddr,
+    port: u16,
+    proto: String,
+    state: String,
+    size: Option<u64>,
+    rate: Option<f64>,
+    #[serde(skip, default = "Instant::now")]
+    last_seen: Instant,
+}
+
+impl ConnectionEntry {
+    fn key(&self) -> ConnectionKey {
+        ConnectionKey { local_ip: self.local_ip, local_port: self.local_port, ip: self.ip, port: self.port }
+    }
+}
+
+impl fmt::Debug for ConnectionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionEntry")
+            .field("local_ip", &format_args!("{}", mask_ip(&self.local_ip, mask::mode())))
+            .field("local_port", &self.local_port)
+            .field("ip", &format_args!("{}", mask_ip(&self.ip, mask::mode())))
+            .field("port", &self.port)
+            .field("proto", &self.proto)
+            .field("state", &self.state)
+            .field("size", &self.size)
+            .field("rate", &self.rate)
+            .field("last_seen", &self.last_seen)
+            .finish()
+    }
+}
+
+impl fmt::Display for ConnectionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", display_endpoint(self.ip, self.port))?;
+        match (self.proto.is_empty(), self.state.is_empty()) {
+            (true, true) => Ok(()),
+            (false, true) => write!(f, " [{}]", self.proto),
+            (true, false) => write!(f, " [{}]", self.state),
+            (false, false) => write!(f, " [{}/{}]", self.proto, self.state),
+        }
+    }
+}
+
+impl ConnectionEntry {
+    /// Same as `Display` with the service name after the port.
+    fn label_with_service(&self, service: &str) -> String {
+        let addr = display_endpoint(self.ip, self.port);
+        self.to_string().replacen(&addr, &format!("{}/{}", addr, service), 1)
+    }
+}
+
+impl PartialEq for ConnectionEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key() && self.proto == other.proto
+    }
+}
+
+impl Eq for ConnectionEntry {}
+
+impl std::hash::Hash for ConnectionEntry {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+        self.proto.hash(state);
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+struct UnixSocketEntry {
+    path: String,
+    type_: String,
+    state: String,
+}
+
+/// A `(LISTEN)` socket of the monitored process; never part of the history.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ListeningSocket {
+    port: u16,
+    ip: IpAddr,
+    proto: String,
+}
+
+impl fmt::Display for ListeningSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} LISTEN", display_endpoint(self.ip, self.port))
+    }
+}
+
+
+type HistoryEntry = (DateTime<Local>, String);
+
+
+enum BackgroundEvent {
+    DataUpdate {
+        active: Vec<ConnectionEntry>,
+        new_history_entries: Vec<HistoryEntry>,
+        unix_sockets: Vec<UnixSocketEntry>,
+        listening: Vec<ListeningSocket>,
+        pid_msg: String,
+        fd_count: Option<u32>,
+        /// How often the monitor thread has been restarted after a panic.
+        restart_count: u32,
+    },
+    Error(MonitorError),
+    Warning(String),
+    DnsResult {
+        ip: IpAddr,
+        hostname: Option<String>,
+    },
+    ProcessRestart {
+        old_pid: u32,
+        new_pid: u32,
+    },
+    ConnectionClosed {
+        connection: ConnectionEntry,
+        duration: Duration,
+    },
+    /// Sent by the monitor thread before polling, at most every `HEARTBEAT_INTERVAL`.
+    Heartbeat {
+        ts: Instant,
+    },
+}
+
+
+struct App {
+    target_name: String,
+
+    active_connections: Vec<ConnectionEntry>,
+    active_total: usize,
+    top_n: Option<usize>,
+    port_range: PortRange,
+    active_unix_sockets: Vec<UnixSocketEntry>,
+    listening_sockets: Vec<ListeningSocket>,
+    show_unix_pane: bool,
+    history_log: Vec<HistoryEntry>,
+    show_history: bool,
+    relative_times: bool,
+    /// Show the added/removed IP diff instead of the history list.
+    history_diff: bool,
+    history_format: HistoryFormat,
+    collapsed_history_ips: HashSet<IpAddr>,
+    history_template: Option<Vec<FormatToken>>,
+    closed_count: u64,
+    conn_rate: Option<f64>,
+    last_conn_rate: Instant,
+    seen_ips: HashSet<String>,
+    max_history: Option<usize>,
+    max_history_age: Option<chrono::Duration>,
+    min_age: Option<Duration>,
+    min_age_invert: bool,
+    /// New-IP entries held back by `--min-age` until their connection
+    /// qualifies; the ones still active are written on exit.
+    held_history: Vec<HistoryEntry>,
+    last_status_msg: String,
+    status_template: Option<Vec<StatusToken>>,
+    /// The failure from the last poll, cleared by the next successful one.
+    last_error: Option<String>,
+    started: Instant,
+    /// Not moved by `reset`, so `--timeout` counts from launch.
+    launched: Instant,
+    poll_count: u64,
+    process_label: String,
+    show_status_bar: bool,
+    error_count: u64,
+    lsof_timeout_count: u32,
+    /// None until the monitor thread's first heartbeat; replayed tabs never get one.
+    last_heartbeat: Option<Instant>,
+    error_flash_until: Option<Instant>,
+    fd_count: Option<u32>,
+    fd_alert: Option<u32>,
+    interface_tracker: Option<InterfaceTracker>,
+    count_delta_alert: Option<usize>,
+    /// Unset until the first update so startup isn't reported as a spike.
+    prev_active_count: Option<usize>,
+    count_spike: bool,
+    bookmarks: HashSet<String>,
+    bookmarks_path: Option<PathBuf>,
+    record_path: Option<PathBuf>,
+    record: Option<DurationRecord>,
+    flash_msg: String,
+    flash_msg_until: Option<Instant>,
+    history_tail: Option<usize>,
+    /// Text typed after `:`, while the command prompt is open.
+    command_input: Option<String>,
+
+    asn_reader: Option<Reader<Mmap>>,
+    asn_cache: HashMap<IpAddr, Option<AsnInfo>>,
+    show_asn: bool,
+    group_by_asn: bool,
+
+    geo_reader: Option<Reader<Mmap>>,
+    geo_cache: HashMap<IpAddr, String>,
+    group_by_geo: bool,
+    collapsed_countries: HashSet<String>,
+
+    timeline: VecDeque<u16>,
+    show_timeline: bool,
+    port_colors: bool,
+    truncate_ip: usize,
+    /// Empty keeps the default `ip:port [proto/state]` layout.
+    columns: Vec<Column>,
+    services: Option<ServiceNames>,
+    new_highlight: Duration,
+    new_since: HashMap<ConnectionKey, Instant>,
+    linger: Duration,
+    /// Remote IPs of connections opened in the last `RECENT_UNIQUE_WINDOW`, oldest first.
+    recent_unique_ips: VecDeque<(Instant, IpAddr)>,
+    /// Recently closed connections and when they disappeared.
+    lingering: HashMap<ConnectionKey, (ConnectionEntry, Instant)>,
+    color_by_age: bool,
+    truecolor: bool,
+    first_seen: HashMap<ConnectionKey, Instant>,
+    color_seen: bool,
+    /// Whether each connection's IP was already in `seen_ips` when it appeared.
+    seen_on_arrival: HashMap<ConnectionKey, bool>,
+    color_by_subnet: Option<u8>,
+    subnet_palette: Vec<Color>,
+    warn_public: bool,
+    allowed_public_ips: HashSet<IpAddr>,
+    /// Active connections to public IPs, counted when `--warn-private-to-public` is set.
+    public_connection_count: u32,
+
+    blacklist: Arc<Blacklist>,
+
+    tags: HashMap<IpAddr, String>,
+    tags_watcher: Option<TagsWatcher>,
+
+    resolve: bool,
+    dns_tx: Option<mpsc::Sender<IpAddr>>,
+    hostname_cache: HashMap<IpAddr, (Option<String>, Instant)>,
+    sort_key: SortKey,
+    pending_dns: HashSet<IpAddr>,
+
+    snapshot_interval: Option<Duration>,
+    last_snapshot: Instant,
+    count_file: Option<CleanupGuard>,
+    rate_file: Option<RateFile>,
+    remote: Option<RemoteReporter>,
+    remote_interval: Duration,
+    last_remote: Instant,
+
+    log_file: Option<File>,
+    export_dir: Option<PathBuf>,
+
+    alert_regex: Option<regex::Regex>,
+    alert_limiter: RateLimiter,
+    alerts: Vec<(Instant, String)>,
+
+    count_target: Option<usize>,
+    timeout: Option<Duration>,
+
+    auto_layout: bool,
+    churn_samples: VecDeque<usize>,
+    churn_rate: f64,
+    layout_ratio: (u16, u16),
+    /// `None` draws panes without borders.
+    border_type: Option<BorderType>,
+
+    focus: Focus,
+    active_state: ListState,
+    history_state: ListState,
+    active_pane_height: u16,
+    history_pane_height: u16,
+}
+
+impl App {
+    fn new(target: String) -> Self {
+        Self {
+            target_name: target,
+            active_connections: Vec::new(),
+            active_total: 0,
+            top_n: None,
+            port_range: PortRange::default(),
+            active_unix_sockets: Vec::new(),
+            listening_sockets: Vec::new(),
+            show_unix_pane: false,
+            history_log: Vec::new(),
+            show_history: true,
+            relative_times: false,
+            history_diff: false,
+            history_format: HistoryFormat::Flat,
+            collapsed_history_ips: HashSet::new(),
+            history_template: None,
+            closed_count: 0,
+            conn_rate: None,
+            last_conn_rate: Instant::now(),
+            seen_ips: HashSet::new(),
+            max_history: None,
+            max_history_age: None,
+            min_age: None,
+            min_age_invert: false,
+            held_history: Vec::new(),
+            last_status_msg: String::from("Initializing..."),
+            status_template: None,
+            last_error: None,
+            started: Instant::now(),
+            launched: Instant::now(),
+            poll_count: 0,
+            process_label: String::new(),
+            show_status_bar: true,
+            error_count: 0,
+            lsof_timeout_count: 0,
+            last_heartbeat: None,
+            interface_tracker: None,
+            error_flash_until: None,
+            fd_count: None,
+            fd_alert: None,
+            count_delta_alert: None,
+            prev_active_count: None,
+            count_spike: false,
+            bookmarks: HashSet::new(),
+            bookmarks_path: None,
+            record_path: None,
+            record: None,
+            flash_msg: String::new(),
+            flash_msg_until: None,
+            history_tail: None,
+            command_input: None,
+
+            asn_reader: None,
+            asn_cache: HashMap::new(),
+            show_asn: false,
+            group_by_asn: false,
+
+            geo_reader: None,
+            geo_cache: HashMap::new(),
+            group_by_geo: false,
+            collapsed_countries: HashSet::new(),
+
+            timeline: VecDeque::with_capacity(TIMELINE_LEN),
+            show_timeline: true,
+            port_colors: true,
+            truncate_ip: 0,
+            columns: Vec::new(),
+            services: None,
+            new_highlight: Duration::ZERO,
+            new_since: HashMap::new(),
+            linger: Duration::ZERO,
+            recent_unique_ips: VecDeque::new(),
+            lingering: HashMap::new(),
+            color_by_age: false,
+            color_seen: false,
+            seen_on_arrival: HashMap::new(),
+            color_by_subnet: None,
+            subnet_palette: DEFAULT_SUBNET_PALETTE.to_vec(),
+            warn_public: false,
+            allowed_public_ips: HashSet::new(),
+            public_connection_count: 0,
+            truecolor: supports_truecolor(),
+            first_seen: HashMap::new(),
+
+            blacklist: Arc::new(Blacklist::default()),
+
+            tags: HashMap::new(),
+            tags_watcher: None,
+
+            resolve: false,
+            dns_tx: None,
+            hostname_cache: HashMap::new(),
+            sort_key: SortKey::Ip,
+            pending_dns: HashSet::new(),
+
+            snapshot_interval: None,
+            count_file: None,
+            rate_file: None,
+            last_snapshot: Instant::now(),
+            remote: None,
+            remote_interval: collector::DEFAULT_REMOTE_INTERVAL,
+            last_remote: Instant::now(),
+
+            log_file: None,
+            export_dir: None,
+
+            alert_regex: None,
+            alert_limiter: RateLimiter::new(DEFAULT_NOTIFY_RATE_LIMIT),
+            alerts: Vec::new(),
+
+            count_target: None,
+            timeout: None,
+
+            auto_layout: false,
+            churn_samples: VecDeque::with_capacity(CHURN_WINDOW),
+            churn_rate: 0.0,
+            layout_ratio: (50, 50),
+            border_type: Some(BorderType::Plain),
+
+            focus: Focus::ActiveList,
+            active_state: ListState::default(),
+            history_state: ListState::default(),
+            active_pane_height: 0,
+            history_pane_height: 0,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_data(target: String, active: Vec<ConnectionEntry>, history: Vec<HistoryEntry>) -> Self {
+        let mut app = Self::new(target);
+        app.active_total = active.len();
+        app.active_connections = active;
+        app.history_log = history;
+        app.update_seen_ips();
+        app
+    }
+
+    fn active_len(&self) -> usize {
+        if self.group_by_geo {
+            self.geo_rows().len()
+        } else if self.group_by_asn {
+            let groups = self.asn_groups();
+            groups.len() + self.active_connections.len()
+        } else {
+            self.active_connections.len()
+        }
+    }
+
+    fn next(&mut self) {
+        self.select_next(1);
+    }
+
+    fn previous(&mut self) {
+        self.select_previous(1);
+    }
+
+    fn page_size(&self) -> usize {
+        let height = match self.focus {
+            Focus::ActiveList => self.active_pane_height,
+            Focus::HistoryList => self.history_pane_height,
+        };
+        page_size_for_height(height)
+    }
+
+    fn page_down(&mut self) {
+        self.select_next(self.page_size());
+    }
+
+    fn page_up(&mut self) {
+        self.select_previous(self.page_size());
+    }
+
+    /// The focused list's state and row count; the count is computed once
+    /// per key so paging doesn't regroup the history on every step.
+    fn focused_list(&mut self) -> (&mut ListState, usize) {
+        match self.focus {
+            Focus::ActiveList => {
+                let len = self.active_len();
+                (&mut self.active_state, len)
+            }
+            Focus::HistoryList => {
+                let len = self.history_len();
+                (&mut self.history_state, len)
+            }
+        }
+    }
+
+    fn select_next(&mut self, steps: usize) {
+        let (state, len) = self.focused_list();
+        if len == 0 { return; }
+
+        let mut selected = state.selected();
+        for _ in 0..steps {
+            selected = Some(match selected {
+                Some(i) => if i >= len - 1 { 0 } else { i + 1 },
+                None => 0,
+            });
+        }
+        state.select(selected);
+    }
+
+    fn select_previous(&mut self, steps: usize) {
+        let (state, len) = self.focused_list();
+        if len == 0 { return; }
+
+        let mut selected = state.selected();
+        for _ in 0..steps {
+            selected = Some(match selected {
+                Some(i) => if i == 0 { len - 1 } else { i - 1 },
+                None => 0,
+            });
+        }
+        state.select(selected);
+    }
+
+    fn selected_ip(&self) -> Option<String> {
+        match self.focus {
+            Focus::ActiveList => {
+                let i = self.active_state.selected()?;
+                let conn = if self.group_by_geo {
+                    match self.geo_rows().into_iter().nth(i)? {
+                        GeoRow::Connection(conn) => conn,
+                        GeoRow::Header { .. } => return None,
+                    }
+                } else if self.group_by_asn {
+                    self.asn_groups().into_iter()
+                        .flat_map(|(_, conns)| std::iter::once(None).chain(conns.into_iter().map(Some)))
+                        .nth(i)??
+                } else {
+                    self.active_connections.get(i)?
+                };
+                Some(conn.ip.to_string())
+            }
+            Focus::HistoryList => {
+                let i = self.history_state.selected()?;
+                let (_, entry) = match self.history_format {
+                    HistoryFormat::Flat => self.tailed_history().iter().rev().nth(i)?,
+                    HistoryFormat::Grouped => match self.history_rows().into_iter().nth(i)? {
+                        HistoryRow::Header(group) => return Some(group.ip.to_string()),
+                        HistoryRow::Entry(entry) => entry,
+                    },
+                };
+                match history_entry_ip(entry) {
+                    Some(ip) => Some(ip.to_string()),
+                    None => {
+                        let addr = entry.split_whitespace().nth(1)?;
+                        let (host, _) = addr.rsplit_once(':')?;
+                        Some(strip_brackets(host).to_string())
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_bookmarked(&self, ip: &IpAddr) -> bool {
+        self.bookmarks.contains(&ip.to_string())
+    }
+
+    fn apply_sort(&mut self) {
+        match self.sort_key {
+            SortKey::Ip => {}
+            SortKey::Hostname => sort_by_hostname(&mut self.active_connections, &self.hostname_cache),
+        }
+        self.sort_bookmarks_first();
+    }
+
+    fn cycle_sort(&mut self) {
+        let selected = self.selected_connection_key();
+        self.sort_key = self.sort_key.next();
+        if self.sort_key == SortKey::Ip {
+            self.active_connections.sort_by_key(|c| (c.ip, c.port));
+        }
+        self.apply_sort();
+        self.reselect_connection(selected);
+    }
+
+    fn sort_bookmarks_first(&mut self) {
+        let bookmarks = &self.bookmarks;
+        self.active_connections.sort_by_key(|c| !bookmarks.contains(&c.ip.to_string()));
+    }
+
+    /// The selected connection, when the active pane lists plain connections.
+    fn selected_connection_key(&self) -> Option<ConnectionKey> {
+        if self.group_by_geo || self.group_by_asn {
+            return None;
+        }
+        self.active_state.selected().and_then(|i| self.active_connections.get(i)).map(ConnectionEntry::key)
+    }
+
+    /// Keeps the selection on the same connection after a re-sort.
+    fn reselect_connection(&mut self, key: Option<ConnectionKey>) {
+        if let Some(key) = key
+            && let Some(i) = self.active_connections.iter().position(|c| c.key() == key) {
+            self.active_state.select(Some(i));
+        }
+    }
+
+    fn toggle_bookmark(&mut self) {
+        let Some(ip) = self.selected_ip() else { return };
+        if !self.bookmarks.remove(&ip) {
+            self.bookmarks.insert(ip);
+        }
+        let selected = self.selected_connection_key();
+        self.sort_bookmarks_first();
+        self.reselect_connection(selected);
+
+        if let Some(path) = &self.bookmarks_path
+            && let Err(e) = bookmarks::save_bookmarks(path, &self.bookmarks) {
+            self.last_status_msg = format!("Bookmarks Error: {}", e);
+        }
+    }
+
+    fn check_record(&mut self, conn: &ConnectionEntry, duration: Duration, now: Instant) {
+        let Some(path) = &self.record_path else { return };
+        if !record::is_new_record(self.record.as_ref(), duration) {
+            return;
+        }
+        let record = DurationRecord { ip: conn.ip.to_string(), port: conn.port, duration_secs: duration.as_secs() };
+        self.flash_msg = match record::save_record(path, &record) {
+            Ok(()) => format!("🏆 New longest connection: {} ({})", format_connection(conn), format_duration(duration)),
+            Err(e) => format!("Record Error: {}", e),
+        };
+        self.flash_msg_until = Some(now + RECORD_MSG_TTL);
+        self.record = Some(record);
+    }
+
+    /// Returns the message to print after leaving the TUI, if exporting is enabled.
+    fn export_on_exit(&self, now: DateTime<Local>) -> Option<String> {
+        let dir = self.export_dir.as_ref()?;
+        Some(match export::export_to_csv(dir, &self.history_log, now) {
+            Ok(path) => format!("History exported to {} ({} entries)", path.display(), self.history_log.len()),
+            Err(e) => format!("Failed to export history to {}: {}", dir.display(), e),
+        })
+    }
+
+    fn format_history(&self, item: &HistoryEntry, now: DateTime<Local>) -> String {
+        let Some(tokens) = &self.history_template else {
+            return if self.relative_times {
+                format_history_entry_relative(item, now)
+            } else {
+                format_history_entry(item)
+            };
+        };
+        let (ts, entry) = item;
+        let ts = if self.relative_times {
+            relative_time((now - *ts).to_std().unwrap_or_default())
+        } else {
+            ts.format("%H:%M:%S").to_string()
+        };
+        template::render(tokens, &HistoryFields { ts: &ts, ..history_fields(entry) })
+    }
+
+    /// How long the monitor thread has been silent, once that exceeds `HEARTBEAT_STALL`.
+    fn stalled_for(&self, now: Instant) -> Option<Duration> {
+        let silent = now.duration_since(self.last_heartbeat?);
+        (silent > HEARTBEAT_STALL).then_some(silent)
+    }
+
+    /// Forgets everything observed so far, as if just started. Options,
+    /// bookmarks and the longest-connection record are kept. The monitor
+    /// thread is reset separately, see `MultiApp::reset_current`.
+    fn reset(&mut self, now: Instant) {
+        self.active_connections.clear();
+        self.active_total = 0;
+        self.active_unix_sockets.clear();
+        self.listening_sockets.clear();
+        self.history_log.clear();
+        self.held_history.clear();
+        self.collapsed_history_ips.clear();
+        self.closed_count = 0;
+        self.conn_rate = None;
+        self.seen_ips.clear();
+        self.last_error = None;
+        self.started = now;
+        self.poll_count = 0;
+        self.error_count = 0;
+        self.lsof_timeout_count = 0;
+        self.error_flash_until = None;
+        self.fd_count = None;
+        self.prev_active_count = None;
+        self.count_spike = false;
+        self.asn_cache.clear();
+        self.geo_cache.clear();
+        self.collapsed_countries.clear();
+        self.timeline.clear();
+        self.new_since.clear();
+        self.recent_unique_ips.clear();
+        self.lingering.clear();
+        self.first_seen.clear();
+        self.seen_on_arrival.clear();
+        self.public_connection_count = 0;
+        self.hostname_cache.clear();
+        self.pending_dns.clear();
+        self.alerts.clear();
+        self.churn_samples.clear();
+        self.churn_rate = 0.0;
+        self.active_state = ListState::default();
+        self.history_state = ListState::default();
+        self.last_status_msg = String::from("Reset — monitoring resumed");
+    }
+
+    /// Applies `--min-age`: whether a connection that lasted `duration` goes into the history.
+    fn keeps_closed(&self, duration: Duration) -> bool {
+        self.min_age.is_none_or(|min| (duration >= min) != self.min_age_invert)
+    }
+
+    /// With `--min-age`, new-IP entries wait in `held_history` instead.
+    fn append_or_hold(&mut self, entries: Vec<HistoryEntry>) {
+        if self.min_age.is_some() {
+            self.held_history.extend(entries);
+        } else {
+            self.append_history(entries);
+        }
+    }
+
+    /// Writes the held entries whose IP matches `release`.
+    fn release_held(&mut self, release: impl Fn(IpAddr) -> bool) {
+        let (released, held): (Vec<HistoryEntry>, Vec<HistoryEntry>) = std::mem::take(&mut self.held_history).into_iter()
+            .partition(|(_, entry)| held_entry_ip(entry).is_some_and(&release));
+        self.held_history = held;
+        if released.is_empty() {
+            return;
+        }
+        let now = Local::now();
+        self.append_history(released.into_iter().map(|(_, entry)| (now, entry)).collect());
+    }
+
+    /// Without `--min-age-invert`, an IP's entry is written once one of its
+    /// connections has been open for `--min-age`.
+    fn release_aged_history(&mut self, now: Instant) {
+        let Some(min) = self.min_age.filter(|_| !self.min_age_invert) else { return };
+        let aged: HashSet<IpAddr> = self.active_connections.iter()
+            .filter(|c| self.connection_age(c, now) >= min)
+            .map(|c| c.ip)
+            .collect();
+        self.release_held(|ip| aged.contains(&ip));
+    }
+
+    /// Connections still open when netmonrs exits are logged whatever their age.
+    fn flush_held_history(&mut self) {
+        let active: HashSet<IpAddr> = self.active_connections.iter().map(|c| c.ip).collect();
+        self.release_held(|ip| active.contains(&ip));
+    }
+
+    /// `--watch-count-file`: the active count after every poll.
+    fn write_count_file(&mut self) {
+        if let Some(guard) = &self.count_file
+            && let Err(e) = countfile::atomic_write_count(guard.path(), self.active_total) {
+            self.last_status_msg = format!("Count File Error: {}", e);
+        }
+    }
+
+    fn fd_count_high(&self) -> bool {
+        matches!((self.fd_count, self.fd_alert), (Some(count), Some(max)) if count > max)
+    }
+
+    /// Compares the active count with the previous poll and returns
+    /// `(old, new)` when it moved by more than `--watch-connections-delta`.
+    fn check_count_spike(&mut self) -> Option<(usize, usize)> {
+        let old = self.prev_active_count.replace(self.active_total);
+        self.count_spike = false;
+        let (old, threshold) = (old?, self.count_delta_alert?);
+        self.count_spike = old.abs_diff(self.active_total) > threshold;
+        self.count_spike.then_some((old, self.active_total))
+    }
+
+    fn copy_selected(&mut self, now: Instant) {
+        let Some(ip) = self.selected_ip() else { return };
+        self.flash_msg = match clipboard::copy(&ip) {
+            Ok(()) => format!("Copied {} to clipboard", ip),
+            Err(e) => format!("Clipboard Error: {}", e),
+        };
+        self.flash_msg_until = Some(now + FLASH_MSG_TTL);
+    }
+
+    fn append_history(&mut self, entries: Vec<HistoryEntry>) {
+        if let Some(file) = self.log_file.as_mut()
+            && let Err(e) = logfile::append_entries(file, &entries) {
+            self.last_status_msg = format!("Log Error: {}", e);
+        }
+        if self.show_history {
+            self.history_log.extend(entries);
+            self.prune_history(Local::now());
+        }
+    }
+
+    fn toggle_focus(&mut self) {
+        if !self.show_history {
+            return;
+        }
+        self.focus = match self.focus {
+            Focus::ActiveList => Focus::HistoryList,
+            Focus::HistoryList => Focus::ActiveList,
+        };
+    }
+
+    fn record_churn(&mut self, new_entries: usize) {
+        if self.churn_samples.len() == CHURN_WINDOW {
+            self.churn_samples.pop_front();
+        }
+        self.churn_samples.push_back(new_entries);
+        self.churn_rate = self.churn_samples.iter().sum::<usize>() as f64 / self.churn_samples.len() as f64;
+
+        if self.auto_layout {
+            let target = history_pct_for_churn(self.churn_rate);
+            let history_pct = step_towards(self.layout_ratio.1, target, LAYOUT_STEP_PCT);
+            self.layout_ratio = (100 - history_pct, history_pct);
+        }
+    }
+
+    fn adjust_layout(&mut self, delta: i16) {
+        let left = (self.layout_ratio.0 as i16 + delta).clamp(MIN_PANE_PCT as i16, MAX_PANE_PCT as i16) as u16;
+        self.layout_ratio = (left, 100 - left);
+    }
+
+    fn mark_new_connections(&mut self, previous: &[ConnectionEntry], now: Instant) {
+        if self.new_highlight.is_zero() {
+            return;
+        }
+        let previous: HashSet<ConnectionKey> = previous.iter().map(ConnectionEntry::key).collect();
+        for conn in &self.active_connections {
+            if !previous.contains(&conn.key()) {
+                self.new_since.entry(conn.key()).or_insert(now);
+            }
+        }
+    }
+
+    /// Runs before the update's history is merged into `seen_ips`, so an IP
+    /// first seen in this update still counts as new.
+    fn track_seen_on_arrival(&mut self) {
+        let active: HashSet<ConnectionKey> = self.active_connections.iter().map(ConnectionEntry::key).collect();
+        self.seen_on_arrival.retain(|key, _| active.contains(key));
+        for conn in &self.active_connections {
+            let seen = self.seen_ips.contains(&conn.ip.to_string());
+            self.seen_on_arrival.entry(conn.key()).or_insert(seen);
+        }
+    }
+
+    fn is_unexpected_public(&self, ip: IpAddr) -> bool {
+        self.warn_public && localips::is_global(ip) && !self.allowed_public_ips.contains(&ip)
+    }
+
+    fn count_public_connections(&mut self) {
+        self.public_connection_count = 0;
+        for conn in &self.active_connections {
+            if self.is_unexpected_public(conn.ip) {
+                self.public_connection_count += 1;
+            }
+        }
+    }
+
+    fn seen_color(&self, conn: &ConnectionEntry) -> Color {
+        let seen = self.seen_on_arrival.get(&conn.key()).copied()
+            .unwrap_or_else(|| self.seen_ips.contains(&conn.ip.to_string()));
+        if seen { Color::DarkGray } else { Color::White }
+    }
+
+    fn track_first_seen(&mut self, now: Instant) {
+        let active: HashSet<ConnectionKey> = self.active_connections.iter().map(ConnectionEntry::key).collect();
+        self.first_seen.retain(|key, _| active.contains(key));
+        for key in active {
+            self.first_seen.entry(key).or_insert(now);
+        }
+    }
+
+    fn connection_age(&self, conn: &ConnectionEntry, now: Instant) -> Duration {
+        self.first_seen.get(&conn.key()).map_or(Duration::ZERO, |since| now.duration_since(*since))
+    }
+
+    fn record_lingering(&mut self, previous: Vec<ConnectionEntry>, now: Instant) {
+        if self.linger.is_zero() {
+            return;
+        }
+        let active: HashSet<ConnectionKey> = self.active_connections.iter().map(ConnectionEntry::key).collect();
+        self.lingering.retain(|key, _| !active.contains(key));
+        for conn in previous.into_iter().filter(|c| !active.contains(&c.key())) {
+            self.lingering.entry(conn.key()).or_insert((conn, now));
+        }
+    }
+
+    fn prune_lingering(&mut self, now: Instant) {
+        let ttl = self.linger;
+        self.lingering.retain(|_, (_, gone)| now.duration_since(*gone) < ttl);
+    }
+
+    /// Most recently closed first.
+    fn lingering_connections(&self) -> Vec<(&ConnectionEntry, Instant)> {
+        let mut conns: Vec<_> = self.lingering.values().map(|(conn, gone)| (conn, *gone)).collect();
+        conns.sort_by_key(|(conn, gone)| (std::cmp::Reverse(*gone), conn.ip, conn.port));
+        conns
+    }
+
+    fn track_recent_ips(&mut self, previous: &[ConnectionEntry], now: Instant) {
+        let previous: HashSet<ConnectionKey> = previous.iter().map(ConnectionEntry::key).collect();
+        for conn in &self.active_connections {
+            if !previous.contains(&conn.key()) {
+                self.recent_unique_ips.push_back((now, conn.ip));
+            }
+        }
+        self.prune_recent_ips(now);
+    }
+
+    fn prune_recent_ips(&mut self, now: Instant) {
+        while self.recent_unique_ips.front().is_some_and(|(at, _)| now.duration_since(*at) >= RECENT_UNIQUE_WINDOW) {
+            self.recent_unique_ips.pop_front();
+        }
+    }
+
+    fn prune_new_since(&mut self, now: Instant) {
+        let ttl = self.new_highlight;
+        self.new_since.retain(|_, since| now.duration_since(*since) < ttl);
+    }
+
+    fn is_new(&self, conn: &ConnectionEntry) -> bool {
+        self.new_since.contains_key(&conn.key())
+    }
+
+    fn count_reached(&self) -> bool {
+        count_reached(self.seen_ips.len(), self.count_target)
+    }
+
+    fn timed_out(&self, now: Instant) -> bool {
+        self.timeout.is_some_and(|t| now.duration_since(self.launched) >= t)
+    }
+
+    fn take_until_count(&mut self, entries: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+        let mut taken = Vec::new();
+        for entry in entries {
+            if self.count_reached() {
+                break;
+            }
+            if let Some(ip) = history_entry_ip(&entry.1) {
+                self.seen_ips.insert(canonicalize_ip(ip));
+            }
+            taken.push(entry);
+        }
+        taken
+    }
+
+    fn update_seen_ips(&mut self) {
+        let mut seen_ips = HashSet::new();
+        let history_to_check = if self.history_log.len() > 1000 {
+            &self.history_log[self.history_log.len() - 1000..]
+        } else {
+            &self.history_log
+        };
+
+        for (_, h) in history_to_check.iter().chain(&self.held_history) {
+             if let Some(ip) = history_entry_ip(h) {
+                 seen_ips.insert(canonicalize_ip(ip));
+             }
+        }
+        self.seen_ips = seen_ips;
+    }
+
+    fn push_timeline(&mut self, count: usize) {
+        if self.timeline.len() == TIMELINE_LEN {
+            self.timeline.pop_front();
+        }
+        self.timeline.push_back(count.min(u16::MAX as usize) as u16);
+    }
+
+    fn prune_history(&mut self, now: DateTime<Local>) {
+        if let Some(max_age) = self.max_history_age {
+            let expired = self.history_log.iter().take_while(|(ts, _)| now - *ts > max_age).count();
+            self.history_log.drain(..expired);
+        }
+        if let Some(max) = self.max_history
+            && self.history_log.len() > max {
+            let excess = self.history_log.len() - max;
+            self.history_log.drain(..excess);
+        }
+        self.clamp_history_selection();
+    }
+
+    fn clamp_history_selection(&mut self) {
+        let len = self.history_len();
+        if let Some(i) = self.history_state.selected()
+            && i >= len {
+            self.history_state.select(len.checked_sub(1));
+        }
+    }
+
+    fn history_len(&self) -> usize {
+        match self.history_format {
+            HistoryFormat::Flat => self.tailed_history().len(),
+            HistoryFormat::Grouped => group_history_by_ip(self.tailed_history()).iter()
+                .map(|g| if self.collapsed_history_ips.contains(&g.ip) { 1 } else { 1 + g.entries.len() })
+                .sum(),
+        }
+    }
+
+    /// `--history-format grouped`: a header per IP, followed by its entries
+    /// newest first unless the group is collapsed.
+    fn history_rows(&self) -> Vec<HistoryRow<'_>> {
+        let mut rows = Vec::new();
+        for group in group_history_by_ip(self.tailed_history()) {
+            let collapsed = self.collapsed_history_ips.contains(&group.ip);
+            let entries = group.entries.clone();
+            rows.push(HistoryRow::Header(group));
+            if !collapsed {
+                rows.extend(entries.into_iter().rev().map(HistoryRow::Entry));
+            }
+        }
+        rows
+    }
+
+    fn toggle_selected_history_group(&mut self) {
+        if self.history_format != HistoryFormat::Grouped || self.focus != Focus::HistoryList {
+            return;
+        }
+        let Some(i) = self.history_state.selected() else { return };
+        let ip = match self.history_rows().into_iter().nth(i) {
+            Some(HistoryRow::Header(group)) => group.ip,
+            _ => return,
+        };
+        if !self.collapsed_history_ips.remove(&ip) {
+            self.collapsed_history_ips.insert(ip);
+        }
+    }
+
+    /// The history entries shown in the pane, oldest first.
+    fn tailed_history(&self) -> &[HistoryEntry] {
+        tail_slice(&self.history_log, self.history_tail)
+    }
+
+    /// 0 shows the whole history.
+    fn set_tail(&mut self, n: usize) {
+        self.history_tail = (n > 0).then_some(n);
+        self.clamp_history_selection();
+    }
+
+    fn run_command(&mut self, command: &str, now: Instant) {
+        let result = match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["t", n] => n.parse()
+                .map(|n| self.set_tail(n))
+                .map_err(|_| format!("Invalid tail length '{}'", n)),
+            [] => Ok(()),
+            _ => Err(format!("Unknown command '{}'", command.trim())),
+        };
+        if let Err(e) = result {
+            self.flash_msg = format!("Command Error: {}", e);
+            self.flash_msg_until = Some(now + FLASH_MSG_TTL);
+        }
+    }
+
+    fn status_row_visible(&self) -> bool {
+        self.show_status_bar || self.command_input.is_some()
+    }
+
+    fn tag_history_entry(&self, entry: String) -> String {
+        let tag = entry.split_whitespace().next()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .and_then(|ip| self.tags.get(&ip));
+        match tag {
+            Some(tag) => format!("{} ({})", entry, tag),
+            None => entry,
+        }
+    }
+
+    fn hostname(&self, ip: &IpAddr) -> Option<&str> {
+        self.hostname_cache.get(ip).and_then(|(host, _)| host.as_deref())
+    }
+
+    fn request_hostnames(&mut self, now: Instant) {
+        self.hostname_cache.retain(|_, (_, at)| now.duration_since(*at) < HOSTNAME_TTL);
+
+        let Some(dns_tx) = &self.dns_tx else { return };
+        for conn in &self.active_connections {
+            if !self.hostname_cache.contains_key(&conn.ip) && self.pending_dns.insert(conn.ip) {
+                let _ = dns_tx.send(conn.ip);
+            }
+        }
+    }
+
+    fn store_hostname(&mut self, ip: IpAddr, hostname: Option<String>, now: Instant) {
+        self.pending_dns.remove(&ip);
+        self.hostname_cache.insert(ip, (hostname, now));
+        if self.sort_key == SortKey::Hostname {
+            self.apply_sort();
+        }
+    }
+
+    fn connection_label(&self, conn: &ConnectionEntry) -> String {
+        let mut label = format_connection(conn);
+        if let Some(host) = self.hostname(&conn.ip) {
+            label.push_str(&format!(" {}", host));
+        }
+        if let Some(tag) = self.tags.get(&conn.ip) {
+            label.push_str(&format!(" ({})", tag));
+        }
+        label
+    }
+
+    fn matches_alert(&self, conn: &ConnectionEntry) -> bool {
+        self.alert_regex.as_ref()
+            .is_some_and(|re| re.is_match(&self.connection_label(conn)))
+    }
+
+    fn record_alerts(&mut self, previous: &[ConnectionEntry], now: Instant) {
+        if self.alert_regex.is_some() {
+            let previous: HashSet<&ConnectionEntry> = previous.iter().collect();
+            let new_alerts: Vec<(IpAddr, String)> = self.active_connections.iter()
+                .filter(|c| !previous.contains(c) && self.matches_alert(c))
+                .map(|c| (c.ip, self.connection_label(c)))
+                .collect();
+            for (ip, label) in new_alerts {
+                if self.alert_limiter.check_at(&ip.to_string(), now) {
+                    self.alerts.push((now, label));
+                }
+            }
+        }
+        self.alerts.retain(|(at, _)| now.duration_since(*at) < ALERT_TTL);
+    }
+
+    fn reload_tags_if_changed(&mut self) {
+        let Some(watcher) = self.tags_watcher.as_mut() else { return; };
+        match watcher.poll() {
+            Some(Ok(tags)) => self.tags = tags,
+            Some(Err(e)) => self.last_status_msg = format!("Tags Error: {}", e),
+            None => {}
+        }
+    }
+
+    fn snapshot(&self) -> AppSnapshot {
+        AppSnapshot {
+            target: self.target_name.clone(),
+            timestamp: Local::now().to_rfc3339(),
+            active_connections: self.active_connections.iter().map(format_connection).collect(),
+            history_count: self.history_log.len(),
+            seen_ips_count: self.seen_ips.len(),
+            last_status: self.last_status_msg.clone(),
+            state: self.to_state(),
+        }
+    }
+
+    fn to_state(&self) -> AppState {
+        let mut connection_counts = BTreeMap::new();
+        for (_, entry) in &self.history_log {
+            let fields = history_fields(entry);
+            if fields.direction != "restart" && !fields.ip.is_empty() {
+                *connection_counts.entry(fields.ip.to_string()).or_insert(0) += 1;
+            }
+        }
+        AppState {
+            target_name: self.target_name.clone(),
+            active_connections: self.active_connections.clone(),
+            history_log: self.history_log.clone(),
+            seen_ips: self.seen_ips.iter().cloned().collect(),
+            connection_counts,
+        }
+    }
+
+    fn from_state(state: AppState) -> Self {
+        let mut app = Self::new(state.target_name);
+        app.active_total = state.active_connections.len();
+        app.active_connections = state.active_connections;
+        app.history_log = state.history_log;
+        app.seen_ips = state.seen_ips.into_iter().collect();
+        app
+    }
+
+    fn write_snapshot_if_due(&mut self) {
+        let Some(interval) = self.snapshot_interval else { return; };
+        if self.last_snapshot.elapsed() < interval {
+            return;
+        }
+        self.last_snapshot = Instant::now();
+        if let Err(e) = write_snapshot(&snapshot_path(&self.target_name), &self.snapshot()) {
+            self.last_status_msg = format!("Snapshot Error: {}", e);
+        }
+    }
+
+    fn write_rate_if_due(&mut self, now: Instant) {
+        let Some(rate_file) = &mut self.rate_file else { return };
+        let result = rate_file.write_if_due(now, Local::now().timestamp(), self.active_total, self.seen_ips.len());
+        if let Err(e) = result {
+            self.last_status_msg = format!("Rate File Error: {}", e);
+        }
+    }
+
+    /// Recomputes the conn/s rate every second, so it falls back to zero when
+    /// no updates arrive. Replayed tabs keep theirs.
+    fn refresh_conn_rate_if_due(&mut self, now: Instant) {
+        if self.last_heartbeat.is_none() || now.duration_since(self.last_conn_rate) < CONN_RATE_REFRESH {
+            return;
+        }
+        self.last_conn_rate = now;
+        self.conn_rate = connections_per_second(&self.history_log, RATE_WINDOW_SECS, Local::now());
+    }
+
+    fn send_remote_if_due(&mut self, now: Instant) {
+        let Some(remote) = &self.remote else { return };
+        if now.duration_since(self.last_remote) < self.remote_interval {
+            return;
+        }
+        self.last_remote = now;
+        remote.send(RemoteSnapshot {
+            hostname: remote.hostname().to_string(),
+            target: self.target_name.clone(),
+            timestamp: Local::now().to_rfc3339(),
+            active_connections: self.active_connections.iter().map(format_connection).collect(),
+            history_count: self.history_log.len(),
+            seen_ips_count: self.seen_ips.len(),
+        });
+    }
+
+    fn toggle_asn_view(&mut self) {
+        self.group_by_asn = !self.group_by_asn;
+        self.group_by_geo = false;
+        self.active_state.select(None);
+    }
+
+    fn toggle_geo_view(&mut self) {
+        if self.geo_reader.is_none() {
+            return;
+        }
+        self.group_by_geo = !self.group_by_geo;
+        self.group_by_asn = false;
+        self.active_state.select(None);
+    }
+
+    fn update_geo_cache(&mut self) {
+        let Some(reader) = &self.geo_reader else { return; };
+        for conn in &self.active_connections {
+            if !self.geo_cache.contains_key(&conn.ip)
+                && let Some(country) = geo::lookup_country(reader, conn.ip) {
+                self.geo_cache.insert(conn.ip, country);
+            }
+        }
+    }
+
+    fn geo_rows(&self) -> Vec<GeoRow<'_>> {
+        let mut rows = Vec::new();
+        for group in geo::group_by_country(&self.active_connections, &self.geo_cache) {
+            let collapsed = self.collapsed_countries.contains(&group.country);
+            rows.push(GeoRow::Header { count: group.connections.len(), country: group.country, collapsed });
+            if !collapsed {
+                rows.extend(group.connections.into_iter().map(GeoRow::Connection));
+            }
+        }
+        rows
+    }
+
+    fn toggle_selected_country(&mut self) {
+        if !self.group_by_geo || self.focus != Focus::ActiveList {
+            return;
+        }
+        let Some(i) = self.active_state.selected() else { return };
+        if let Some(GeoRow::Header { country, .. }) = self.geo_rows().into_iter().nth(i)
+            && !self.collapsed_countries.remove(&country) {
+            self.collapsed_countries.insert(country);
+        }
+    }
+
+    fn update_asn_cache(&mut self) {
+        let Some(reader) = &self.asn_reader else { return; };
+        for conn in &self.active_connections {
+            self.asn_cache.entry(conn.ip).or_insert_with(|| lookup_asn(reader, conn.ip));
+        }
+    }
+
+    fn asn_for(&self, ip: &IpAddr) -> Option<&AsnInfo> {
+        self.asn_cache.get(ip).and_then(|a| a.as_ref())
+    }
+
+    fn asn_groups(&self) -> Vec<(Option<&AsnInfo>, Vec<&ConnectionEntry>)> {
+        let mut groups: Vec<(Option<&AsnInfo>, Vec<&ConnectionEntry>)> = Vec::new();
+        for conn in &self.active_connections {
+            let asn = self.asn_for(&conn.ip);
+            match groups.iter_mut().find(|(a, _)| a.map(|i| i.asn) == asn.map(|i| i.asn)) {
+                Some((_, conns)) => conns.push(conn),
+                None => groups.push((asn, vec![conn])),
+            }
+        }
+        groups.sort_by_key(|(a, _)| (a.is_none(), a.map(|i| i.asn)));
+        groups
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HistoryFormat {
+    Flat,
+    Grouped,
+}
+
+fn parse_history_format(s: &str) -> Result<HistoryFormat, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "flat" => Ok(HistoryFormat::Flat),
+        "grouped" => Ok(HistoryFormat::Grouped),
+        _ => Err(format!("expected flat or grouped, got '{}'", s)),
+    }
+}
+
+/// All history entries of one remote IP, oldest first.
+#[derive(Debug)]
+struct HistoryGroup<'a> {
+    ip: IpAddr,
+    first_seen: DateTime<Local>,
+    last_seen: DateTime<Local>,
+    entries: Vec<&'a HistoryEntry>,
+}
+
+enum HistoryRow<'a> {
+    Header(HistoryGroup<'a>),
+    Entry(&'a HistoryEntry),
+}
+
+/// Groups new and closed entries by IP, most recently seen IP first.
+/// Restart and count spike markers belong to no IP and are left out.
+fn group_history_by_ip(log: &[HistoryEntry]) -> Vec<HistoryGroup<'_>> {
+    let mut groups: Vec<HistoryGroup> = Vec::new();
+    let mut index: HashMap<IpAddr, usize> = HashMap::new();
+    for item in log {
+        let fields = history_fields(&item.1);
+        if fields.direction != "new" && fields.direction != "closed" {
+            continue;
+        }
+        let Ok(ip) = fields.ip.parse::<IpAddr>() else { continue };
+        match index.get(&ip) {
+            Some(&i) => {
+                let group = &mut groups[i];
+                group.first_seen = group.first_seen.min(item.0);
+                group.last_seen = group.last_seen.max(item.0);
+                group.entries.push(item);
+            }
+            None => {
+                index.insert(ip, groups.len());
+                groups.push(HistoryGroup { ip, first_seen: item.0, last_seen: item.0, entries: vec![item] });
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.ip.cmp(&b.ip)));
+    groups
+}
+
+enum GeoRow<'a> {
+    Header { country: String, count: usize, collapsed: bool },
+    Connection(&'a ConnectionEntry),
+}
+
+fn port_color(port: u16) -> Color {
+    match port {
+        443 => Color::Blue,
+        80 | 8000..=8999 => Color::Yellow,
+        22 => Color::Red,
+        3306 | 5432 | 27017 => Color::Rgb(255, 165, 0),
+        _ => Color::White,
+    }
+}
+
+const AGE_LEGEND: &str = " <10s=green  <1m=yellow  <5m=orange  5m+=red ";
+
+fn age_color(age: Duration) -> Color {
+    match age.as_secs() {
+        0..10 => Color::Green,
+        10..60 => Color::Yellow,
+        60..300 => Color::Rgb(255, 165, 0),
+        _ => Color::Red,
+    }
+}
+
+const DEFAULT_SUBNET_PALETTE: [Color; 8] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightGreen,
+];
+
+/// Hashes the network part of `ip` (FNV-1a), so every address in the same
+/// subnet gets the same palette entry.
+fn subnet_color(ip: &IpAddr, prefix_len: u8, palette: &[Color]) -> Color {
+    let (mut bytes, bits) = match ip {
+        IpAddr::V4(v4) => (v4.octets().to_vec(), 32),
+        IpAddr::V6(v6) => (v6.octets().to_vec(), 128),
+    };
+    let prefix_len = usize::from(prefix_len).min(bits);
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let keep = prefix_len.saturating_sub(i * 8).min(8);
+        *byte &= !(0xffu8.checked_shr(keep as u32).unwrap_or(0));
+    }
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    if palette.is_empty() {
+        return Color::Reset;
+    }
+    palette[(hash % palette.len() as u64) as usize]
+}
+
+fn parse_palette(s: &str) -> Result<Vec<Color>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.parse::<Color>().map_err(|_| format!("unknown color '{}'", name)))
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|colors| if colors.is_empty() { Err(String::from("no colors given")) } else { Ok(colors) })
+}
+
+/// 8-color terminals have no orange, so 1-5m stays yellow.
+fn age_color_basic(age: Duration) -> Color {
+    match age_color(age) {
+        Color::Rgb(..) => Color::Yellow,
+        color => color,
+    }
+}
+
+fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit")
+}
+
+fn tail_slice<T>(entries: &[T], tail: Option<usize>) -> &[T] {
+    match tail {
+        Some(n) => &entries[entries.len().saturating_sub(n)..],
+        None => entries,
+    }
+}
+
+/// Resolved hostnames come first, alphabetically; the rest are ordered by IP.
+fn sort_by_hostname(entries: &mut [ConnectionEntry], cache: &HashMap<IpAddr, (Option<String>, Instant)>) {
+    entries.sort_by(|a, b| {
+        let host = |c: &ConnectionEntry| cache.get(&c.ip).and_then(|(host, _)| host.as_deref());
+        let (host_a, host_b) = (host(a), host(b));
+        (host_a.is_none(), host_a, a.ip, a.port).cmp(&(host_b.is_none(), host_b, b.ip, b.port))
+    });
+}
+
+/// Bookmarked IPs are kept first so `--top-n` never cuts them.
+fn top_n_recent(mut entries: Vec<ConnectionEntry>, n: usize, bookmarks: &HashSet<String>) -> Vec<ConnectionEntry> {
+    entries.sort_by_key(|c| (!bookmarks.contains(&c.ip.to_string()), std::cmp::Reverse(c.last_seen)));
+    entries.truncate(n);
+    entries
+}
+
+fn parse_column_layout(s: &str) -> Result<(u16, u16), String> {
+    let (left, right) = s.split_once(':').ok_or_else(|| format!("expected LEFT:RIGHT, got '{}'", s))?;
+    let left: u16 = left.trim().parse().map_err(|_| format!("invalid left width '{}'", left))?;
+    let right: u16 = right.trim().parse().map_err(|_| format!("invalid right width '{}'", right))?;
+    if left as u32 + right as u32 != 100 {
+        return Err(format!("widths must add up to 100, got {}", left as u32 + right as u32));
+    }
+    Ok((left, right))
+}
+
+fn parse_border_style(s: &str) -> Result<Option<BorderType>, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "plain" => Ok(Some(BorderType::Plain)),
+        "rounded" => Ok(Some(BorderType::Rounded)),
+        "double" => Ok(Some(BorderType::Double)),
+        "thick" => Ok(Some(BorderType::Thick)),
+        "none" => Ok(None),
+        _ => Err(format!("expected plain, rounded, double, thick or none, got '{}'", s)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Ipv6Display {
+    #[default]
+    Compressed,
+    /// All eight groups, zero-padded to four digits.
+    Expanded,
+    /// IPv4-mapped addresses as plain dotted decimal.
+    Mapped,
+}
+
+/// Set once from `--ipv6-display`; only affects what's drawn, never logs or exports.
+static IPV6_DISPLAY: OnceLock<Ipv6Display> = OnceLock::new();
+
+fn parse_ipv6_display(s: &str) -> Result<Ipv6Display, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "compressed" => Ok(Ipv6Display::Compressed),
+        "expanded" => Ok(Ipv6Display::Expanded),
+        "mapped" => Ok(Ipv6Display::Mapped),
+        _ => Err(format!("expected compressed, expanded or mapped, got '{}'", s)),
+    }
+}
+
+fn format_ipv6(ip: Ipv6Addr, mode: Ipv6Display) -> String {
+    match mode {
+        Ipv6Display::Compressed => ip.to_string(),
+        Ipv6Display::Expanded => ip.segments().iter().map(|s| format!("{:04x}", s)).collect::<Vec<_>>().join(":"),
+        Ipv6Display::Mapped => ip.to_ipv4_mapped().map_or_else(|| ip.to_string(), |v4| v4.to_string()),
+    }
+}
+
+/// `format_endpoint` with IPv6 addresses rendered per `--ipv6-display`.
+fn display_endpoint(ip: IpAddr, port: u16) -> String {
+    display_endpoint_as(ip, port, IPV6_DISPLAY.get().copied().unwrap_or_default())
+}
+
+fn display_endpoint_as(ip: IpAddr, port: u16, mode: Ipv6Display) -> String {
+    let IpAddr::V6(v6) = ip else { return format_endpoint(ip, port) };
+    match v6.to_ipv4_mapped() {
+        Some(v4) if mode == Ipv6Display::Mapped => format_endpoint(IpAddr::V4(v4), port),
+        _ if port == 0 => format_ipv6(v6, mode),
+        _ => format!("[{}]:{}", format_ipv6(v6, mode), port),
+    }
+}
+
+/// Unlike `--ipv6-display mapped`, this changes the address itself, so
+/// history, logs and exports see the IPv4 form too.
+fn strip_ipv4_mapped(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Ip,
+    Port,
+    Proto,
+    State,
+    Age,
+    Bytes,
+    Country,
+    Asn,
+    Hostname,
+}
+
+fn parse_columns(s: &str) -> Result<Vec<Column>, String> {
+    let columns = s.split(',')
+        .map(|name| match name.trim().to_ascii_lowercase().as_str() {
+            "ip" => Ok(Column::Ip),
+            "port" => Ok(Column::Port),
+            "proto" => Ok(Column::Proto),
+            "state" => Ok(Column::State),
+            "age" => Ok(Column::Age),
+            "bytes" => Ok(Column::Bytes),
+            "country" => Ok(Column::Country),
+            "asn" => Ok(Column::Asn),
+            "hostname" => Ok(Column::Hostname),
+            other => Err(format!("unknown column '{}'", other)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !columns.contains(&Column::Ip) {
+        return Err(String::from("the ip column is required"));
+    }
+    Ok(columns)
+}
+
+/// What `render_columns` looks up besides the connection itself.
+struct RenderCtx<'a> {
+    app: &'a App,
+    now: Instant,
+}
+
+/// Joins the selected columns with two spaces; missing values show as `-`.
+fn render_columns(conn: &ConnectionEntry, columns: &[Column], ctx: &RenderCtx) -> String {
+    let app = ctx.app;
+    columns.iter()
+        .map(|column| {
+            let value = match column {
+                Column::Ip => Some(truncate_ip_display(&display_endpoint(conn.ip, 0), app.truncate_ip).into_owned()),
+                Column::Port => match app.services.as_ref().and_then(|s| s.lookup(conn.port, &conn.proto)) {
+                    Some(service) => Some(format!("{}/{}", conn.port, service)),
+                    None => Some(conn.port.to_string()).filter(|_| conn.port != 0),
+                },
+                Column::Proto => Some(conn.proto.clone()).filter(|p| !p.is_empty()),
+                Column::State => Some(conn.state.clone()).filter(|s| !s.is_empty()),
+                Column::Age => Some(format_duration(app.connection_age(conn, ctx.now))),
+                Column::Bytes => conn.size.map(format_bytes),
+                Column::Country => app.geo_cache.get(&conn.ip).cloned(),
+                Column::Asn => app.asn_for(&conn.ip).map(AsnInfo::label),
+                Column::Hostname => app.hostname(&conn.ip).map(str::to_string),
+            };
+            value.unwrap_or_else(|| String::from("-"))
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn history_pct_for_churn(rate: f64) -> u16 {
+    if rate < 1.0 {
+        30
+    } else if rate < 5.0 {
+        40
+    } else {
+        50
+    }
+}
+
+fn step_towards(current: u16, target: u16, step: u16) -> u16 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        current.saturating_sub(step).max(target)
+    }
+}
+
+fn with_retries<T, E>(retries: u32, delay: Duration, mut f: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Err(_) if attempt < retries => {
+                attempt += 1;
+                thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn poll_jitter(rng: &mut impl Rng, jitter_ms: u64) -> Duration {
+    if jitter_ms == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rng.gen_range(0..=jitter_ms))
+}
+
+fn should_sample(poll_counter: u64, sample_rate: u64) -> bool {
+    sample_rate <= 1 || poll_counter.is_multiple_of(sample_rate)
+}
+
+/// Distinct IPs among the connections opened in the last minute.
+fn recent_unique_count(app: &App) -> usize {
+    app.recent_unique_ips.iter().map(|(_, ip)| ip).collect::<HashSet<_>>().len()
+}
+
+fn count_reached(seen: usize, target: Option<usize>) -> bool {
+    target.is_some_and(|n| seen >= n)
+}
+
+/// `height` is the pane's inner height, without whatever borders and titles
+/// its block draws; 0 until the pane has been rendered.
+fn page_size_for_height(height: u16) -> usize {
+    if height == 0 {
+        DEFAULT_PAGE_SIZE
+    } else {
+        height as usize
+    }
+}
+
+/// `*` binds every interface and is shown as the unspecified address.
+fn parse_listening_socket(line: &str) -> Option<ListeningSocket> {
+    let raw = parser::parse_listen_line(line)?;
+    let (host, port) = parser::split_endpoint(&raw.local)?;
+    let ip = match host {
+        "*" if raw.ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        "*" => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        host => host.parse().ok()?,
+    };
+    Some(ListeningSocket { port: port.parse().ok()?, ip, proto: raw.proto })
+}
+
+fn parse_listening_sockets(output: &str) -> Vec<ListeningSocket> {
+    let mut sockets: Vec<ListeningSocket> = output.lines().skip(1).filter_map(parse_listening_socket).collect();
+    sockets.sort();
+    sockets.dedup();
+    sockets
+}
+
+fn parse_unix_socket(line: &str) -> Option<UnixSocketEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.get(4) != Some(&"unix") || fields.len() < 9 {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let mut type_ = "";
+    let mut state = "";
+    for field in &fields[8..] {
+        if let Some(t) = field.strip_prefix("type=") {
+            type_ = t;
+        } else if field.starts_with('(') && field.ends_with(')') {
+            state = field.trim_start_matches('(').trim_end_matches(')');
+        } else {
+            path.push(*field);
+        }
+    }
+
+    Some(UnixSocketEntry {
+        path: if path.is_empty() { String::from("socket") } else { path.join(" ") },
+        type_: type_.to_string(),
+        state: state.to_string(),
+    })
+}
+
+fn parse_connection(line: &str) -> Option<ConnectionEntry> {
+    let raw = parser::parse_lsof_line(line)?;
+    let (local_ip, local_port) = parser::split_endpoint(&raw.local)?;
+    let (ip, port) = parser::split_endpoint(&raw.remote)?;
+
+    Some(ConnectionEntry {
+        local_ip: local_ip.parse().ok()?,
+        local_port: local_port.parse().ok()?,
+        ip: ip.parse().ok()?,
+        port: port.parse().ok()?,
+        proto: raw.proto,
+        state: raw.state,
+        size: raw.size,
+        rate: None,
+        last_seen: Instant::now(),
+    })
+}
+
+fn estimate_rate(
+    prev_sizes: &HashMap<ConnectionKey, (u64, Instant)>,
+    key: &ConnectionKey,
+    size: Option<u64>,
+    now: Instant,
+) -> Option<f64> {
+    let size = size?;
+    let (prev_size, prev_time) = prev_sizes.get(key)?;
+    let elapsed = now.duration_since(*prev_time).as_secs_f64();
+    if elapsed <= 0.0 {
+        return None;
+    }
+    Some(size.saturating_sub(*prev_size) as f64 / elapsed)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format_rate(bytes as f64).trim_end_matches("/s").to_string()
+}
+
+/// `eth0: ↓ 1.2 MB/s ↑ 0.3 MB/s`, one entry per interface; loopback is left out.
+fn format_interface_rates(rates: &[InterfaceRate]) -> String {
+    rates.iter()
+        .filter(|r| r.name != "lo")
+        .map(|r| format!("{}: ↓ {} ↑ {}", r.name, format_rate(r.rx_per_sec), format_rate(r.tx_per_sec)))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn format_rate(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn glob_to_pattern(glob: &str) -> String {
+    let mut pattern = String::new();
+    for c in format!("*{}*", glob).chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c if "\\.+()[]{}^$|".contains(c) => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+fn format_pid_msg(processes: &[(String, String)], sample_rate: u64, excluded_count: usize) -> String {
+    let mut msg = format_matched_processes(processes);
+    if sample_rate > 1 {
+        msg = format!("Sampling 1:{} | {}", sample_rate, msg);
+    }
+    if excluded_count > 0 {
+        msg.push_str(&format!(" | Excluded: {}", excluded_count));
+    }
+    msg
+}
+
+fn format_matched_processes(processes: &[(String, String)]) -> String {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (pid, name) in processes {
+        match groups.iter_mut().find(|(n, _)| n == name) {
+            Some((_, pids)) => pids.push(pid),
+            None => groups.push((name, vec![pid])),
+        }
+    }
+
+    let shown: Vec<String> = groups.iter()
+        .take(MAX_DISPLAYED_PROCESSES)
+        .map(|(name, pids)| format!("{} ({})", name, pids.join(", ")))
+        .collect();
+
+    let mut msg = format!("Monitoring: {}", shown.join(", "));
+    if groups.len() > MAX_DISPLAYED_PROCESSES {
+        msg.push_str(&format!(" and {} more", groups.len() - MAX_DISPLAYED_PROCESSES));
+    }
+    msg
+}
+
+/// History entries start with the remote IP, except for `CLOSED ...`, `RESTART ...` and `── ... ──` records.
+fn history_entry_ip(entry: &str) -> Option<&str> {
+    entry.split_whitespace().next()
+        .filter(|ip| *ip != CLOSED_MARKER && *ip != RESTART_MARKER && *ip != SPIKE_MARKER)
+        .map(strip_brackets)
+}
+
+/// Splits a history entry into template fields; the timestamp is left empty.
+fn history_fields(entry: &str) -> HistoryFields<'_> {
+    if let Some(rest) = entry.strip_prefix(RESTART_MARKER) {
+        return HistoryFields { ip: rest.trim(), direction: "restart", ..Default::default() };
+    }
+    if let Some(rest) = entry.strip_prefix(SPIKE_MARKER) {
+        return HistoryFields { ip: rest.trim_end_matches(SPIKE_MARKER).trim(), direction: "spike", ..Default::default() };
+    }
+    let Some(rest) = entry.strip_prefix(CLOSED_MARKER) else {
+        let ip = entry.split_whitespace().next().map(strip_brackets).unwrap_or("");
+        return HistoryFields { ip, direction: "new", ..Default::default() };
+    };
+    let (addr, duration) = rest.trim().split_once(" after ").unwrap_or((rest.trim(), ""));
+    let (ip, port) = match addr.rsplit_once(':') {
+        Some((ip, port)) if addr.starts_with('[') || !ip.contains(':') => (strip_brackets(ip), port),
+        _ => (addr, ""),
+    };
+    HistoryFields { ip, port, direction: "closed", duration, ..Default::default() }
+}
+
+/// Normalizes IPv6 addresses to compressed form so history loaded from older
+/// logs matches live addresses; anything that isn't an IP is returned as-is.
+fn canonicalize_ip(ip: &str) -> String {
+    ip.parse::<IpAddr>().map_or_else(|_| ip.to_string(), |ip| ip.to_string())
+}
+
+fn strip_brackets(ip: &str) -> &str {
+    ip.strip_prefix('[').and_then(|s| s.strip_suffix(']')).unwrap_or(ip)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{}h {}m {}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+fn held_entry_ip(entry: &str) -> Option<IpAddr> {
+    history_entry_ip(entry)?.parse().ok()
+}
+
+fn closed_entry(conn: &ConnectionEntry, duration: Duration) -> String {
+    format!("{} {} after {}", CLOSED_MARKER, format_connection(conn), format_duration(duration))
+}
+
+fn closed_connections(prev: &HashSet<ConnectionEntry>, curr: &HashSet<ConnectionEntry>) -> Vec<ConnectionEntry> {
+    let mut closed: Vec<ConnectionEntry> = prev.difference(curr).cloned().collect();
+    closed.sort_by_key(|c| (c.ip, c.port));
+    closed
+}
+
+/// Returns `None` when there is no history to measure against.
+fn connections_per_second(history: &[HistoryEntry], window_secs: i64, now: DateTime<Local>) -> Option<f64> {
+    if history.is_empty() {
+        return None;
+    }
+    let since = now - chrono::Duration::seconds(window_secs);
+    let opened = history.iter().rev()
+        .take_while(|(ts, _)| *ts > since)
+        .filter(|(_, entry)| history_entry_ip(entry).is_some())
+        .count();
+    Some(opened as f64 / window_secs as f64)
+}
+
+fn format_history_entry((ts, entry): &HistoryEntry) -> String {
+    format!("[{}] {}", ts.format("%H:%M:%S"), entry)
+}
+
+fn format_history_entry_relative((ts, entry): &HistoryEntry, now: DateTime<Local>) -> String {
+    let elapsed = (now - *ts).to_std().unwrap_or_default();
+    format!("[{}] {}", relative_time(elapsed), entry)
+}
+
+fn relative_time(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 5 {
+        String::from("just now")
+    } else if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else {
+        format!("{}h ago", secs / 3600)
+    }
+}
+
+/// Cuts `s` to `max_len` characters ending in `…`; 0 means no limit.
+fn truncate_ip_display(s: &str, max_len: usize) -> Cow<'_, str> {
+    if max_len == 0 || s.chars().count() <= max_len {
+        return Cow::Borrowed(s);
+    }
+    let mut short: String = s.chars().take(max_len - 1).collect();
+    short.push('…');
+    Cow::Owned(short)
+}
+
+fn gone_suffix(elapsed: Duration) -> String {
+    format!("(gone {}s ago)", elapsed.as_secs())
+}
+
+fn format_connection(conn: &ConnectionEntry) -> String {
+    format_endpoint(conn.ip, conn.port)
+}
+
+/// Port 0 means the port is unknown, so only the address is shown.
+fn format_endpoint(ip: IpAddr, port: u16) -> String {
+    match (ip, port) {
+        (ip, 0) => ip.to_string(),
+        (IpAddr::V4(ip), port) => format!("{}:{}", ip, port),
+        (IpAddr::V6(ip), port) => format!("[{}]:{}", ip, port),
+    }
+}
+
+
+/// A bare port means all interfaces.
+fn listen_addr(listen: &str) -> String {
+    match listen.parse::<u16>() {
+        Ok(port) => format!("0.0.0.0:{}", port),
+        Err(_) => listen.to_string(),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args = Args::parse();
+    match &args.command {
+        Some(CliCommand::Collect(collect)) => {
+            if let Err(e) = collector::run(&listen_addr(&collect.listen)) {
+                eprintln!("Collector failed: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(CliCommand::Check(check)) => {
+            let config = monitor_config(&args, &check.target, &App::new(check.target.clone()), None);
+            std::process::exit(healthcheck::run(&check.target, || find_target(&config)));
+        }
+        None => {}
+    }
+
+    let mut merged = match config::config_path().map(|path| config::load_config(&path)) {
+        Some(Ok(cfg)) => cfg.unwrap_or_default(),
+        None => config::Config::default(),
+        Some(Err(e)) => {
+            eprintln!("Invalid config file {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(name) = &args.profile {
+        match config::load_profile(name) {
+            Ok(profile) => merged = config::merge_config(merged, profile),
+            Err(e) => {
+                eprintln!("Failed to load profile '{}': {}", name, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    config::merge_into_args(&mut args, merged);
+
+    if let Some(name) = &args.save_profile {
+        match config::save_profile(name, &args) {
+            Ok(path) => eprintln!("Saved profile '{}' to {}", name, path.display()),
+            Err(e) => {
+                eprintln!("Failed to save profile '{}': {}", name, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut app = match &args.replay {
+        Some(path) => match snapshot::read_snapshot(path) {
+            Ok(snapshot) => {
+                let mut app = App::from_state(snapshot.state);
+                app.last_status_msg = format!("Replaying {} from {}", path.display(), snapshot.timestamp);
+                app
+            }
+            Err(e) => {
+                eprintln!("Failed to read snapshot {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => App::new(args.target().to_string()),
+    };
+    if let Err(e) = configure_app(&mut app, &args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    // Only the first tab writes the count so extra tabs don't overwrite it.
+    app.count_file = args.watch_count_file.clone().map(CleanupGuard::new);
+    app.rate_file = match open_rate_file(&args) {
+        Ok(rate_file) => rate_file,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let blacklist = load_blacklist(&args.blacklist_file);
+    for err in &blacklist.errors {
+        eprintln!("Blacklist: {}", err);
+    }
+    app.blacklist = Arc::new(blacklist);
+
+    if let Some(path) = &args.log_file {
+        let grep = args.history_grep.as_deref().map(|p| match regex::Regex::new(p) {
+            Ok(re) => re,
+            Err(e) => {
+                eprintln!("Invalid --history-grep pattern: {}", e);
+                std::process::exit(1);
+            }
+        });
+        match logfile::load_history(path, grep.as_ref()).and_then(|h| Ok((h, logfile::open_log(path)?))) {
+            Ok((history, file)) => {
+                app.history_log = history;
+                app.prune_history(Local::now());
+                app.update_seen_ips();
+                if !app.show_history {
+                    app.history_log.clear();
+                }
+                app.log_file = Some(file);
+            }
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let netns = resolve_netns(&args);
+    let mut config = monitor_config(&args, args.target(), &app, netns.clone());
+    config.capture = open_capture(&args);
+    config.tee = open_tee(&args);
+
+    // Hooks and the lock are set up before the headless modes return, so
+    // `--exec-on-*` fire in every mode; the lock only guards the TUI.
+    #[cfg(unix)]
+    let _lock = if args.lock && !args.headless() {
+        let path = lock::lock_path(args.target());
+        match lock::acquire(&path) {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => {
+                eprintln!("Another netmonrs instance is already monitoring '{}' ({})", args.target(), path.display());
+                std::process::exit(3);
+            }
+            Err(e) => {
+                eprintln!("Failed to lock {}: {}", path.display(), e);
+                std::process::exit(3);
+            }
+        }
+    } else {
+        None
+    };
+
+    let pid = if args.exec_on_startup.is_some() || args.exec_on_exit.is_some() { startup_pid(&config) } else { String::new() };
+    if let Some(template) = &args.exec_on_startup {
+        let cmd = hooks::render_lifecycle_command(template, args.target(), &pid);
+        if let Err(e) = hooks::run_hook(&cmd, args.exec_timeout_ms.map(Duration::from_millis)) {
+            eprintln!("--exec-on-startup failed: {}", e);
+        }
+    }
+    let exit_hook = hooks::ExitHook(
+        args.exec_on_exit.as_deref().map(|t| hooks::render_lifecycle_command(t, args.target(), &pid)),
+        args.exec_timeout_ms.map(Duration::from_millis),
+    );
+
+    if args.once {
+        let code = run_once(&config, args.remote_monitor.as_deref());
+        drop(exit_hook);
+        std::process::exit(code);
+    }
+
+    let timeout = args.timeout.map(Duration::from_secs);
+    if args.diff {
+        return exit_on_error(run_diff(config, timeout, HeadlessRemote::take_from(&mut app)), exit_hook);
+    }
+
+    if let Some(secs) = args.report_after {
+        let interval = timeout.map_or(Duration::from_secs(secs), |t| t.min(Duration::from_secs(secs)));
+        let res = run_report(config, interval, args.report_loop, timeout, HeadlessRemote::take_from(&mut app));
+        return exit_on_error(res, exit_hook);
+    }
+
+    if args.quiet {
+        return exit_on_error(run_quiet(config, args.count, timeout, HeadlessRemote::take_from(&mut app)), exit_hook);
+    }
+
+    let mut multi = MultiApp::new(args.clone(), netns, Arc::clone(&app.blacklist));
+    multi.add_tab(app, args.replay.is_none().then_some(config));
+    for target in &args.tab {
+        if let Err(e) = multi.open_tab(target) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Raw mode turns Ctrl+C into a key press; this catches `kill` and
+    // signals that arrive before the terminal is set up.
+    let shutdown = register_shutdown_flag()?;
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+
+    let res = run_app(&mut stdout, multi, &shutdown);
+
+    disable_raw_mode()?;
+    execute!(stdout, LeaveAlternateScreen)?;
+
+    match res {
+        Ok(AppExit::CountReached(n)) => println!("Collected {} unique IPs", n),
+        Ok(AppExit::Quit(Some(msg))) => println!("{}", msg),
+        Ok(AppExit::Quit(None)) => {}
+        Err(err) => {
+            println!("App Error: {:?}", MaskedDebug(&err));
+            drop(exit_hook);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+struct TabState {
+    app: App,
+    rx: mpsc::Receiver<BackgroundEvent>,
+    /// Tells the tab's monitor thread to forget the IPs it has already reported.
+    monitor_reset: Arc<AtomicBool>,
+}
+
+/// One `App` and monitor thread per target, shown as tabs.
+struct MultiApp {
+    tabs: Vec<TabState>,
+    current_tab: usize,
+    /// Target typed after pressing `+`, until Enter or Esc.
+    new_tab_input: Option<String>,
+    args: Args,
+    netns: Option<NetNamespace>,
+    blacklist: Arc<Blacklist>,
+}
+
+impl MultiApp {
+    fn new(args: Args, netns: Option<NetNamespace>, blacklist: Arc<Blacklist>) -> Self {
+        Self { tabs: Vec::new(), current_tab: 0, new_tab_input: None, args, netns, blacklist }
+    }
+
+    /// Quits like `q` once SIGINT or SIGTERM has been received.
+    fn exit_on_shutdown(&mut self, shutdown: &AtomicBool) -> Option<AppExit> {
+        shutdown.load(Ordering::Relaxed).then(|| self.quit())
+    }
+
+    /// Quits like `q` once the first tab has run for `--timeout`.
+    fn exit_on_timeout(&mut self, now: Instant) -> Option<AppExit> {
+        self.tabs.first().is_some_and(|tab| tab.app.timed_out(now)).then(|| self.quit())
+    }
+
+    /// Writes the history `--min-age` held back, then exports the current tab.
+    fn quit(&mut self) -> AppExit {
+        self.flush_held_history();
+        AppExit::Quit(self.current().export_on_exit(Local::now()))
+    }
+
+    fn flush_held_history(&mut self) {
+        for tab in &mut self.tabs {
+            tab.app.flush_held_history();
+        }
+    }
+
+    fn current(&mut self) -> &mut App {
+        &mut self.tabs[self.current_tab].app
+    }
+
+    /// Without a `config` (replaying a snapshot) no monitor thread is started.
+    fn add_tab(&mut self, mut app: App, config: Option<MonitorConfig>) {
+        let depth = config.as_ref().map_or(DEFAULT_EVENT_QUEUE_DEPTH, |c| c.event_queue_depth);
+        let (tx, rx) = mpsc::sync_channel(depth);
+        if app.resolve {
+            app.dns_tx = Some(resolver::spawn_resolver(tx.clone()));
+        }
+        let monitor_reset = config.as_ref().map_or_else(Arc::default, |c| Arc::clone(&c.reset));
+        if let Some(config) = config {
+            spawn_monitor(config, tx);
+        }
+        self.tabs.push(TabState { app, rx, monitor_reset });
+        self.current_tab = self.tabs.len() - 1;
+    }
+
+    fn open_tab(&mut self, target: &str) -> Result<(), String> {
+        let mut app = App::new(target.to_string());
+        configure_app(&mut app, &self.args)?;
+        app.blacklist = Arc::clone(&self.blacklist);
+        let config = monitor_config(&self.args, target, &app, self.netns.clone());
+        self.add_tab(app, Some(config));
+        Ok(())
+    }
+
+    fn submit_new_tab(&mut self) {
+        let Some(target) = self.new_tab_input.take() else { return };
+        let target = target.trim();
+        if target.is_empty() {
+            return;
+        }
+        if let Err(e) = self.open_tab(target) {
+            self.current().last_status_msg = format!("Tab Error: {}", e);
+        }
+    }
+
+    /// The last tab can't be closed; quit with `q` instead.
+    fn close_tab(&mut self) {
+        if self.tabs.len() <= 1 {
+            return;
+        }
+        self.tabs.remove(self.current_tab);
+        self.current_tab = self.current_tab.min(self.tabs.len() - 1);
+    }
+
+    /// `R`: resets the current tab and its monitor thread.
+    fn reset_current(&mut self, now: Instant) {
+        let tab = &mut self.tabs[self.current_tab];
+        tab.app.reset(now);
+        tab.monitor_reset.store(true, Ordering::Relaxed);
+    }
+
+    fn next_tab(&mut self) {
+        self.current_tab = (self.current_tab + 1) % self.tabs.len();
+    }
+
+    fn previous_tab(&mut self) {
+        self.current_tab = (self.current_tab + self.tabs.len() - 1) % self.tabs.len();
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.current_tab = index;
+        }
+    }
+
+    fn tab_titles(&self) -> Vec<String> {
+        self.tabs.iter()
+            .map(|tab| format!("{} ({})", tab.app.target_name, tab.app.active_total))
+            .collect()
+    }
+
+    fn show_tab_bar(&self) -> bool {
+        self.tabs.len() > 1 || self.new_tab_input.is_some()
+    }
+}
+
+enum AppExit {
+    /// Carries the `--export-on-exit` message, if any.
+    Quit(Option<String>),
+    /// Number of unique IPs seen when `--count` was reached.
+    CountReached(usize),
+}
+
+fn run_app(terminal: &mut Stdout, mut multi: MultiApp, shutdown: &AtomicBool) -> io::Result<AppExit> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(terminal))?;
+
+    loop {
+        if let Some(exit) = multi.exit_on_shutdown(shutdown).or_else(|| multi.exit_on_timeout(Instant::now())) {
+            return Ok(exit);
+        }
+        multi.current().prune_new_since(Instant::now());
+        multi.current().prune_lingering(Instant::now());
+        multi.current().prune_recent_ips(Instant::now());
+        terminal.draw(|f| multi_ui(f, &mut multi))?;
+
+        if event::poll(Duration::from_millis(16))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press {
+            if multi.current().command_input.is_some() {
+                handle_command_key(multi.current(), key);
+                continue;
+            }
+            if let Some(input) = multi.new_tab_input.as_mut() {
+                match key.code {
+                    KeyCode::Enter => multi.submit_new_tab(),
+                    KeyCode::Esc => multi.new_tab_input = None,
+                    KeyCode::Backspace => { input.pop(); }
+                    KeyCode::Char(c) => input.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => return Ok(multi.quit()),
+                KeyCode::Tab if multi.tabs.len() > 1 => multi.next_tab(),
+                KeyCode::BackTab => multi.previous_tab(),
+                KeyCode::Char(c @ '1'..='9') => multi.select_tab(c as usize - '1' as usize),
+                KeyCode::Char('+') => multi.new_tab_input = Some(String::new()),
+                KeyCode::Char('X') => multi.close_tab(),
+                KeyCode::Char('R') => multi.reset_current(Instant::now()),
+                _ => handle_key(multi.current(), key),
+            }
+        }
+
+        let mut reached = None;
+        'tabs: for tab in &mut multi.tabs {
+            tab.app.reload_tags_if_changed();
+            tab.app.write_snapshot_if_due();
+            tab.app.send_remote_if_due(Instant::now());
+            tab.app.write_rate_if_due(Instant::now());
+            tab.app.refresh_conn_rate_if_due(Instant::now());
+
+            while let Ok(event) = tab.rx.try_recv() {
+                if let BackgroundEvent::Error(err @ MonitorError::ProcessWaitTimeout(_)) = &event {
+                    return Err(process_wait_error(err));
+                }
+                handle_event(&mut tab.app, event);
+                if tab.app.count_reached() {
+                    reached = Some(tab.app.seen_ips.len());
+                    break 'tabs;
+                }
+            }
+        }
+        if let Some(seen) = reached {
+            multi.flush_held_history();
+            return Ok(AppExit::CountReached(seen));
+        }
+    }
+}
+
+fn handle_key(app: &mut App, key: event::KeyEvent) {
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => app.copy_selected(Instant::now()),
+        KeyCode::Tab | KeyCode::Left | KeyCode::Right => app.toggle_focus(),
+        KeyCode::Down | KeyCode::Char('j') => app.next(),
+        KeyCode::Up | KeyCode::Char('k') => app.previous(),
+        KeyCode::PageDown => app.page_down(),
+        KeyCode::PageUp => app.page_up(),
+        KeyCode::Char('a') => app.toggle_asn_view(),
+        KeyCode::Char('G') => app.toggle_geo_view(),
+        KeyCode::Enter => {
+            app.toggle_selected_country();
+            app.toggle_selected_history_group();
+        }
+        KeyCode::Char('u') => app.show_unix_pane = !app.show_unix_pane,
+        KeyCode::Char('T') => app.relative_times = !app.relative_times,
+        KeyCode::Char('D') => app.history_diff = !app.history_diff,
+        KeyCode::Char('S') => app.show_status_bar = !app.show_status_bar,
+        KeyCode::Char('b') => app.toggle_bookmark(),
+        KeyCode::Char('s') => app.cycle_sort(),
+        KeyCode::Char('<') => app.adjust_layout(-5),
+        KeyCode::Char('>') => app.adjust_layout(5),
+        KeyCode::Char(':') => app.command_input = Some(String::new()),
+        _ => {}
+    }
+}
+
+fn handle_command_key(app: &mut App, key: event::KeyEvent) {
+    let Some(input) = app.command_input.as_mut() else { return };
+    match key.code {
+        KeyCode::Enter => {
+            let command = std::mem::take(input);
+            app.command_input = None;
+            app.run_command(&command, Instant::now());
+        }
+        KeyCode::Esc => app.command_input = None,
+        KeyCode::Backspace => { input.pop(); }
+        KeyCode::Char(c) => input.push(c),
+        _ => {}
+    }
+}
+
+fn handle_event(app: &mut App, event: BackgroundEvent) {
+    match event {
+        BackgroundEvent::DataUpdate { active, new_history_entries, unix_sockets, listening, pid_msg, fd_count, restart_count } => {
+            app.active_total = active.len();
+            app.poll_count += 1;
+            app.last_error = None;
+            let active = match app.top_n {
+                Some(n) => top_n_recent(active, n, &app.bookmarks),
+                None => active,
+            };
+            let selected = app.selected_connection_key();
+            let previous = std::mem::replace(&mut app.active_connections, active);
+            app.apply_sort();
+            app.reselect_connection(selected);
+            app.request_hostnames(Instant::now());
+            app.record_alerts(&previous, Instant::now());
+            app.mark_new_connections(&previous, Instant::now());
+            app.track_recent_ips(&previous, Instant::now());
+            app.track_first_seen(Instant::now());
+            app.track_seen_on_arrival();
+            app.count_public_connections();
+            app.record_lingering(previous, Instant::now());
+            app.active_unix_sockets = unix_sockets;
+            app.listening_sockets = listening;
+            app.fd_count = fd_count;
+            app.process_label = pid_msg.clone();
+            app.last_status_msg = match fd_count {
+                Some(fds) => format!("{} | FDs: {} | Net: {}", pid_msg, fds, app.active_total),
+                None => pid_msg,
+            };
+            if restart_count > 0 {
+                app.last_status_msg.push_str(&format!(" | Restarted {} times", restart_count));
+            }
+            if app.lsof_timeout_count > 0 {
+                app.last_status_msg.push_str(&format!(" | lsof timeouts: {}", app.lsof_timeout_count));
+            }
+            if app.public_connection_count > 0 {
+                app.last_status_msg.push_str(&format!(" | External: {}", app.public_connection_count));
+            }
+            if let Some(tracker) = &mut app.interface_tracker
+                && let Ok(reading) = ifstats::read_proc_net_dev() {
+                let rates = tracker.update(Instant::now(), reading);
+                if !rates.is_empty() {
+                    app.last_status_msg.push_str(&format!(" | {}", format_interface_rates(&rates)));
+                }
+            }
+            if app.fd_count_high() {
+                app.last_status_msg = format!("⚠ High FD count: {}", app.last_status_msg);
+            }
+            app.write_count_file();
+            let spike = app.check_count_spike();
+            if let Some((old, new)) = spike {
+                app.last_status_msg = format!("⚠ Connection count changed by {:+} ({}→{})", new as i64 - old as i64, old, new);
+            }
+            app.update_asn_cache();
+            app.update_geo_cache();
+            app.push_timeline(app.active_connections.len());
+
+            app.record_churn(new_history_entries.len());
+            if let Some(rate_file) = &mut app.rate_file {
+                rate_file.record_new(new_history_entries.len());
+            }
+            let tagged: Vec<HistoryEntry> = app.take_until_count(new_history_entries).into_iter()
+                .map(|(ts, entry)| (ts, app.tag_history_entry(entry)))
+                .collect();
+            app.append_or_hold(tagged);
+            app.release_aged_history(Instant::now());
+            if let Some((old, new)) = spike {
+                app.append_history(vec![(Local::now(), format!("{} count spike: {}→{} {}", SPIKE_MARKER, old, new, SPIKE_MARKER))]);
+            }
+            if app.count_reached() {
+                return;
+            }
+            if app.show_history {
+                app.update_seen_ips();
+            }
+            app.conn_rate = connections_per_second(&app.history_log, RATE_WINDOW_SECS, Local::now());
+        }
+        BackgroundEvent::Error(err) => {
+            app.error_count += 1;
+            if !app.show_status_bar {
+                app.error_flash_until = Some(Instant::now() + ERROR_FLASH_TTL);
+            }
+            app.last_status_msg = err.to_string();
+            app.last_error = Some(err.to_string());
+            if err.clears_active() {
+                app.active_connections.clear();
+                app.active_total = 0;
+                app.write_count_file();
+            }
+        }
+        BackgroundEvent::Warning(msg) => {
+            if msg == LSOF_TIMEOUT_WARNING {
+                app.lsof_timeout_count += 1;
+            }
+            app.last_status_msg = format!("Warning: {}", msg);
+        }
+        BackgroundEvent::DnsResult { ip, hostname } => {
+            app.store_hostname(ip, hostname, Instant::now());
+        }
+        BackgroundEvent::ProcessRestart { old_pid, new_pid } => {
+            app.append_history(vec![(Local::now(), format!("{} {} -> {}", RESTART_MARKER, old_pid, new_pid))]);
+        }
+        BackgroundEvent::ConnectionClosed { connection, duration } => {
+            app.closed_count += 1;
+            app.check_record(&connection, duration, Instant::now());
+            if app.keeps_closed(duration) {
+                app.release_held(|ip| ip == connection.ip);
+                app.append_history(vec![(Local::now(), closed_entry(&connection, duration))]);
+            } else if !app.active_connections.iter().any(|c| c.ip == connection.ip && c.key() != connection.key()) {
+                app.held_history.retain(|(_, entry)| held_entry_ip(entry) != Some(connection.ip));
+            }
+        }
+        BackgroundEvent::Heartbeat { ts } => {
+            app.last_heartbeat = Some(ts);
+        }
+    }
+}
+
+fn register_shutdown_flag() -> io::Result<Arc<AtomicBool>> {
+    register_flag(&[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM])
+}
+
+fn register_flag(signals: &[i32]) -> io::Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    for signal in signals {
+        signal_hook::flag::register(*signal, Arc::clone(&flag))?;
+    }
+    Ok(flag)
+}
+
+/// `--remote-monitor` for `--quiet`, `--diff` and `--report-after`, which
+/// don't keep an `App` to snapshot.
+struct HeadlessRemote {
+    reporter: RemoteReporter,
+    interval: Duration,
+    last_sent: Instant,
+    target: String,
+    active_connections: Vec<String>,
+    seen_ips_count: usize,
+}
+
+impl HeadlessRemote {
+    fn take_from(app: &mut App) -> Option<Self> {
+        Some(Self {
+            reporter: app.remote.take()?,
+            interval: app.remote_interval,
+            last_sent: app.last_remote,
+            target: app.target_name.clone(),
+            active_connections: Vec::new(),
+            seen_ips_count: app.seen_ips.len(),
+        })
+    }
+
+    fn update(&mut self, active: &[ConnectionEntry], new_ips: usize, now: Instant) {
+        self.active_connections = active.iter().map(format_connection).collect();
+        self.seen_ips_count += new_ips;
+        if now.duration_since(self.last_sent) < self.interval {
+            return;
+        }
+        self.last_sent = now;
+        self.reporter.send(RemoteSnapshot {
+            hostname: self.reporter.hostname().to_string(),
+            target: self.target.clone(),
+            timestamp: Local::now().to_rfc3339(),
+            active_connections: self.active_connections.clone(),
+            // Every new IP is one history entry; there is no history otherwise.
+            history_count: self.seen_ips_count,
+            seen_ips_count: self.seen_ips_count,
+        });
+    }
+}
+
+fn run_quiet(config: MonitorConfig, count: Option<usize>, timeout: Option<Duration>, mut remote: Option<HeadlessRemote>) -> io::Result<()> {
+    let shutdown = register_shutdown_flag()?;
+    let mut seen = config.initial_seen.len();
+    let (tx, rx) = mpsc::sync_channel(config.event_queue_depth);
+    spawn_monitor(config, tx);
+    let mut stdout = io::stdout();
+    let started = Instant::now();
+
+    while !shutdown.load(Ordering::Relaxed) && timeout.is_none_or(|t| started.elapsed() < t) {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(BackgroundEvent::DataUpdate { active, new_history_entries, .. }) => {
+                if let Some(remote) = &mut remote {
+                    remote.update(&active, new_history_entries.len(), Instant::now());
+                }
+                for entry in new_history_entries {
+                    if count_reached(seen, count) {
+                        break;
+                    }
+                    writeln!(stdout, "{}", format_history_entry(&entry))?;
+                    seen += 1;
+                }
+                stdout.flush()?;
+                if count_reached(seen, count) {
+                    writeln!(stdout, "Collected {} unique IPs", seen)?;
+                    break;
+                }
+            }
+            Ok(BackgroundEvent::Error(err @ MonitorError::ProcessWaitTimeout(_))) => return Err(process_wait_error(&err)),
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// `--pid-wait` ran out; every mode ends with this error.
+fn process_wait_error(err: &MonitorError) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, err.to_string())
+}
+
+/// Ends a headless mode: errors are printed and exit with code 1, after
+/// running the exit hook that `std::process::exit` would skip.
+fn exit_on_error(res: io::Result<()>, exit_hook: hooks::ExitHook) -> io::Result<()> {
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        drop(exit_hook);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn join_pids(processes: &[(String, String)]) -> String {
+    processes.iter().map(|(pid, _)| pid.as_str()).collect::<Vec<_>>().join(",")
+}
+
+/// What one `lsof -i` poll yields.
+#[derive(Clone, Debug, Default)]
+struct Listing {
+    connections: Vec<ConnectionEntry>,
+    /// The `(LISTEN)` lines, shown with `--show-listening`.
+    listening: Vec<ListeningSocket>,
+}
+
+fn listing_from_output(output: &str) -> Result<Listing, MonitorError> {
+    Ok(Listing { connections: connections_from_output(output)?, listening: parse_listening_sockets(output) })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn list_sockets(source: &LsofSource, pid: &str, tee: Option<&mut Tee>) -> Result<Listing, MonitorError> {
+    let output = get_lsof_output(source, pid).map_err(MonitorError::LsofFailed)?;
+    if let Some(tee) = tee {
+        let _ = tee.write(pid, &output, Utc::now());
+    }
+    listing_from_output(&output)
+}
+
+#[cfg(target_os = "windows")]
+fn list_sockets(source: &LsofSource, pid: &str, tee: Option<&mut Tee>) -> Result<Listing, MonitorError> {
+    match source {
+        LsofSource::File(_) | LsofSource::Mock(_) => {
+            let output = get_lsof_output(source, pid).map_err(MonitorError::LsofFailed)?;
+            if let Some(tee) = tee {
+                let _ = tee.write(pid, &output, Utc::now());
+            }
+            listing_from_output(&output)
+        }
+        LsofSource::Live { .. } => netstat::list_connections(pid)
+            .map(|connections| Listing { connections, listening: Vec::new() })
+            .map_err(MonitorError::LsofFailed),
+    }
+}
+
+fn lsof_command(lsof_bin: &str, no_sudo: bool) -> Command {
+    if no_sudo {
+        Command::new(lsof_bin)
+    } else {
+        let mut cmd = Command::new("sudo");
+        cmd.arg(lsof_bin);
+        cmd
+    }
+}
+
+/// `[sudo] [nsenter --net=<netns>] <lsof_bin>`, for every lsof call that
+/// must see the same namespace as the connection listing. Without sudo,
+/// `nsenter` itself is the spawned program.
+fn lsof_command_in_netns(lsof_bin: &str, netns: Option<&Path>, no_sudo: bool) -> Command {
+    let Some(path) = netns else {
+        return lsof_command(lsof_bin, no_sudo);
+    };
+    let mut cmd = if no_sudo {
+        Command::new("nsenter")
+    } else {
+        let mut cmd = Command::new("sudo");
+        cmd.arg("nsenter");
+        cmd
+    };
+    cmd.arg(format!("--net={}", path.display())).arg(lsof_bin);
+    cmd
+}
+
+/// The connection listing: `[sudo] [nsenter ...] <lsof_bin> -i -P -n -p <pid> <extra_args...>`.
+fn build_lsof_cmd_with_netns(lsof_bin: &str, extra_args: &[String], pid: &str, netns: Option<&Path>, no_sudo: bool) -> Command {
+    let mut cmd = lsof_command_in_netns(lsof_bin, netns, no_sudo);
+    cmd.arg("-i").arg("-P").arg("-n").arg("-p").arg(pid).args(extra_args);
+    cmd
+}
+
+fn parse_lsof_args(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+fn get_lsof_output(source: &LsofSource, pid: &str) -> io::Result<String> {
+    match source {
+        LsofSource::Live { no_sudo, netns, timeout, lsof_bin, lsof_args } => {
+            let cmd = build_lsof_cmd_with_netns(lsof_bin, lsof_args, pid, netns.as_ref().map(NetNamespace::path), *no_sudo);
+            let out = hooks::run_with_timeout(cmd, *timeout)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::NotFound if netns.is_some() && *no_sudo => {
+                        io::Error::new(io::ErrorKind::NotFound, "nsenter not found (install util-linux)")
+                    }
+                    _ => e,
+                })?;
+            // lsof also exits 1 when the process simply has no sockets; only
+            // treat it as a failure when it complained on stderr.
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            if !out.status.success() && !stderr.trim().is_empty() {
+                return Err(io::Error::other(stderr.trim().to_string()));
+            }
+            Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+        }
+        LsofSource::File(path) => fs::read_to_string(path),
+        LsofSource::Mock(net) => Ok(net.lock().unwrap_or_else(|e| e.into_inner()).lsof_output()),
+    }
+}
+
+/// Re-parses the input file only when its mtime moved past the cached reading.
+fn read_input_file(
+    path: &Path,
+    cache: &mut Option<(SystemTime, Listing)>,
+) -> Result<Listing, MonitorError> {
+    let mtime = fs::metadata(path).and_then(|m| m.modified()).map_err(MonitorError::LsofFailed)?;
+    if let Some((read_at, listing)) = cache.as_ref()
+        && mtime <= *read_at {
+        return Ok(listing.clone());
+    }
+    let output = fs::read_to_string(path).map_err(MonitorError::LsofFailed)?;
+    let listing = listing_from_output(&output)?;
+    *cache = Some((mtime, listing.clone()));
+    Ok(listing)
+}
+
+fn discover_processes(config: &MonitorConfig, pattern: &str) -> Result<Vec<(String, String)>, MonitorError> {
+    if let Some(path) = &config.pid_file {
+        return read_pid_file(path).map(|pid| vec![(pid.to_string(), config.target.clone())]);
+    }
+    match &config.source {
+        LsofSource::File(path) => Ok(vec![(String::from("file"), path.display().to_string())]),
+        LsofSource::Mock(_) => Ok(vec![(String::from("1"), String::from("mock"))]),
+        LsofSource::Live { .. } => config.finder.find_pids(pattern),
+    }
+}
+
+/// Applies `--exclude-process` to live lookups; the PID file, input file and
+/// mock network name their own process.
+fn filter_excluded_processes(config: &MonitorConfig, processes: Vec<(String, String)>) -> (Vec<(String, String)>, usize) {
+    if config.pid_file.is_some() || !matches!(config.source, LsofSource::Live { .. }) {
+        return (processes, 0);
+    }
+    process::exclude_processes(processes, &config.exclude_process, config.glob, process::process_names)
+}
+
+fn read_pid_file(path: &Path) -> Result<u32, MonitorError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) if !content.trim().is_empty() => content,
+        _ => return Err(MonitorError::PidFileMissing(path.to_path_buf())),
+    };
+    let line = content.trim();
+    line.parse().map_err(|_| MonitorError::ParseError {
+        line: line.to_string(),
+        reason: format!("PID file {} does not contain a numeric PID", path.display()),
+    })
+}
+
+/// `-U -a -n -p <pid>`, in the same namespace as the connection listing.
+fn build_unix_sockets_cmd(lsof_bin: &str, pid: &str, netns: Option<&Path>, no_sudo: bool) -> Command {
+    let mut cmd = lsof_command_in_netns(lsof_bin, netns, no_sudo);
+    cmd.arg("-U").arg("-a").arg("-n").arg("-p").arg(pid);
+    cmd
+}
+
+#[cfg(not(target_os = "windows"))]
+fn list_unix_sockets(cmd: Command, timeout: Option<Duration>) -> io::Result<Vec<UnixSocketEntry>> {
+    let out = hooks::run_with_timeout(cmd, timeout)?;
+    let output = String::from_utf8_lossy(&out.stdout);
+    Ok(output.lines().skip(1).filter_map(parse_unix_socket).collect())
+}
+
+/// Counts every open file descriptor, not just sockets. Reads `/proc/<pid>/fd`
+/// when it is readable and only runs lsof otherwise.
+#[cfg(not(target_os = "windows"))]
+fn count_fds(lsof_bin: &str, pid: &str, netns: Option<&Path>, no_sudo: bool, timeout: Option<Duration>) -> io::Result<u32> {
+    if let Some(count) = count_proc_fds(Path::new("/proc"), pid) {
+        return Ok(count);
+    }
+    let mut cmd = lsof_command_in_netns(lsof_bin, netns, no_sudo);
+    cmd.arg("-n").arg("-P").arg("-p").arg(pid);
+    let out = hooks::run_with_timeout(cmd, timeout)?;
+    Ok(count_lsof_rows(&String::from_utf8_lossy(&out.stdout)))
+}
+
+#[cfg(target_os = "windows")]
+fn count_fds(_lsof_bin: &str, _pid: &str, _netns: Option<&Path>, _no_sudo: bool, _timeout: Option<Duration>) -> io::Result<u32> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// `None` unless the fd directory of every comma-separated PID is readable.
+fn count_proc_fds(proc_root: &Path, pids: &str) -> Option<u32> {
+    pids.split(',')
+        .map(|pid| fs::read_dir(proc_root.join(pid).join("fd")).ok().map(|dir| dir.count() as u32))
+        .sum()
+}
+
+fn count_lsof_rows(output: &str) -> u32 {
+    output.lines().skip(1).filter(|l| !l.trim().is_empty()).count() as u32
+}
+
+#[cfg(target_os = "windows")]
+fn list_unix_sockets(_cmd: Command, _timeout: Option<Duration>) -> io::Result<Vec<UnixSocketEntry>> {
+    Ok(Vec::new())
+}
+
+/// Fails only when the output has connection lines but none of them parse.
+fn connections_from_output(output: &str) -> Result<Vec<ConnectionEntry>, MonitorError> {
+    let conns = parse_connections(output);
+    if conns.is_empty()
+        && let Some(line) = output.lines().skip(1).find(|l| l.contains("->")) {
+        return Err(MonitorError::ParseError {
+            line: line.to_string(),
+            reason: String::from("unrecognized connection address"),
+        });
+    }
+    Ok(conns)
+}
+
+fn parse_connections(output: &str) -> Vec<ConnectionEntry> {
+    let active: HashSet<ConnectionEntry> = output.lines().skip(1).filter_map(parse_connection).collect();
+    let mut sorted_connections: Vec<ConnectionEntry> = active.into_iter().collect();
+    sorted_connections.sort_by_key(|c| (c.ip, c.port));
+    sorted_connections
+}
+
+/// The target's processes, found the way the monitor thread finds them.
+fn find_target(config: &MonitorConfig) -> Result<Vec<(String, String)>, MonitorError> {
+    let pattern = if config.glob { glob_to_pattern(&config.target) } else { config.target.clone() };
+    discover_processes(config, &pattern).map(|p| filter_excluded_processes(config, p).0)
+}
+
+/// Applies `--strip-ipv4-mapped`, `--port-range` and `--exclude-self` to a
+/// fresh listing, for both the monitor loop and `--once`.
+fn filter_connections(config: &MonitorConfig, connections: Vec<ConnectionEntry>) -> Vec<ConnectionEntry> {
+    connections.into_iter()
+        .map(|mut c| {
+            if config.strip_ipv4_mapped {
+                c.ip = strip_ipv4_mapped(c.ip);
+                c.local_ip = strip_ipv4_mapped(c.local_ip);
+            }
+            c
+        })
+        .filter(|c| config.port_range.contains(c.port))
+        .filter(|c| !config.local_ips.as_ref().is_some_and(|l| l.contains(c.ip)))
+        .collect()
+}
+
+fn run_once(config: &MonitorConfig, remote: Option<&str>) -> i32 {
+    let processes = match find_target(config) {
+        Ok(p) if !p.is_empty() => p,
+        Ok(_) => {
+            eprintln!("Process '{}' not found", config.target);
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    match list_sockets(&config.source, &join_pids(&processes), None) {
+        Ok(Listing { connections, .. }) => {
+            let connections = filter_connections(config, connections);
+            let active: Vec<String> = connections.iter().map(format_connection).collect();
+            for conn in &active {
+                println!("{}", conn);
+            }
+            if let Some(addr) = remote {
+                let seen: HashSet<IpAddr> = connections.iter().map(|c| c.ip).collect();
+                let snapshot = RemoteSnapshot {
+                    hostname: collector::local_hostname(),
+                    target: config.target.clone(),
+                    timestamp: Local::now().to_rfc3339(),
+                    active_connections: active,
+                    history_count: 0,
+                    seen_ips_count: seen.len(),
+                };
+                if let Err(e) = collector::send_now(addr, &snapshot) {
+                    eprintln!("--remote-monitor failed: {}", e);
+                }
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+fn compute_diff(prev: &HashSet<String>, curr: &HashSet<String>) -> (Vec<String>, Vec<String>) {
+    let mut added: Vec<String> = curr.difference(prev).cloned().collect();
+    let mut removed: Vec<String> = prev.difference(curr).cloned().collect();
+    added.sort();
+    removed.sort();
+    (added, removed)
+}
+
+fn run_diff(config: MonitorConfig, timeout: Option<Duration>, mut remote: Option<HeadlessRemote>) -> io::Result<()> {
+    let shutdown = register_shutdown_flag()?;
+    let (tx, rx) = mpsc::sync_channel(config.event_queue_depth);
+    spawn_monitor(config, tx);
+    let mut stdout = io::stdout();
+    let mut prev = HashSet::new();
+    let started = Instant::now();
+
+    while !shutdown.load(Ordering::Relaxed) && timeout.is_none_or(|t| started.elapsed() < t) {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(BackgroundEvent::DataUpdate { active, new_history_entries, .. }) => {
+                if let Some(remote) = &mut remote {
+                    remote.update(&active, new_history_entries.len(), Instant::now());
+                }
+                let curr: HashSet<String> = active.iter().map(format_connection).collect();
+                let (added, removed) = compute_diff(&prev, &curr);
+                if !added.is_empty() || !removed.is_empty() {
+                    writeln!(stdout, "=== {} ===", Local::now().format("%H:%M:%S"))?;
+                    for a in added {
+                        writeln!(stdout, "+ {}", a)?;
+                    }
+                    for r in removed {
+                        writeln!(stdout, "- {}", r)?;
+                    }
+                    writeln!(stdout)?;
+                    stdout.flush()?;
+                }
+                prev = curr;
+            }
+            Ok(BackgroundEvent::Error(err @ MonitorError::ProcessWaitTimeout(_))) => return Err(process_wait_error(&err)),
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// With a `timeout`, a looping report stops after the window that reaches it.
+fn run_report(
+    config: MonitorConfig,
+    interval: Duration,
+    repeat: bool,
+    timeout: Option<Duration>,
+    mut remote: Option<HeadlessRemote>,
+) -> io::Result<()> {
+    let shutdown = register_shutdown_flag()?;
+    let (tx, rx) = mpsc::sync_channel(config.event_queue_depth);
+    spawn_monitor(config, tx);
+    let mut stdout = io::stdout();
+    let mut prev: HashSet<ConnectionEntry> = HashSet::new();
+    let mut opened: Vec<(DateTime<Local>, ConnectionEntry)> = Vec::new();
+    let started = Instant::now();
+    let mut window_start = started;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(BackgroundEvent::DataUpdate { active, new_history_entries, .. }) => {
+                if let Some(remote) = &mut remote {
+                    remote.update(&active, new_history_entries.len(), Instant::now());
+                }
+                let now = Local::now();
+                opened.extend(active.iter().filter(|c| !prev.contains(*c)).map(|c| (now, c.clone())));
+                prev = active.into_iter().collect();
+            }
+            Ok(BackgroundEvent::Error(err @ MonitorError::ProcessWaitTimeout(_))) => return Err(process_wait_error(&err)),
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let timed_out = timeout.is_some_and(|t| started.elapsed() >= t);
+        if window_start.elapsed() >= interval || timed_out {
+            let stats = report::Stats { elapsed: window_start.elapsed() };
+            write!(stdout, "{}", report::generate_report(&opened, &stats))?;
+            stdout.flush()?;
+            if !repeat || timed_out {
+                break;
+            }
+            writeln!(stdout)?;
+            opened.clear();
+            window_start = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct PortRange {
+    min: Option<u16>,
+    max: Option<u16>,
+}
+
+impl PortRange {
+    fn contains(&self, port: u16) -> bool {
+        self.min.is_none_or(|min| port >= min) && self.max.is_none_or(|max| port <= max)
+    }
+
+    fn is_set(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    fn label(&self) -> String {
+        format!("ports {}-{}", self.min.unwrap_or(0), self.max.unwrap_or(u16::MAX))
+    }
+}
+
+enum LsofSource {
+    Live {
+        no_sudo: bool,
+        netns: Option<NetNamespace>,
+        timeout: Option<Duration>,
+        lsof_bin: String,
+        lsof_args: Vec<String>,
+    },
+    File(PathBuf),
+    Mock(Box<Mutex<MockNetwork>>),
+}
+
+struct MonitorConfig {
+    target: String,
+    pid_file: Option<PathBuf>,
+    pid_wait: Option<Duration>,
+    finder: Box<dyn ProcessFinder>,
+    source: LsofSource,
+    initial_seen: HashSet<String>,
+    glob: bool,
+    exclude_process: Vec<String>,
+    unix_sockets: bool,
+    listening: bool,
+    port_range: PortRange,
+    local_ips: Option<LocalIps>,
+    strip_ipv4_mapped: bool,
+    sample_rate: u64,
+    lsof_retries: u32,
+    alert_on_failures: Option<u32>,
+    fd_count: bool,
+    event_queue_depth: usize,
+    jitter_ms: u64,
+    seed: Option<u64>,
+    blacklist: Arc<Blacklist>,
+    capture: Option<BufWriter<File>>,
+    tee: Option<Tee>,
+    syslog: Option<EventLogger>,
+    syslog_limiter: RateLimiter,
+    exec_on_connect: Option<String>,
+    exec_on_disconnect: Option<String>,
+    exec_timeout: Option<Duration>,
+    /// Set by `R`; the monitor thread clears it along with its seen IPs.
+    reset: Arc<AtomicBool>,
+}
+
+fn spawn_monitor(mut config: MonitorConfig, tx: mpsc::SyncSender<BackgroundEvent>) {
+    thread::spawn(move || {
+        // Kept outside the restart loop so IPs aren't reported again after a panic.
+        let mut seen_ips: HashSet<String> = std::mem::take(&mut config.initial_seen)
+            .iter()
+            .map(|ip| canonicalize_ip(strip_brackets(ip)))
+            .collect();
+        let panic_tx = tx.clone();
+        run_restarting(
+            MONITOR_RESTART_DELAY,
+            |restart_count| monitor_loop(&mut config, tx.clone(), &mut seen_ips, restart_count),
+            |_| !matches!(
+                panic_tx.try_send(BackgroundEvent::Error(MonitorError::MonitorPanicked)),
+                Err(mpsc::TrySendError::Disconnected(_))
+            ),
+        );
+    });
+}
+
+/// Runs `work` again after `delay` each time it panics, passing the number
+/// of restarts so far. Gives up when `on_panic` returns false.
+fn run_restarting(delay: Duration, mut work: impl FnMut(u32), mut on_panic: impl FnMut(u32) -> bool) {
+    let mut restarts = 0;
+    while panic::catch_unwind(AssertUnwindSafe(|| work(restarts))).is_err() {
+        restarts += 1;
+        if !on_panic(restarts) {
+            return;
+        }
+        thread::sleep(delay);
+    }
+}
+
+fn monitor_loop(
+    config: &mut MonitorConfig,
+    tx: mpsc::SyncSender<BackgroundEvent>,
+    seen_ips_thread_copy: &mut HashSet<String>,
+    restart_count: u32,
+) {
+    let target = config.target.clone();
+    let pattern = if config.glob { glob_to_pattern(&target) } else { target.clone() };
+    let mut events = EventSender::new(tx, EVENT_SEND_TIMEOUT);
+    let mut prev_active: HashSet<ConnectionEntry> = HashSet::new();
+    let mut prev_sizes: HashMap<ConnectionKey, (u64, Instant)> = HashMap::new();
+    let mut opened_at: HashMap<ConnectionEntry, Instant> = HashMap::new();
+    let mut poll_counter: u64 = 0;
+    let mut file_cache: Option<(SystemTime, Listing)> = None;
+    let mut consecutive_failures: u32 = 0;
+    let mut last_pid: Option<u32> = None;
+    let mut wait_start: Option<Instant> = None;
+    let mut last_heartbeat: Option<Instant> = None;
+    let mut unsent_entries: Vec<HistoryEntry> = Vec::new();
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(rand::thread_rng()).expect("thread_rng never fails"),
+    };
+
+    loop {
+        if config.reset.swap(false, Ordering::Relaxed) {
+            seen_ips_thread_copy.clear();
+            prev_active.clear();
+            prev_sizes.clear();
+            opened_at.clear();
+            unsent_entries.clear();
+        }
+        let start_time = Instant::now();
+        if last_heartbeat.is_none_or(|ts| start_time.duration_since(ts) >= HEARTBEAT_INTERVAL) {
+            events.send(BackgroundEvent::Heartbeat { ts: start_time });
+            last_heartbeat = Some(start_time);
+        }
+        let sampled = should_sample(poll_counter, config.sample_rate);
+        poll_counter = poll_counter.wrapping_add(1);
+
+        match discover_processes(config, &pattern) {
+            Ok(matched_processes) => {
+                let (matched_processes, excluded_count) = filter_excluded_processes(config, matched_processes);
+                if config.pid_file.is_some()
+                    && let Some((pid, _)) = matched_processes.first()
+                    && let Ok(pid) = pid.parse::<u32>() {
+                    if let Some(old_pid) = last_pid.filter(|old| *old != pid) {
+                        events.send(BackgroundEvent::ProcessRestart { old_pid, new_pid: pid });
+                    }
+                    last_pid = Some(pid);
+                }
+                if !matched_processes.is_empty() {
+                    wait_start = None;
+                    let pid = join_pids(&matched_processes);
+                    let unix_handle = match &config.source {
+                        LsofSource::Live { no_sudo, netns, timeout, lsof_bin, .. } if config.unix_sockets => {
+                            let cmd = build_unix_sockets_cmd(lsof_bin, &pid, netns.as_ref().map(NetNamespace::path), *no_sudo);
+                            let timeout = *timeout;
+                            Some(thread::spawn(move || list_unix_sockets(cmd, timeout).unwrap_or_default()))
+                        }
+                        _ => None,
+                    };
+                    let pid = pid.as_str();
+                    let fd_count = match &config.source {
+                        LsofSource::Live { no_sudo, netns, timeout, lsof_bin, .. } if config.fd_count => {
+                            count_fds(lsof_bin, pid, netns.as_ref().map(NetNamespace::path), *no_sudo, *timeout).ok()
+                        }
+                        _ => None,
+                    };
+
+                    let listing = with_retries(config.lsof_retries, LSOF_RETRY_DELAY, || match &config.source {
+                        LsofSource::File(path) => read_input_file(path, &mut file_cache),
+                        LsofSource::Live { .. } | LsofSource::Mock(_) => list_sockets(&config.source, pid, config.tee.as_mut()),
+                    });
+                    consecutive_failures = if listing.is_ok() { 0 } else { consecutive_failures + 1 };
+                    let unix_sockets = unix_handle
+                        .and_then(|h| h.join().ok())
+                        .unwrap_or_default();
+
+                    match listing {
+                        Ok(Listing { connections, listening }) => {
+                            let mut active = HashSet::new();
+                            let mut new_entries = std::mem::take(&mut unsent_entries);
+                            let now = Instant::now();
+                            let mut sizes = HashMap::new();
+                            if let Some(local) = &mut config.local_ips {
+                                local.refresh_if_stale(now);
+                            }
+
+                            for mut conn in filter_connections(config, connections) {
+                                let s = conn.ip.to_string();
+                                conn.rate = estimate_rate(&prev_sizes, &conn.key(), conn.size, now);
+                                conn.last_seen = match prev_active.get(&conn) {
+                                    Some(prev) if !conn.rate.is_some_and(|r| r > 0.0) => prev.last_seen,
+                                    _ => now,
+                                };
+                                if let Some(size) = conn.size {
+                                    sizes.insert(conn.key(), (size, now));
+                                }
+
+                                if sampled && !seen_ips_thread_copy.contains(&s) {
+                                    seen_ips_thread_copy.insert(s.clone());
+                                    new_entries.push((Local::now(), s.clone()));
+
+                                    if let Some(logger) = config.syslog.as_mut()
+                                        && config.syslog_limiter.check(&s)
+                                        && let Err(e) = logger.new_connection(&format_connection(&conn), &target, pid, config.blacklist.contains(conn.ip)) {
+                                        events.send(BackgroundEvent::Warning(e));
+                                    }
+                                    if let Some(template) = &config.exec_on_connect {
+                                        let cmd = hooks::render_command(template, &s, conn.port, &conn.proto);
+                                        hooks::spawn_hook(cmd, config.exec_timeout);
+                                    }
+                                }
+                                opened_at.entry(conn.clone()).or_insert(now);
+                                active.insert(conn);
+                            }
+
+                            for conn in closed_connections(&prev_active, &active) {
+                                let duration = opened_at.remove(&conn)
+                                    .map(|at| now.duration_since(at))
+                                    .unwrap_or_default();
+                                events.send(BackgroundEvent::ConnectionClosed { connection: conn, duration });
+                            }
+
+                            if let Some(w) = config.capture.as_mut() {
+                                let skipped = record_capture(w, &prev_active, &active);
+                                if skipped > 0 {
+                                    events.send(BackgroundEvent::Warning(format!("pcap: skipped {} packet(s) with mixed IPv4/IPv6 endpoints", skipped)));
+                                }
+                            }
+
+                            if let Some(template) = &config.exec_on_disconnect {
+                                let current_ips: HashSet<IpAddr> = active.iter().map(|c| c.ip).collect();
+                                let mut fired = HashSet::new();
+                                for conn in &prev_active {
+                                    if !current_ips.contains(&conn.ip) && fired.insert(conn.ip) {
+                                        let cmd = hooks::render_command(template, &conn.ip.to_string(), conn.port, &conn.proto);
+                                        hooks::spawn_hook(cmd, config.exec_timeout);
+                                    }
+                                }
+                            }
+
+                            let mut sorted_connections: Vec<ConnectionEntry> = active.iter().cloned().collect();
+                            sorted_connections.sort_by_key(|c| (c.ip, c.port));
+                            prev_active = active;
+                            prev_sizes = sizes;
+
+                            let sent = events.send(BackgroundEvent::DataUpdate {
+                                active: sorted_connections,
+                                new_history_entries: new_entries.clone(),
+                                unix_sockets,
+                                listening: if config.listening { listening } else { Vec::new() },
+                                pid_msg: format_pid_msg(&matched_processes, config.sample_rate, excluded_count),
+                                fd_count,
+                                restart_count,
+                            });
+                            // These IPs are already in the seen set, so they'd
+                            // never be reported again if the update was dropped.
+                            if !sent {
+                                unsent_entries = new_entries;
+                            }
+                        }
+                        Err(MonitorError::LsofFailed(e)) if e.kind() == io::ErrorKind::TimedOut => {
+                            events.send(BackgroundEvent::Warning(String::from(LSOF_TIMEOUT_WARNING)));
+                        }
+                        Err(e) => {
+                            events.send(BackgroundEvent::Error(e));
+                            if config.alert_on_failures.is_some_and(|m| consecutive_failures > m) {
+                                events.send(BackgroundEvent::Warning(format!("lsof failed {} times in a row", consecutive_failures)));
+                            }
+                        }
+                    }
+                } else if wait_timed_out(&mut wait_start, config.pid_wait, start_time) {
+                    events.send(BackgroundEvent::Error(MonitorError::ProcessWaitTimeout(target.clone())));
+                    break;
+                } else {
+                    events.send(BackgroundEvent::Error(MonitorError::PgrepNoPid(target.clone())));
+                }
+            }
+            Err(e) => { events.send(BackgroundEvent::Error(e)); }
+        }
+        if events.is_closed() {
+            break;
+        }
+
+        let elapsed = start_time.elapsed();
+        let delay = Duration::from_secs(1).saturating_sub(elapsed) + poll_jitter(&mut rng, config.jitter_ms);
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// PIDs of the target at startup for `{pid}`, empty if it isn't running.
+fn startup_pid(config: &MonitorConfig) -> String {
+    let pattern = if config.glob { glob_to_pattern(&config.target) } else { config.target.clone() };
+    discover_processes(config, &pattern).map(|p| join_pids(&p)).unwrap_or_default()
+}
+
+/// Starts the wait on the first miss; true once it has lasted longer than `limit`.
+fn wait_timed_out(wait_start: &mut Option<Instant>, limit: Option<Duration>, now: Instant) -> bool {
+    let Some(limit) = limit else { return false };
+    now.duration_since(*wait_start.get_or_insert(now)) > limit
+}
+
+/// Applies the display and filtering options shared by every tab.
+fn configure_app(app: &mut App, args: &Args) -> Result<(), String> {
+    app.show_asn = args.show_asn;
+    app.show_timeline = !args.no_timeline;
+    app.show_history = !args.no_history;
+    if let Some(layout) = &args.column_layout {
+        app.layout_ratio = parse_column_layout(layout).map_err(|e| format!("Invalid --column-layout: {}", e))?;
+    }
+    if let Some(style) = &args.border_style {
+        app.border_type = parse_border_style(style).map_err(|e| format!("Invalid --border-style: {}", e))?;
+    }
+    if let Some(mode) = &args.ipv6_display {
+        let mode = parse_ipv6_display(mode).map_err(|e| format!("Invalid --ipv6-display: {}", e))?;
+        // Every tab gets the same args, so only the first set matters.
+        let _ = IPV6_DISPLAY.set(mode);
+    }
+    mask::set_mode(if args.mask_ips { MaskMode::Masked } else { MaskMode::Plain });
+    if let Some(format) = &args.history_format {
+        app.history_format = parse_history_format(format).map_err(|e| format!("Invalid --history-format: {}", e))?;
+    }
+    if let Some(template) = &args.format_history {
+        let tokens = template::parse_template(template).map_err(|e| format!("Invalid --format-history: {}", e))?;
+        app.history_template = Some(tokens);
+    }
+    if let Some(template) = &args.format_status {
+        let tokens = template::parse_status_template(template).map_err(|e| format!("Invalid --format-status: {}", e))?;
+        app.status_template = Some(tokens);
+    }
+    app.port_colors = !args.no_port_colors;
+    app.truncate_ip = args.truncate_ip.unwrap_or(0);
+    if let Some(list) = &args.columns {
+        app.columns = parse_columns(list).map_err(|e| format!("Invalid --columns: {}", e))?;
+    }
+    if args.resolve_ports {
+        // Without /etc/services only the built-in port names are shown.
+        app.services = Some(ServiceNames::load(Path::new(services::ETC_SERVICES)).unwrap_or_default());
+    }
+    app.new_highlight = Duration::from_secs(args.color_new_connections.unwrap_or(0));
+    app.linger = Duration::from_secs(args.linger_secs.unwrap_or(0));
+    app.color_by_age = args.color_by_age;
+    app.color_seen = args.color_seen;
+    if let Some(prefix_len) = args.color_by_subnet {
+        if prefix_len > 128 {
+            return Err(format!("Invalid --color-by-subnet: prefix length {} is longer than 128", prefix_len));
+        }
+        app.color_by_subnet = Some(prefix_len);
+    }
+    if let Some(palette) = &args.color_palette {
+        app.subnet_palette = parse_palette(palette).map_err(|e| format!("Invalid --color-palette: {}", e))?;
+    }
+    app.warn_public = args.warn_private_to_public;
+    app.allowed_public_ips = args.allow_public_ip.iter().copied().collect();
+    app.max_history = args.max_history;
+    app.max_history_age = args.max_history_age.map(|secs| chrono::Duration::seconds(secs as i64));
+    app.min_age = args.min_age.map(Duration::from_secs);
+    app.min_age_invert = args.min_age_invert;
+    app.snapshot_interval = args.snapshot_interval.map(Duration::from_secs);
+    app.remote = args.remote_monitor.clone().map(RemoteReporter::spawn);
+    if let Some(secs) = args.remote_interval {
+        app.remote_interval = Duration::from_secs(secs);
+    }
+    app.show_unix_pane = args.show_unix_sockets;
+    app.count_target = args.count;
+    app.timeout = args.timeout.map(Duration::from_secs);
+    app.fd_alert = args.alert_fd_count;
+    app.interface_tracker = args.interface_stats.then(InterfaceTracker::default);
+    app.count_delta_alert = args.watch_connections_delta;
+    app.show_status_bar = !args.hide_status_bar;
+    if args.export_on_exit {
+        app.export_dir = Some(args.export_path.clone().unwrap_or_else(|| PathBuf::from(".")));
+    }
+    app.auto_layout = args.auto_layout;
+    app.top_n = args.top_n;
+    app.set_tail(args.tail.unwrap_or(0));
+    app.resolve = args.resolve;
+
+    if let Some(path) = &args.save_bookmarks {
+        app.bookmarks = bookmarks::load_bookmarks(path)
+            .map_err(|e| format!("Failed to load bookmarks {}: {}", path.display(), e))?;
+        app.bookmarks_path = Some(path.clone());
+    }
+    if let Some(path) = &args.all_time_record {
+        app.record = record::load_record(path);
+        app.record_path = Some(path.clone());
+    }
+
+    let port_range = PortRange { min: args.min_port, max: args.max_port };
+    if let (Some(min), Some(max)) = (port_range.min, port_range.max)
+        && min > max {
+        return Err(format!("--min-port ({}) must not be greater than --max-port ({})", min, max));
+    }
+    app.port_range = port_range;
+    if let Some(pattern) = &args.alert_regex {
+        let re = regex::Regex::new(pattern).map_err(|e| format!("Invalid --alert-regex pattern: {}", e))?;
+        app.alert_regex = Some(re);
+    }
+    app.alert_limiter = RateLimiter::new(notify_rate_limit(args));
+
+    if let Some(path) = &args.tags_file {
+        app.tags = load_tags(path).map_err(|e| format!("Failed to load tags: {}", e))?;
+        app.tags_watcher = Some(TagsWatcher::new(path.clone()));
+    }
+    if let Some(path) = &args.asn_db {
+        let reader = open_asn_db(path).map_err(|e| format!("Failed to open ASN database {}: {}", path.display(), e))?;
+        app.asn_reader = Some(reader);
+    }
+    if let Some(path) = &args.geoip_db {
+        let reader = geo::open_geoip_db(path).map_err(|e| format!("Failed to open GeoIP database {}: {}", path.display(), e))?;
+        app.geo_reader = Some(reader);
+    }
+    Ok(())
+}
+
+/// The pcap capture and `--tee` files are left to the caller so extra tabs
+/// don't write to them.
+fn monitor_config(args: &Args, target: &str, app: &App, netns: Option<NetNamespace>) -> MonitorConfig {
+    MonitorConfig {
+        target: target.to_string(),
+        initial_seen: app.seen_ips.clone(),
+        glob: args.glob,
+        exclude_process: args.exclude_process.clone(),
+        pid_file: args.watch_pid_file.clone(),
+        pid_wait: args.pid_wait.filter(|secs| *secs > 0).map(Duration::from_secs),
+        finder: if args.fuzzy {
+            Box::new(FuzzyFinder)
+        } else {
+            // Globs are already converted to a regex by the monitor thread.
+            Box::new(PgrepFinder { use_regex: true })
+        },
+        source: match &args.input_file {
+            _ if args.mock => LsofSource::Mock(Box::new(Mutex::new(MockNetwork::new(args.mock_seed)))),
+            Some(path) => LsofSource::File(path.clone()),
+            None => LsofSource::Live {
+                no_sudo: args.no_sudo,
+                netns,
+                timeout: Some(args.lsof_timeout.unwrap_or(DEFAULT_LSOF_TIMEOUT_MS))
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis),
+                lsof_bin: args.lsof_cmd.clone().unwrap_or_else(|| String::from(DEFAULT_LSOF_CMD)),
+                lsof_args: args.lsof_args.as_deref().map(parse_lsof_args).unwrap_or_default(),
+            },
+        },
+        unix_sockets: args.show_unix_sockets && args.input_file.is_none() && !args.mock,
+        listening: args.show_listening,
+        port_range: app.port_range,
+        local_ips: args.exclude_self.then(LocalIps::detect),
+        strip_ipv4_mapped: args.strip_ipv4_mapped,
+        sample_rate: args.sample_rate.unwrap_or(1),
+        lsof_retries: args.retry_on_error.unwrap_or(0),
+        alert_on_failures: args.alert_on_failures,
+        fd_count: args.show_fd_count || args.alert_fd_count.is_some(),
+        event_queue_depth: args.event_queue_depth.map_or(DEFAULT_EVENT_QUEUE_DEPTH, |n| n as usize),
+        jitter_ms: args.watch_interval_jitter.unwrap_or(0),
+        seed: args.seed,
+        blacklist: Arc::clone(&app.blacklist),
+        capture: None,
+        tee: None,
+        // The TUI owns the terminal, so syslog failures go to its status bar.
+        syslog: args.syslog.then(|| EventLogger::init(args.headless() || !io::stderr().is_terminal())),
+        syslog_limiter: RateLimiter::new(notify_rate_limit(args)),
+        exec_on_connect: args.exec_on_connect.clone(),
+        exec_on_disconnect: args.exec_on_disconnect.clone(),
+        exec_timeout: args.exec_timeout_ms.map(Duration::from_millis),
+        reset: Arc::new(AtomicBool::new(false)),
+    }
+}
+
+fn notify_rate_limit(args: &Args) -> usize {
+    args.notify_rate_limit.map_or(DEFAULT_NOTIFY_RATE_LIMIT, |n| n as usize)
+}
+
+fn resolve_netns(args: &Args) -> Option<NetNamespace> {
+    let name = match (&args.container, &args.netns) {
+        (Some(name), _) => name,
+        (None, Some(path)) => return Some(NetNamespace::from_path(path.clone())),
+        (None, None) => return None,
+    };
+    match NetNamespace::for_container(name) {
+        Ok(netns) => Some(netns),
+        Err(e) => {
+            eprintln!("Failed to find container '{}': {}", name, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "pcap-output")]
+fn open_capture(args: &Args) -> Option<BufWriter<File>> {
+    let path = args.capture_file.as_ref()?;
+    let capture = File::create(path).and_then(|f| {
+        let mut w = BufWriter::new(f);
+        pcap::write_pcap_global_header(&mut w)?;
+        Ok(w)
+    });
+    match capture {
+        Ok(w) => Some(w),
+        Err(e) => {
+            eprintln!("Failed to create capture file {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "pcap-output"))]
+fn open_capture(_args: &Args) -> Option<BufWriter<File>> {
+    None
+}
+
+fn open_rate_file(args: &Args) -> Result<Option<RateFile>, String> {
+    let Some(path) = &args.rate_file else { return Ok(None) };
+    let format = args.rate_file_format.as_deref()
+        .map(parse_rate_format)
+        .transpose()
+        .map_err(|e| format!("Invalid --rate-file-format: {}", e))?
+        .unwrap_or_default();
+    RateFile::create(path.clone(), format, Instant::now())
+        .map(Some)
+        .map_err(|e| format!("Failed to open rate file {}: {}", path.display(), e))
+}
+
+fn open_tee(args: &Args) -> Option<Tee> {
+    let path = args.tee.as_ref()?;
+    let max_bytes = args.tee_max_mb.map(|mb| mb * 1024 * 1024);
+    match Tee::open(path.clone(), max_bytes) {
+        Ok(tee) => Some(tee),
+        Err(e) => {
+            eprintln!("Failed to open tee file {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns how many packets were skipped because their endpoints mix IPv4 and IPv6.
+#[cfg(feature = "pcap-output")]
+fn record_capture(w: &mut BufWriter<File>, prev: &HashSet<ConnectionEntry>, curr: &HashSet<ConnectionEntry>) -> usize {
+    use io::Write;
+
+    let ts = Local::now().timestamp_micros() as u64;
+    let mut skipped = 0;
+    let mut count = |res: io::Result<()>| {
+        if res.is_err_and(|e| e.kind() == io::ErrorKind::InvalidInput) {
+            skipped += 1;
+        }
+    };
+    for conn in curr.difference(prev) {
+        count(pcap::write_synthetic_syn(w, (conn.local_ip, conn.local_port), (conn.ip, conn.port), ts));
+    }
+    for conn in prev.difference(curr) {
+        count(pcap::write_synthetic_fin(w, (conn.local_ip, conn.local_port), (conn.ip, conn.port), ts));
+    }
+    let _ = w.flush();
+    skipped
+}
+
+#[cfg(not(feature = "pcap-output"))]
+fn record_capture(_w: &mut BufWriter<File>, _prev: &HashSet<ConnectionEntry>, _curr: &HashSet<ConnectionEntry>) -> usize {
+    0
+}
+
+fn multi_ui(f: &mut Frame, multi: &mut MultiApp) {
+    let mut area = f.size();
+    if multi.show_tab_bar() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
+            .split(area);
+        match &multi.new_tab_input {
+            Some(input) => f.render_widget(
+                ratatui::widgets::Paragraph::new(format!("New tab target: {}_", input))
+                    .style(Style::default().fg(Color::Yellow)),
+                chunks[0],
+            ),
+            None => f.render_widget(
+                Tabs::new(multi.tab_titles())
+                    .select(multi.current_tab)
+                    .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                chunks[0],
+            ),
+        }
+        area = chunks[1];
+    }
+    ui(f, multi.current(), area);
+}
+
+/// The `--show-listening` rows and the divider below them.
+fn listening_items<'a>(app: &App) -> Vec<ListItem<'a>> {
+    if app.listening_sockets.is_empty() {
+        return Vec::new();
+    }
+    app.listening_sockets.iter()
+        .map(|l| ListItem::new(format!("🔊 {}", l)).style(Style::default().fg(Color::Cyan)))
+        .chain(std::iter::once(ListItem::new("─── listening ───").style(Style::default().fg(Color::DarkGray))))
+        .collect()
+}
+
+/// Prefixes an orange ⚠ for `--warn-private-to-public`.
+fn public_warning_line<'a>(app: &App, c: &ConnectionEntry, text: String) -> Line<'a> {
+    if app.is_unexpected_public(c.ip) {
+        Line::from(vec![Span::styled("⚠ ", Style::default().fg(Color::Rgb(255, 165, 0))), Span::raw(text)])
+    } else {
+        Line::from(text)
+    }
+}
+
+fn style_active_item<'a>(app: &App, c: &ConnectionEntry, now: Instant, item: ListItem<'a>) -> ListItem<'a> {
+    if app.blacklist.contains(c.ip) {
+        item.style(Style::default().fg(Color::Red))
+    } else if app.matches_alert(c) {
+        item.style(Style::default().fg(Color::Magenta))
+    } else if app.is_new(c) {
+        item.style(Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD))
+    } else if app.color_by_age {
+        let age = app.connection_age(c, now);
+        let color = if app.truecolor { age_color(age) } else { age_color_basic(age) };
+        item.style(Style::default().fg(color))
+    } else if app.color_seen {
+        item.style(Style::default().fg(app.seen_color(c)))
+    } else if let Some(prefix_len) = app.color_by_subnet {
+        item.style(Style::default().fg(subnet_color(&c.ip, prefix_len, &app.subnet_palette)))
+    } else if app.port_colors {
+        item.style(Style::default().fg(port_color(c.port)))
+    } else {
+        item
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App, area: Rect) {
+    let mut constraints = vec![Constraint::Min(1)];
+    if app.show_timeline {
+        constraints.push(Constraint::Length(TIMELINE_HEIGHT));
+    }
+    if app.status_row_visible() {
+        constraints.push(Constraint::Length(1));
+    }
+
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+
+    let unix_pct = if app.show_unix_pane { 30 } else { 0 };
+    let history_pct = if app.show_history { app.layout_ratio.1 * (100 - unix_pct) / 100 } else { 0 };
+    let mut list_constraints = vec![Constraint::Percentage(100 - unix_pct - history_pct)];
+    if app.show_history {
+        list_constraints.push(Constraint::Percentage(history_pct));
+    }
+    if app.show_unix_pane {
+        list_constraints.push(Constraint::Percentage(unix_pct));
+    }
+    let list_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(list_constraints)
+        .split(main_chunks[0]);
+
+    let active_style = if app.focus == Focus::ActiveList {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let listening = if app.group_by_geo || app.group_by_asn { Vec::new() } else { listening_items(app) };
+    let listening_rows = listening.len();
+    let active_items: Vec<ListItem> = if app.group_by_geo {
+        app.geo_rows().into_iter()
+            .map(|row| match row {
+                GeoRow::Header { country, count, collapsed } => {
+                    let arrow = if collapsed { "▸" } else { "▾" };
+                    ListItem::new(format!("{} {} ({})", arrow, country, count))
+                        .style(Style::default().add_modifier(Modifier::BOLD))
+                }
+                GeoRow::Connection(c) => ListItem::new(format!("   🚀 {}", c)),
+            })
+            .collect()
+    } else if app.group_by_asn {
+        app.asn_groups().into_iter()
+            .flat_map(|(asn, conns)| {
+                let header = match asn {
+                    Some(info) => format!("{} ({})", info.label(), conns.len()),
+                    None => format!("Unknown AS ({})", conns.len()),
+                };
+                std::iter::once(ListItem::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+                    .chain(conns.into_iter().map(|c| ListItem::new(format!("   🚀 {}", c))))
+            })
+            .collect()
+    } else {
+        let now = Instant::now();
+        let ctx = RenderCtx { app, now };
+        listening.into_iter().chain(app.active_connections.iter()
+            .map(|c| {
+                let marker = if app.is_bookmarked(&c.ip) { "★ " } else { "" };
+                if !app.columns.is_empty() {
+                    let text = format!("{}🚀 {}", marker, render_columns(c, &app.columns, &ctx));
+                    return style_active_item(app, c, now, ListItem::new(public_warning_line(app, c, text)));
+                }
+                let label = match app.services.as_ref().and_then(|s| s.lookup(c.port, &c.proto)) {
+                    Some(service) => c.label_with_service(service),
+                    None => c.to_string(),
+                };
+                let ip = display_endpoint(c.ip, 0);
+                let label = match truncate_ip_display(&ip, app.truncate_ip) {
+                    Cow::Owned(short) => label.replacen(&ip, &short, 1),
+                    Cow::Borrowed(_) => label,
+                };
+                let mut text = format!("{}🚀 {}", marker, label);
+                if let Some(host) = app.hostname(&c.ip) {
+                    text.push_str(&format!(" {}", host));
+                }
+                match c.rate {
+                    Some(rate) if rate > 0.0 => text.push_str(&format!(" ↑ {}", format_rate(rate))),
+                    Some(_) => {}
+                    None => text.push_str(" N/A"),
+                }
+                if let Some(info) = app.asn_for(&c.ip).filter(|_| app.show_asn) {
+                    text.push_str(&format!(" [{}]", info.label()));
+                }
+                if let Some(tag) = app.tags.get(&c.ip) {
+                    text.push_str(&format!(" ({})", tag));
+                }
+                style_active_item(app, c, now, ListItem::new(public_warning_line(app, c, text)))
+            }))
+            .chain(app.lingering_connections().into_iter().map(|(c, gone)| {
+                ListItem::new(format!("   {} {}", c, gone_suffix(now.duration_since(gone))))
+                    .style(Style::default().fg(Color::DarkGray))
+            }))
+            .collect()
+    };
+
+    let title_style = if app.error_flash_until.is_some_and(|until| Instant::now() < until) {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    let mut active_block = pane_block(app.border_type)
+        .title(active_title(app))
+        .title_style(title_style)
+        .border_style(active_style);
+    if app.color_by_age {
+        active_block = active_block.title_bottom(AGE_LEGEND);
+    }
+    app.active_pane_height = active_block.inner(list_chunks[0]).height;
+    let list_active = List::new(active_items)
+        .block(active_block)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+
+    // The selection indexes the connections, which start below the listening rows.
+    let mut active_state = app.active_state.clone();
+    active_state.select(app.active_state.selected().map(|i| i + listening_rows));
+    f.render_stateful_widget(list_active, list_chunks[0], &mut active_state);
+    *app.active_state.offset_mut() = active_state.offset();
+
+
+    if app.show_history && app.history_diff {
+        render_history_diff(f, app, list_chunks[1]);
+    } else if app.show_history {
+        render_history(f, app, list_chunks[1]);
+    }
+
+
+    if app.show_unix_pane {
+        let unix_items: Vec<ListItem> = app.active_unix_sockets.iter()
+            .map(|u| {
+                let mut text = format!("🔌 {}", u.path);
+                if !u.type_.is_empty() {
+                    text.push_str(&format!(" [{}]", u.type_));
+                }
+                if !u.state.is_empty() {
+                    text.push_str(&format!(" {}", u.state));
+                }
+                ListItem::new(text)
+            })
+            .collect();
+
+        let list_unix = List::new(unix_items)
+            .block(pane_block(app.border_type)
+                .title(format!(" Unix Sockets [{}] ({}) ", app.target_name, app.active_unix_sockets.len()))
+                .border_style(Style::default().fg(Color::DarkGray)));
+
+        f.render_widget(list_unix, list_chunks[list_chunks.len() - 1]);
+    }
+
+
+    if app.show_timeline {
+        render_timeline(f, app, main_chunks[1]);
+    }
+
+    if !app.status_row_visible() {
+        return;
+    }
+    let status_area = main_chunks[main_chunks.len() - 1];
+    if let Some(input) = &app.command_input {
+        f.render_widget(
+            ratatui::widgets::Paragraph::new(format!(":{}_", input)).style(Style::default().fg(Color::Yellow)),
+            status_area,
+        );
+        return;
+    }
+    if let Some(stalled) = app.stalled_for(Instant::now()) {
+        f.render_widget(
+            ratatui::widgets::Paragraph::new(format!("⚠ Monitor thread may be stalled (last heartbeat {}s ago)", stalled.as_secs()))
+                .style(Style::default().fg(Color::Rgb(255, 165, 0))),
+            status_area,
+        );
+        return;
+    }
+
+
+    let status_style = if app.last_status_msg.contains("Error") || app.last_status_msg.contains("Wait") {
+        Style::default().fg(Color::Red)
+    } else if app.fd_count_high() || app.count_spike {
+        Style::default().fg(Color::Rgb(255, 165, 0))
+    } else {
+        Style::default().fg(Color::Green)
+    };
+
+    let show_flash_msg = app.flash_msg_until.is_some_and(|until| Instant::now() < until);
+    let mode = mask::mode();
+    let status_bar = match app.alerts.last() {
+        _ if show_flash_msg => ratatui::widgets::Paragraph::new(mask_text(&app.flash_msg, mode))
+            .style(Style::default().fg(Color::Cyan)),
+        Some((_, alert)) => ratatui::widgets::Paragraph::new(mask_text(&format!("ALERT: {} | {}", alert, app.last_status_msg), mode).into_owned())
+            .style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+        None => match &app.status_template {
+            Some(tokens) => ratatui::widgets::Paragraph::new(status_line(app, tokens, Instant::now())),
+            None => ratatui::widgets::Paragraph::new(mask_text(&app.last_status_msg, mode))
+                .style(status_style),
+        },
+    };
+
+    f.render_widget(status_bar, status_area);
+}
+
+/// Renders `--format-status`; only `{error}` is drawn in red.
+fn status_line<'a>(app: &App, tokens: &'a [StatusToken], now: Instant) -> Line<'a> {
+    let color = if app.fd_count_high() || app.count_spike { Color::Rgb(255, 165, 0) } else { Color::Green };
+    let base = Style::default().fg(color);
+    let spans: Vec<Span> = tokens.iter()
+        .map(|token| match token {
+            StatusToken::Literal(s) => Span::styled(s.as_str(), base),
+            StatusToken::Pid => Span::styled(mask_text(&app.process_label, mask::mode()).into_owned(), base),
+            StatusToken::ActiveCount => Span::styled(app.active_total.to_string(), base),
+            StatusToken::HistoryCount => Span::styled(app.history_log.len().to_string(), base),
+            StatusToken::Uptime => Span::styled(format_duration(now.duration_since(app.started)), base),
+            StatusToken::PollCount => Span::styled(app.poll_count.to_string(), base),
+            StatusToken::Rate => Span::styled(app.conn_rate.map_or(String::from("N/A"), |r| format!("{:.1} conn/s", r)), base),
+            StatusToken::Error => Span::styled(
+                mask_text(app.last_error.as_deref().unwrap_or_default(), mask::mode()).into_owned(),
+                Style::default().fg(Color::Red),
+            ),
+        })
+        .collect();
+    Line::from(spans)
+}
+
+fn active_title(app: &App) -> String {
+    let mut title = match app.conn_rate {
+        Some(rate) => format!(" Active Connections [{}] | {:.1} conn/s ", app.target_name, rate),
+        None => format!(" Active Connections [{}] | N/A ", app.target_name),
+    };
+    if let Some(n) = app.top_n {
+        title.push_str(&format!("(top {} of {}) ", n, app.active_total));
+    }
+    if app.port_range.is_set() {
+        title.push_str(&format!("({}) ", app.port_range.label()));
+    }
+    if app.sort_key == SortKey::Hostname {
+        title.push_str("(sort: hostname) ");
+    }
+    if !app.show_status_bar && !app.process_label.is_empty() {
+        title.push_str(&format!("| {} ", app.process_label));
+    }
+    title
+}
+
+fn render_history(f: &mut Frame, app: &mut App, area: Rect) {
+    let history_style = if app.focus == Focus::HistoryList {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let now = Local::now();
+    let history_item = |i: &HistoryEntry, indent: &str| {
+        let text = app.format_history(i, now);
+        let bookmarked = history_entry_ip(&i.1).is_some_and(|ip| app.bookmarks.contains(ip));
+        ListItem::new(if bookmarked { format!("{}★ {}", indent, text) } else { format!("{}{}", indent, text) })
+    };
+    let history_items: Vec<ListItem> = match app.history_format {
+        HistoryFormat::Flat => app.tailed_history().iter().rev().map(|i| history_item(i, "")).collect(),
+        HistoryFormat::Grouped => app.history_rows().into_iter()
+            .map(|row| match row {
+                HistoryRow::Header(group) => {
+                    let arrow = if app.collapsed_history_ips.contains(&group.ip) { "▸" } else { "▾" };
+                    ListItem::new(format!(
+                        "{} {} ({}) first {} last {}",
+                        arrow,
+                        group.ip,
+                        group.entries.len(),
+                        group.first_seen.format("%H:%M:%S"),
+                        group.last_seen.format("%H:%M:%S"),
+                    ))
+                    .style(Style::default().add_modifier(Modifier::BOLD))
+                }
+                HistoryRow::Entry(i) => history_item(i, "   "),
+            })
+            .collect(),
+    };
+
+    let mut title = format!(
+        " Connection History (closed: {}) | Unique IPs ({}s): {} ",
+        app.closed_count,
+        RECENT_UNIQUE_WINDOW.as_secs(),
+        recent_unique_count(app),
+    );
+    if let Some(n) = app.history_tail {
+        title.push_str(&format!("(tail: {}) ", n));
+    }
+    if !app.show_status_bar {
+        title.push_str(&format!("| errors: {} ", app.error_count));
+    }
+    let history_block = pane_block(app.border_type)
+        .title(title)
+        .border_style(history_style);
+    app.history_pane_height = history_block.inner(area).height;
+    let list_history = List::new(history_items)
+        .block(history_block)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(list_history, area, &mut app.history_state);
+}
+
+/// IPs first seen in the last `window`, and IPs whose connections closed in
+/// it and that have no connection in `active` any more. Each IP only has
+/// one new-connection entry per session, so "removed" comes from the
+/// `CLOSED` entries.
+fn compute_window_diff(history: &[HistoryEntry], window: Duration, active: &[ConnectionEntry], now: DateTime<Local>) -> (Vec<IpAddr>, Vec<IpAddr>) {
+    let window_start = now - chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+    let mut earlier = HashSet::new();
+    let mut added = BTreeSet::new();
+    let mut closed = BTreeSet::new();
+    for (ts, entry) in history.iter().filter(|(ts, _)| *ts <= now) {
+        let fields = history_fields(entry);
+        let Ok(ip) = fields.ip.parse::<IpAddr>() else { continue };
+        match fields.direction {
+            "new" if *ts < window_start => { earlier.insert(ip); }
+            "new" if !earlier.contains(&ip) => { added.insert(ip); }
+            "closed" if *ts >= window_start => { closed.insert(ip); }
+            _ => {}
+        }
+    }
+    let active: HashSet<IpAddr> = active.iter().map(|c| c.ip).collect();
+    (added.into_iter().collect(), closed.into_iter().filter(|ip| !active.contains(ip)).collect())
+}
+
+fn render_history_diff(f: &mut Frame, app: &App, area: Rect) {
+    let (added, removed) = compute_window_diff(&app.history_log, DIFF_WINDOW, &app.active_connections, Local::now());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let mins = DIFF_WINDOW.as_secs() / 60;
+    for (ips, title, color, column) in [
+        (added, format!(" Added (last {}m) ", mins), Color::Green, columns[0]),
+        (removed, format!(" Removed (last {}m) ", mins), Color::Red, columns[1]),
+    ] {
+        let items: Vec<ListItem> = ips.iter().map(|ip| ListItem::new(ip.to_string())).collect();
+        let list = List::new(items)
+            .style(Style::default().fg(color))
+            .block(pane_block(app.border_type)
+                .title(title)
+                .border_style(Style::default().fg(Color::DarkGray)));
+        f.render_widget(list, column);
+    }
+}
+
+fn pane_block<'a>(border_type: Option<BorderType>) -> Block<'a> {
+    match border_type {
+        Some(border_type) => Block::default().borders(Borders::ALL).border_type(border_type),
+        // Keep a gap between neighbouring panes now that no border separates them.
+        None => Block::default().borders(Borders::NONE).padding(Padding::horizontal(1)),
+    }
+}
+
+fn render_timeline(f: &mut Frame, app: &App, area: Rect) {
+    let len = app.timeline.len();
+    let labels: Vec<String> = (0..len).map(|i| format!("{}", len - 1 - i)).collect();
+    let data: Vec<(&str, u64)> = labels.iter()
+        .zip(app.timeline.iter())
+        .map(|(label, count)| (label.as_str(), *count as u64))
+        .collect();
+
+    let max = app.timeline.iter().copied().max().unwrap_or(0).max(1) as u64;
+    let bar_width = (area.width.saturating_sub(2) / TIMELINE_LEN as u16).saturating_sub(1).max(1);
+
+    let chart = BarChart::default()
+        .block(pane_block(app.border_type)
+            .title(" Active Connections (last 30s) ")
+            .border_style(Style::default().fg(Color::DarkGray)))
+        .data(&data)
+        .max(max)
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
+    f.render_widget(chart, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(s: &str) -> HistoryEntry {
+        (Local::now(), s.to_string())
+    }
+
+    pub(crate) fn test_conn(ip: &str, port: u16) -> ConnectionEntry {
+        ConnectionEntry {
+            local_ip: "192.168.1.2".parse().unwrap(),
+            local_port: 50000,
+            ip: ip.parse().unwrap(),
+            port,
+            proto: String::from("TCP"),
+            state: String::from("ESTABLISHED"),
+            size: None,
+            rate: None,
+            last_seen: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_update_seen_ips_empty_history() {
+        let mut app = App::new(String::from("test"));
+        app.update_seen_ips();
+        assert!(app.seen_ips.is_empty());
+    }
+
+    #[test]
+    fn test_update_seen_ips_single_entry() {
+        let mut app = App::new(String::from("test"));
+        app.history_log.push(entry("192.168.1.1"));
+        app.update_seen_ips();
+        assert!(app.seen_ips.contains("192.168.1.1"));
+        assert_eq!(app.seen_ips.len(), 1);
+    }
+
+    #[test]
+    fn test_update_seen_ips_multiple_entries() {
+        let mut app = App::new(String::from("test"));
+        app.history_log.push(entry("192.168.1.1"));
+        app.history_log.push(entry("10.0.0.1"));
+        app.history_log.push(entry("172.16.0.1"));
+        app.update_seen_ips();
+        assert!(app.seen_ips.contains("192.168.1.1"));
+        assert!(app.seen_ips.contains("10.0.0.1"));
+        assert!(app.seen_ips.contains("172.16.0.1"));
+        assert_eq!(app.seen_ips.len(), 3);
+    }
+
+    #[test]
+    fn test_update_seen_ips_duplicate_ips() {
+        let mut app = App::new(String::from("test"));
+        app.history_log.push(entry("192.168.1.1"));
+        app.history_log.push(entry("192.168.1.1"));
+        app.history_log.push(entry("10.0.0.1"));
+        app.update_seen_ips();
+        assert!(app.seen_ips.contains("192.168.1.1"));
+        assert!(app.seen_ips.contains("10.0.0.1"));
+        assert_eq!(app.seen_ips.len(), 2);
+    }
+
+    #[test]
+    fn test_update_seen_ips_ipv6() {
+        let mut app = App::new(String::from("test"));
+        app.history_log.push(entry("2001:db8::1"));
+        app.history_log.push(entry("[::1]"));
+        app.update_seen_ips();
+        assert!(app.seen_ips.contains("2001:db8::1"));
+        assert!(app.seen_ips.contains("::1"));
+        assert_eq!(app.seen_ips.len(), 2);
+    }
+
+    #[test]
+    fn test_update_seen_ips_dedupes_bracketed_ipv6() {
+        let mut app = App::new(String::from("test"));
+        app.history_log.push(entry("[::1]"));
+        app.history_log.push(entry("::1"));
+        app.update_seen_ips();
+        assert_eq!(app.seen_ips, string_set(&["::1"]));
+    }
+
+    #[test]
+    fn test_update_seen_ips_limited_history() {
+        let mut app = App::new(String::from("test"));
+        for i in 0..1001 {
+            app.history_log.push(entry(&format!("192.168.1.{}", i)));
+        }
+        app.update_seen_ips();
+        assert_eq!(app.seen_ips.len(), 1000);
+        assert!(!app.seen_ips.contains("192.168.1.0"));
+        assert!(app.seen_ips.contains("192.168.1.1000"));
+    }
+
+
+
+    #[test]
+    fn test_parse_connection_ipv4() {
+        let line = "firefox 1234 user 45u IPv4 0x1 0t0 TCP 192.168.1.2:54321->93.184.216.34:443 (ESTABLISHED)";
+        let conn = parse_connection(line).unwrap();
+        assert_eq!(conn.ip, "93.184.216.34".parse::<IpAddr>().unwrap());
+        assert_eq!(conn.port, 443);
+        assert_eq!(conn.proto, "TCP");
+        assert_eq!(conn.state, "ESTABLISHED");
+        assert_eq!(format_connection(&conn), "93.184.216.34:443");
+    }
+
+    #[test]
+    fn test_parse_connection_ipv6() {
+        let line = "firefox 1234 user 46u IPv6 0x2 0t0 TCP [2001:db8::2]:40000->[2001:db8::1]:443 (ESTABLISHED)";
+        let conn = parse_connection(line).unwrap();
+        assert_eq!(conn.ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(conn.port, 443);
+        assert_eq!(format_connection(&conn), "[2001:db8::1]:443");
+    }
+
+    #[test]
+    fn test_parse_connection_size_off() {
+        let line = "curl 99 user 5u IPv4 0x1 0t2048 TCP 10.0.0.2:40000->10.0.0.1:80 (ESTABLISHED)";
+        let conn = parse_connection(line).unwrap();
+        assert_eq!(conn.size, Some(2048));
+        assert_eq!(conn.local_port, 40000);
+    }
+
+    #[test]
+    fn test_estimate_rate() {
+        let conn = test_conn("10.0.0.1", 80);
+        let start = Instant::now();
+        let mut prev = HashMap::new();
+        assert_eq!(estimate_rate(&prev, &conn.key(), Some(100), start), None);
+
+        prev.insert(conn.key(), (1000, start));
+        let later = start + Duration::from_secs(2);
+        assert_eq!(estimate_rate(&prev, &conn.key(), Some(3048), later), Some(1024.0));
+        assert_eq!(estimate_rate(&prev, &conn.key(), None, later), None);
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(512.0), "512 B/s");
+        assert_eq!(format_rate(1228.8), "1.2 KB/s");
+        assert_eq!(format_rate(3.0 * 1024.0 * 1024.0), "3.0 MB/s");
+    }
+
+    #[test]
+    fn test_connection_identity_ignores_size() {
+        let mut a = test_conn("10.0.0.1", 80);
+        let mut b = a.clone();
+        a.size = Some(1);
+        b.size = Some(2);
+        assert_eq!(a, b);
+        let set: HashSet<ConnectionEntry> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_record_alerts_matches_new_connections() {
+        let mut app = App::new(String::from("test"));
+        app.alert_regex = Some(regex::Regex::new(r"^10\.0\.|\(vpn\)").unwrap());
+        app.tags.insert("8.8.8.8".parse().unwrap(), String::from("vpn"));
+        app.alert_limiter = RateLimiter::new(10);
+        let now = Instant::now();
+
+        let previous = vec![test_conn("10.0.0.1", 22)];
+        app.active_connections = vec![test_conn("10.0.0.1", 22), test_conn("10.0.0.2", 443), test_conn("8.8.8.8", 53), test_conn("1.1.1.1", 53)];
+        app.record_alerts(&previous, now);
+
+        let alerts: Vec<&str> = app.alerts.iter().map(|(_, a)| a.as_str()).collect();
+        assert_eq!(alerts, vec!["10.0.0.2:443", "8.8.8.8:53 (vpn)"]);
+        assert!(app.matches_alert(&test_conn("10.0.0.1", 22)));
+        assert!(!app.matches_alert(&test_conn("1.1.1.1", 53)));
+
+        let previous = app.active_connections.clone();
+        app.record_alerts(&previous, now + Duration::from_secs(61));
+        assert!(app.alerts.is_empty());
+    }
+
+    #[test]
+    fn test_port_range_contains() {
+        let range = PortRange { min: Some(1), max: Some(65535) };
+        assert!(!range.contains(0));
+        assert!(range.contains(1));
+        assert!(range.contains(65535));
+
+        let range = PortRange { min: Some(0), max: Some(1) };
+        assert!(range.contains(0));
+        assert!(range.contains(1));
+        assert!(!range.contains(2));
+
+        let range = PortRange { min: None, max: Some(0) };
+        assert!(range.contains(0));
+        assert!(!range.contains(65535));
+
+        assert!(PortRange::default().contains(0));
+        assert!(PortRange::default().contains(65535));
+    }
+
+    #[test]
+    fn test_active_title_shows_port_range() {
+        let mut app = App::new(String::from("nginx"));
+        assert_eq!(active_title(&app), " Active Connections [nginx] | N/A ");
+        app.port_range = PortRange { min: Some(8000), max: Some(9000) };
+        app.conn_rate = Some(3.2);
+        assert_eq!(active_title(&app), " Active Connections [nginx] | 3.2 conn/s (ports 8000-9000) ");
+    }
+
+    fn command_line(cmd: &Command) -> Vec<String> {
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|s| s.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_lsof_cmd_with_netns() {
+        assert_eq!(command_line(&build_lsof_cmd_with_netns("lsof", &[], "42", None, true)), vec!["lsof", "-i", "-P", "-n", "-p", "42"]);
+        let netns = Path::new("/proc/7/ns/net");
+        assert_eq!(
+            command_line(&build_lsof_cmd_with_netns("lsof", &[], "42", Some(netns), true)),
+            vec!["nsenter", "--net=/proc/7/ns/net", "lsof", "-i", "-P", "-n", "-p", "42"],
+        );
+        assert_eq!(
+            command_line(&build_lsof_cmd_with_netns("lsof", &[], "42", Some(netns), false)),
+            vec!["sudo", "nsenter", "--net=/proc/7/ns/net", "lsof", "-i", "-P", "-n", "-p", "42"],
+        );
+        assert_eq!(
+            command_line(&build_lsof_cmd_with_netns("/opt/bin/elof", &parse_lsof_args("-b"), "42", Some(netns), true)),
+            vec!["nsenter", "--net=/proc/7/ns/net", "/opt/bin/elof", "-i", "-P", "-n", "-p", "42", "-b"],
+        );
+    }
+
+    #[test]
+    fn test_build_unix_sockets_cmd() {
+        assert_eq!(command_line(&build_unix_sockets_cmd("lsof", "42", None, true)), vec!["lsof", "-U", "-a", "-n", "-p", "42"]);
+        assert_eq!(
+            command_line(&build_unix_sockets_cmd("lsof", "42", Some(Path::new("/proc/7/ns/net")), false)),
+            vec!["sudo", "nsenter", "--net=/proc/7/ns/net", "lsof", "-U", "-a", "-n", "-p", "42"],
+        );
+    }
+
+    #[test]
+    fn test_build_lsof_command_without_netns() {
+        assert_eq!(command_line(&build_lsof_cmd_with_netns("lsof", &[], "42", None, false)), vec!["sudo", "lsof", "-i", "-P", "-n", "-p", "42"]);
+        let extra = parse_lsof_args("  -b   -w ");
+        assert_eq!(extra, vec!["-b", "-w"]);
+        assert_eq!(
+            command_line(&build_lsof_cmd_with_netns("/usr/local/bin/lsof-wrapper", &extra, "1,2", None, true)),
+            vec!["/usr/local/bin/lsof-wrapper", "-i", "-P", "-n", "-p", "1,2", "-b", "-w"],
+        );
+        assert_eq!(
+            command_line(&build_lsof_cmd_with_netns("elof", &extra, "42", None, false)),
+            vec!["sudo", "elof", "-i", "-P", "-n", "-p", "42", "-b", "-w"],
+        );
+    }
+
+    #[test]
+    fn test_lsof_args_accepts_hyphen_values() {
+        let args = Args::parse_from(["netmonrs", "nginx", "--lsof-cmd", "elof", "--lsof-args", "-b -w"]);
+        assert_eq!(args.lsof_cmd.as_deref(), Some("elof"));
+        assert_eq!(args.lsof_args.as_deref(), Some("-b -w"));
+    }
+
+    #[test]
+    fn test_app_state_round_trip() {
+        let mut app = App::with_data(
+            String::from("nginx"),
+            vec![test_conn("1.1.1.1", 443)],
+            vec![entry("1.1.1.1 (cdn)"), entry("CLOSED 1.1.1.1:80 after 3s"), entry("RESTART 1 -> 2")],
+        );
+        app.update_seen_ips();
+        let state = app.to_state();
+        assert_eq!(state.connection_counts.get("1.1.1.1"), Some(&2));
+        assert_eq!(state.connection_counts.len(), 1);
+
+        let json = snapshot::to_stable_json(&state).unwrap();
+        let restored = App::from_state(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.target_name, "nginx");
+        assert_eq!(restored.active_connections, app.active_connections);
+        assert_eq!(restored.history_log, app.history_log);
+        assert_eq!(restored.seen_ips, app.seen_ips);
+    }
+
+    #[test]
+    fn test_hidden_status_bar_moves_status_to_titles() {
+        let mut app = App::new(String::from("nginx"));
+        handle_event(&mut app, data_update(Vec::new(), &[]));
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::LsofFailed(io::Error::other("denied"))));
+        assert_eq!(app.error_count, 1);
+        assert!(app.error_flash_until.is_none());
+        assert_eq!(active_title(&app), " Active Connections [nginx] | N/A ");
+
+        app.show_status_bar = false;
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::LsofFailed(io::Error::other("denied"))));
+        assert_eq!(app.error_count, 2);
+        assert!(app.error_flash_until.is_some_and(|until| until > Instant::now()));
+        assert_eq!(active_title(&app), " Active Connections [nginx] | N/A | Monitoring: nginx (1234) ");
+    }
+
+    #[test]
+    fn test_relative_time() {
+        assert_eq!(relative_time(Duration::ZERO), "just now");
+        assert_eq!(relative_time(Duration::from_millis(4999)), "just now");
+        assert_eq!(relative_time(Duration::from_secs(5)), "5s ago");
+        assert_eq!(relative_time(Duration::from_secs(59)), "59s ago");
+        assert_eq!(relative_time(Duration::from_secs(60)), "1m ago");
+        assert_eq!(relative_time(Duration::from_secs(3599)), "59m ago");
+        assert_eq!(relative_time(Duration::from_secs(3600)), "1h ago");
+        assert_eq!(relative_time(Duration::from_secs(86400)), "24h ago");
+    }
+
+    #[test]
+    fn test_format_history_entry_relative() {
+        let now = Local::now();
+        let e = (now - chrono::Duration::seconds(180), String::from("1.1.1.1"));
+        assert_eq!(format_history_entry_relative(&e, now), "[3m ago] 1.1.1.1");
+        let future = (now + chrono::Duration::seconds(2), String::from("1.1.1.1"));
+        assert_eq!(format_history_entry_relative(&future, now), "[just now] 1.1.1.1");
+    }
+
+    fn data_update(active: Vec<ConnectionEntry>, new: &[&str]) -> BackgroundEvent {
+        BackgroundEvent::DataUpdate {
+            active,
+            new_history_entries: new.iter().map(|s| entry(s)).collect(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::from("Monitoring: nginx (1234)"),
+            fd_count: None,
+            restart_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_with_data() {
+        let app = App::with_data(String::from("nginx"), vec![test_conn("1.1.1.1", 443)], vec![entry("1.1.1.1"), entry("2.2.2.2")]);
+        assert_eq!(app.active_connections.len(), 1);
+        assert_eq!(app.history_log.len(), 2);
+        assert_eq!(app.seen_ips, string_set(&["1.1.1.1", "2.2.2.2"]));
+    }
+
+    #[test]
+    fn test_handle_event_data_update() {
+        let mut app = App::with_data(String::from("nginx"), Vec::new(), vec![entry("1.1.1.1")]);
+        handle_event(&mut app, data_update(vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 80)], &["2.2.2.2"]));
+
+        assert_eq!(app.active_connections.len(), 2);
+        assert_eq!(app.history_log.iter().map(|(_, e)| e.as_str()).collect::<Vec<_>>(), vec!["1.1.1.1", "2.2.2.2"]);
+        assert_eq!(app.seen_ips, string_set(&["1.1.1.1", "2.2.2.2"]));
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234)");
+        assert_eq!(app.timeline.back(), Some(&2));
+    }
+
+    #[test]
+    fn test_handle_event_error() {
+        let mut app = App::with_data(String::from("nginx"), vec![test_conn("1.1.1.1", 443)], Vec::new());
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::ParseError { line: String::new(), reason: String::from("bad") }));
+        assert_eq!(app.active_connections.len(), 1);
+
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::PgrepNoPid(String::from("nginx"))));
+        assert!(app.active_connections.is_empty());
+        assert_eq!(app.last_status_msg, "Waiting for process 'nginx'...");
+    }
+
+    #[test]
+    fn test_error_resets_active_total() {
+        let mut app = App::new(String::from("nginx"));
+        handle_event(&mut app, data_update(vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 80)], &[]));
+        assert_eq!(app.active_total, 2);
+
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::ParseError { line: String::new(), reason: String::from("bad") }));
+        assert_eq!(app.active_total, 2);
+
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::LsofFailed(io::Error::other("denied"))));
+        assert!(app.active_connections.is_empty());
+        assert_eq!(app.active_total, 0);
+    }
+
+    #[test]
+    fn test_handle_event_connection_closed() {
+        let mut app = App::with_data(String::from("nginx"), Vec::new(), vec![entry("1.1.1.1")]);
+        handle_event(&mut app, BackgroundEvent::ConnectionClosed {
+            connection: test_conn("1.1.1.1", 443),
+            duration: Duration::from_secs(65),
+        });
+        assert_eq!(app.closed_count, 1);
+        assert_eq!(app.history_log.last().unwrap().1, "CLOSED 1.1.1.1:443 after 1m 5s");
+    }
+
+    #[test]
+    fn test_keeps_closed_min_age() {
+        let mut app = App::new(String::from("nginx"));
+        assert!(app.keeps_closed(Duration::ZERO));
+
+        app.min_age = Some(Duration::from_secs(2));
+        assert!(!app.keeps_closed(Duration::from_millis(1999)));
+        assert!(app.keeps_closed(Duration::from_secs(2)));
+        assert!(app.keeps_closed(Duration::from_secs(60)));
+
+        app.min_age_invert = true;
+        assert!(app.keeps_closed(Duration::from_millis(1999)));
+        assert!(!app.keeps_closed(Duration::from_secs(2)));
+
+        assert!(Args::try_parse_from(["netmonrs", "--min-age-invert", "nginx"]).is_err());
+        assert!(Args::try_parse_from(["netmonrs", "--min-age", "2", "--min-age-invert", "nginx"]).is_ok());
+    }
+
+    #[test]
+    fn test_min_age_filters_closed_history() {
+        let mut app = App::new(String::from("nginx"));
+        app.min_age = Some(Duration::from_secs(5));
+        let closed = |secs| BackgroundEvent::ConnectionClosed { connection: test_conn("1.1.1.1", 53), duration: Duration::from_secs(secs) };
+        handle_event(&mut app, closed(0));
+        handle_event(&mut app, closed(30));
+        assert_eq!(app.closed_count, 2);
+        assert_eq!(app.history_log.len(), 1);
+        assert_eq!(app.history_log[0].1, "CLOSED 1.1.1.1:53 after 30s");
+    }
+
+    #[test]
+    fn test_min_age_holds_new_entries() {
+        let mut app = App::new(String::from("nginx"));
+        app.min_age = Some(Duration::from_secs(5));
+        let (long, short) = (test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 53));
+        handle_event(&mut app, data_update(vec![long.clone(), short.clone()], &["1.1.1.1", "2.2.2.2"]));
+        assert!(app.history_log.is_empty());
+        assert_eq!(app.seen_ips, string_set(&["1.1.1.1", "2.2.2.2"]));
+
+        handle_event(&mut app, BackgroundEvent::ConnectionClosed { connection: short, duration: Duration::from_millis(300) });
+        assert!(app.history_log.is_empty());
+        assert_eq!(app.held_history.len(), 1);
+
+        app.release_aged_history(Instant::now() + Duration::from_secs(5));
+        let logged: Vec<&str> = app.history_log.iter().map(|(_, e)| e.as_str()).collect();
+        assert_eq!(logged, vec!["1.1.1.1"]);
+        assert!(app.held_history.is_empty());
+    }
+
+    #[test]
+    fn test_min_age_invert_logs_on_close() {
+        let mut app = App::new(String::from("nginx"));
+        app.min_age = Some(Duration::from_secs(5));
+        app.min_age_invert = true;
+        let (long, short) = (test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 53));
+        handle_event(&mut app, data_update(vec![long, short.clone()], &["1.1.1.1", "2.2.2.2"]));
+        app.release_aged_history(Instant::now() + Duration::from_secs(60));
+        assert!(app.history_log.is_empty());
+
+        handle_event(&mut app, BackgroundEvent::ConnectionClosed { connection: short, duration: Duration::from_secs(1) });
+        let logged: Vec<&str> = app.history_log.iter().map(|(_, e)| e.as_str()).collect();
+        assert_eq!(logged, vec!["2.2.2.2", "CLOSED 2.2.2.2:53 after 1s"]);
+    }
+
+    #[test]
+    fn test_min_age_flushes_active_on_exit() {
+        let mut app = App::new(String::from("nginx"));
+        app.min_age = Some(Duration::from_secs(5));
+        handle_event(&mut app, data_update(vec![test_conn("1.1.1.1", 443)], &["1.1.1.1"]));
+        assert!(app.history_log.is_empty());
+
+        let mut multi = MultiApp::new(Args::parse_from(["netmonrs", "nginx"]), None, Arc::clone(&app.blacklist));
+        multi.add_tab(app, None);
+        multi.quit();
+        assert_eq!(multi.current().history_log.len(), 1);
+        assert_eq!(multi.current().history_log[0].1, "1.1.1.1");
+    }
+
+    #[test]
+    fn test_no_history_mode_leaves_history_untouched() {
+        let mut app = App::new(String::from("test"));
+        app.show_history = false;
+
+        let taken = app.take_until_count(vec![entry("1.1.1.1"), entry("2.2.2.2")]);
+        app.append_history(taken);
+        app.append_history(vec![entry("CLOSED 1.1.1.1:443 after 5s")]);
+        assert!(app.history_log.is_empty());
+        assert_eq!(app.seen_ips, string_set(&["1.1.1.1", "2.2.2.2"]));
+
+        app.toggle_focus();
+        app.next();
+        app.page_down();
+        assert!(app.focus == Focus::ActiveList);
+        assert_eq!(app.history_state.selected(), None);
+    }
+
+    #[test]
+    fn test_selected_ip() {
+        let mut app = App::new(String::from("test"));
+        assert_eq!(app.selected_ip(), None);
+
+        app.active_connections = vec![test_conn("1.1.1.1", 443), test_conn("2001:db8::1", 80)];
+        app.active_state.select(Some(1));
+        assert_eq!(app.selected_ip().as_deref(), Some("2001:db8::1"));
+
+        app.group_by_asn = true;
+        app.active_state.select(Some(0));
+        assert_eq!(app.selected_ip(), None);
+        app.active_state.select(Some(1));
+        assert_eq!(app.selected_ip().as_deref(), Some("1.1.1.1"));
+
+        app.toggle_focus();
+        app.history_log = vec![entry("93.184.216.34 (cdn)"), entry("CLOSED [::1]:8080 after 3s")];
+        app.history_state.select(Some(0));
+        assert_eq!(app.selected_ip().as_deref(), Some("::1"));
+        app.history_state.select(Some(1));
+        assert_eq!(app.selected_ip().as_deref(), Some("93.184.216.34"));
+    }
+
+    #[test]
+    fn test_conn_rate_refreshes_without_updates() {
+        let mut app = App::new(String::from("nginx"));
+        let now = Instant::now();
+        app.history_log = vec![(Local::now() - chrono::Duration::seconds(30), String::from("1.1.1.1"))];
+        app.conn_rate = Some(2.0);
+
+        app.refresh_conn_rate_if_due(now + Duration::from_secs(5));
+        assert_eq!(app.conn_rate, Some(2.0), "replayed tabs keep their rate");
+
+        app.last_heartbeat = Some(now);
+        app.refresh_conn_rate_if_due(now + Duration::from_secs(5));
+        assert_eq!(app.conn_rate, Some(0.0));
+
+        app.conn_rate = Some(2.0);
+        app.refresh_conn_rate_if_due(now + Duration::from_millis(5500));
+        assert_eq!(app.conn_rate, Some(2.0));
+    }
+
+    #[test]
+    fn test_connections_per_second() {
+        let now = Local::now();
+        let at = |secs_ago: i64, s: &str| (now - chrono::Duration::seconds(secs_ago), s.to_string());
+
+        assert_eq!(connections_per_second(&[], 10, now), None);
+
+        let history = vec![
+            at(30, "1.1.1.1"),
+            at(9, "2.2.2.2"),
+            at(5, "3.3.3.3"),
+            at(4, "CLOSED 1.1.1.1:443 after 26s"),
+            at(0, "4.4.4.4"),
+        ];
+        assert_eq!(connections_per_second(&history, 10, now), Some(0.3));
+        assert_eq!(connections_per_second(&history[..1], 10, now), Some(0.0));
+        assert_eq!(connections_per_second(&history, 60, now), Some(4.0 / 60.0));
+    }
+
+    #[test]
+    fn test_closed_connections() {
+        let before: HashSet<ConnectionEntry> = [test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 80), test_conn("3.3.3.3", 22)].into_iter().collect();
+        let after: HashSet<ConnectionEntry> = [test_conn("2.2.2.2", 80), test_conn("4.4.4.4", 53)].into_iter().collect();
+
+        let closed = closed_connections(&before, &after);
+        assert_eq!(closed.iter().map(format_connection).collect::<Vec<_>>(), vec!["1.1.1.1:443", "3.3.3.3:22"]);
+        assert!(closed_connections(&after, &after).is_empty());
+        assert!(closed_connections(&HashSet::new(), &after).is_empty());
+    }
+
+    #[test]
+    fn test_closed_entry_format() {
+        let conn = test_conn("93.184.216.34", 443);
+        assert_eq!(closed_entry(&conn, Duration::from_secs(192)), "CLOSED 93.184.216.34:443 after 3m 12s");
+        assert_eq!(format_duration(Duration::from_secs(7)), "7s");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 2m 5s");
+    }
+
+    #[test]
+    fn test_update_seen_ips_skips_closed_entries() {
+        let mut app = App::new(String::from("test"));
+        app.history_log = vec![entry("1.1.1.1"), entry("CLOSED 1.1.1.1:443 after 5s")];
+        app.update_seen_ips();
+        assert_eq!(app.seen_ips, string_set(&["1.1.1.1"]));
+    }
+
+    #[test]
+    fn test_hostname_cache_ttl() {
+        let mut app = App::new(String::from("test"));
+        let (tx, rx) = mpsc::channel();
+        app.dns_tx = Some(tx);
+        app.active_connections = vec![test_conn("8.8.8.8", 53)];
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+        let now = Instant::now();
+
+        app.request_hostnames(now);
+        app.request_hostnames(now);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![ip]);
+
+        app.store_hostname(ip, Some(String::from("dns.google")), now);
+        assert_eq!(app.connection_label(&app.active_connections[0]), "8.8.8.8:53 dns.google");
+        app.request_hostnames(now + Duration::from_secs(60));
+        assert!(rx.try_recv().is_err());
+
+        app.request_hostnames(now + HOSTNAME_TTL);
+        assert_eq!(app.hostname(&ip), None);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![ip]);
+    }
+
+    #[test]
+    fn test_subnet_color_is_deterministic() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let palette = DEFAULT_SUBNET_PALETTE;
+        let color = subnet_color(&ip("93.184.216.34"), 24, &palette);
+        assert_eq!(subnet_color(&ip("93.184.216.34"), 24, &palette), color);
+        assert_eq!(subnet_color(&ip("93.184.216.1"), 24, &palette), color);
+        assert_eq!(subnet_color(&ip("93.184.216.255"), 24, &palette), color);
+        assert_eq!(subnet_color(&ip("93.184.0.1"), 16, &palette), subnet_color(&ip("93.184.216.34"), 16, &palette));
+        assert_eq!(subnet_color(&ip("2001:db8::1"), 64, &palette), subnet_color(&ip("2001:db8::ffff"), 64, &palette));
+
+        // Different subnets spread over the palette instead of sharing one color.
+        let colors: HashSet<Color> = (0..=255u8)
+            .map(|i| subnet_color(&IpAddr::V4(Ipv4Addr::new(10, 0, i, 1)), 24, &palette))
+            .collect();
+        assert_eq!(colors.len(), palette.len());
+
+        assert_eq!(subnet_color(&ip("10.0.0.1"), 24, &[Color::Blue]), Color::Blue);
+        assert_eq!(subnet_color(&ip("10.0.0.1"), 24, &[]), Color::Reset);
+    }
+
+    #[test]
+    fn test_parse_palette() {
+        assert_eq!(parse_palette("red, green,blue"), Ok(vec![Color::Red, Color::Green, Color::Blue]));
+        assert_eq!(parse_palette("#ff8000,33"), Ok(vec![Color::Rgb(255, 128, 0), Color::Indexed(33)]));
+        assert!(parse_palette("red,notacolor").unwrap_err().contains("notacolor"));
+        assert!(parse_palette(" , ").is_err());
+    }
+
+    #[test]
+    fn test_port_color() {
+        assert_eq!(port_color(443), Color::Blue);
+        assert_eq!(port_color(80), Color::Yellow);
+        assert_eq!(port_color(7999), Color::White);
+        assert_eq!(port_color(8000), Color::Yellow);
+        assert_eq!(port_color(8999), Color::Yellow);
+        assert_eq!(port_color(9000), Color::White);
+        assert_eq!(port_color(22), Color::Red);
+        assert_eq!(port_color(21), Color::White);
+        assert_eq!(port_color(23), Color::White);
+        assert_eq!(port_color(3306), Color::Rgb(255, 165, 0));
+        assert_eq!(port_color(5432), Color::Rgb(255, 165, 0));
+        assert_eq!(port_color(27017), Color::Rgb(255, 165, 0));
+        assert_eq!(port_color(0), Color::White);
+        assert_eq!(port_color(u16::MAX), Color::White);
+    }
+
+    #[test]
+    fn test_tail_slice() {
+        let entries = [1, 2, 3, 4, 5];
+        assert_eq!(tail_slice(&entries, Some(10)), &[1, 2, 3, 4, 5]);
+        assert_eq!(tail_slice(&entries, Some(5)), &[1, 2, 3, 4, 5]);
+        assert_eq!(tail_slice(&entries, Some(2)), &[4, 5]);
+        assert_eq!(tail_slice(&entries, None), &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_tail_command() {
+        let mut app = App::new(String::from("test"));
+        let now = Instant::now();
+        app.history_log = (0..5).map(|i| (Local::now(), format!("10.0.0.{}", i))).collect();
+        app.history_state.select(Some(4));
+
+        app.run_command("t 2", now);
+        assert_eq!(app.history_tail, Some(2));
+        assert_eq!(app.tailed_history().len(), 2);
+        assert_eq!(app.history_state.selected(), Some(1));
+
+        app.run_command("t 0", now);
+        assert_eq!(app.history_tail, None);
+        assert!(app.flash_msg_until.is_none());
+
+        app.run_command("t x", now);
+        assert_eq!(app.flash_msg, "Command Error: Invalid tail length 'x'");
+    }
+
+    #[test]
+    fn test_sort_by_hostname() {
+        let now = Instant::now();
+        let cache = HashMap::from([
+            ("9.9.9.9".parse().unwrap(), (Some(String::from("alpha.example")), now)),
+            ("1.1.1.1".parse().unwrap(), (Some(String::from("beta.example")), now)),
+            ("2.2.2.2".parse().unwrap(), (Some(String::from("alpha.example")), now)),
+            ("3.3.3.3".parse().unwrap(), (None, now)),
+        ]);
+        let mut conns = vec![
+            test_conn("4.4.4.4", 443),
+            test_conn("1.1.1.1", 443),
+            test_conn("3.3.3.3", 443),
+            test_conn("9.9.9.9", 443),
+            test_conn("2.2.2.2", 443),
+        ];
+        sort_by_hostname(&mut conns, &cache);
+        let ips: Vec<String> = conns.iter().map(|c| c.ip.to_string()).collect();
+        assert_eq!(ips, vec!["2.2.2.2", "9.9.9.9", "1.1.1.1", "3.3.3.3", "4.4.4.4"]);
+    }
+
+    #[test]
+    fn test_cycle_sort_keeps_bookmarks_first() {
+        let mut app = App::new(String::from("test"));
+        let now = Instant::now();
+        app.active_connections = vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443), test_conn("3.3.3.3", 443)];
+        app.store_hostname("1.1.1.1".parse().unwrap(), Some(String::from("zeta.example")), now);
+        app.store_hostname("2.2.2.2".parse().unwrap(), Some(String::from("alpha.example")), now);
+        app.bookmarks.insert(String::from("3.3.3.3"));
+
+        app.cycle_sort();
+        assert_eq!(app.sort_key, SortKey::Hostname);
+        let ips: Vec<String> = app.active_connections.iter().map(|c| c.ip.to_string()).collect();
+        assert_eq!(ips, vec!["3.3.3.3", "2.2.2.2", "1.1.1.1"]);
+
+        app.cycle_sort();
+        let ips: Vec<String> = app.active_connections.iter().map(|c| c.ip.to_string()).collect();
+        assert_eq!(ips, vec!["3.3.3.3", "1.1.1.1", "2.2.2.2"]);
+    }
+
+    #[test]
+    fn test_top_n_recent() {
+        let now = Instant::now();
+        let conns: Vec<ConnectionEntry> = (0..3u16)
+            .map(|i| ConnectionEntry { last_seen: now + Duration::from_secs(i as u64), ..test_conn("1.1.1.1", 80 + i) })
+            .collect();
+
+        let none = HashSet::new();
+        let top = top_n_recent(conns.clone(), 2, &none);
+        assert_eq!(top.iter().map(|c| c.port).collect::<Vec<_>>(), vec![82, 81]);
+
+        assert_eq!(top_n_recent(conns.clone(), 3, &none).len(), 3);
+        assert_eq!(top_n_recent(conns.clone(), 10, &none).len(), 3);
+        assert!(top_n_recent(Vec::new(), 5, &none).is_empty());
+
+        let mut conns = conns;
+        conns[0].ip = "9.9.9.9".parse().unwrap();
+        let top = top_n_recent(conns, 2, &string_set(&["9.9.9.9"]));
+        assert_eq!(top.iter().map(|c| c.port).collect::<Vec<_>>(), vec![80, 82]);
+    }
+
+    #[test]
+    fn test_display_connection_ipv4() {
+        assert_eq!(test_conn("93.184.216.34", 443).to_string(), "93.184.216.34:443 [TCP/ESTABLISHED]");
+    }
+
+    #[test]
+    fn test_display_connection_ipv6() {
+        assert_eq!(test_conn("2001:db8::1", 8080).to_string(), "[2001:db8::1]:8080 [TCP/ESTABLISHED]");
+    }
+
+    #[test]
+    fn test_display_connection_missing_state() {
+        let conn = ConnectionEntry { proto: String::from("UDP"), state: String::new(), ..test_conn("8.8.8.8", 53) };
+        assert_eq!(conn.to_string(), "8.8.8.8:53 [UDP]");
+        let bare = ConnectionEntry { proto: String::new(), ..conn };
+        assert_eq!(bare.to_string(), "8.8.8.8:53");
+    }
+
+    #[test]
+    fn test_display_connection_missing_port() {
+        assert_eq!(test_conn("10.0.0.1", 0).to_string(), "10.0.0.1 [TCP/ESTABLISHED]");
+        assert_eq!(test_conn("::1", 0).to_string(), "::1 [TCP/ESTABLISHED]");
+    }
+
+    #[test]
+    fn test_format_ipv6_modes() {
+        let cases = [
+            ("::", "::", "0000:0000:0000:0000:0000:0000:0000:0000", "::"),
+            ("::1", "::1", "0000:0000:0000:0000:0000:0000:0000:0001", "::1"),
+            ("2001:db8::1", "2001:db8::1", "2001:0db8:0000:0000:0000:0000:0000:0001", "2001:db8::1"),
+            ("fe80::1:2", "fe80::1:2", "fe80:0000:0000:0000:0000:0000:0001:0002", "fe80::1:2"),
+            ("ff02::fb", "ff02::fb", "ff02:0000:0000:0000:0000:0000:0000:00fb", "ff02::fb"),
+            ("2001:db8:85a3:8d3:1319:8a2e:370:7348", "2001:db8:85a3:8d3:1319:8a2e:370:7348",
+                "2001:0db8:85a3:08d3:1319:8a2e:0370:7348", "2001:db8:85a3:8d3:1319:8a2e:370:7348"),
+            ("::ffff:192.168.1.1", "::ffff:192.168.1.1", "0000:0000:0000:0000:0000:ffff:c0a8:0101", "192.168.1.1"),
+            ("64:ff9b::808:808", "64:ff9b::808:808", "0064:ff9b:0000:0000:0000:0000:0808:0808", "64:ff9b::808:808"),
+        ];
+        for (ip, compressed, expanded, mapped) in cases {
+            let ip: Ipv6Addr = ip.parse().unwrap();
+            assert_eq!(format_ipv6(ip, Ipv6Display::Compressed), compressed);
+            assert_eq!(format_ipv6(ip, Ipv6Display::Expanded), expanded);
+            assert_eq!(format_ipv6(ip, Ipv6Display::Mapped), mapped);
+        }
+    }
+
+    #[test]
+    fn test_strip_ipv4_mapped() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(strip_ipv4_mapped(ip("192.168.1.1")), ip("192.168.1.1"));
+        assert_eq!(strip_ipv4_mapped(ip("::ffff:192.168.1.1")), ip("192.168.1.1"));
+        assert_eq!(strip_ipv4_mapped(ip("::ffff:c0a8:101")), ip("192.168.1.1"));
+        assert_eq!(strip_ipv4_mapped(ip("0:0:0:0:0:ffff:192.168.1.1")), ip("192.168.1.1"));
+        assert_eq!(strip_ipv4_mapped(ip("2001:db8::1")), ip("2001:db8::1"));
+        // IPv4-compatible addresses (::a.b.c.d) are deprecated and left alone.
+        assert_eq!(strip_ipv4_mapped(ip("::192.168.1.1")), ip("::192.168.1.1"));
+    }
+
+    #[test]
+    fn test_strip_ipv4_mapped_range_boundary() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(strip_ipv4_mapped(ip("::ffff:0:0")), ip("0.0.0.0"));
+        assert_eq!(strip_ipv4_mapped(ip("::ffff:ffff:ffff")), ip("255.255.255.255"));
+        assert_eq!(strip_ipv4_mapped(ip("::fffe:ffff:ffff")), ip("::fffe:ffff:ffff"));
+        assert_eq!(strip_ipv4_mapped(ip("::1:ffff:0:0")), ip("::1:ffff:0:0"));
+    }
+
+    #[test]
+    fn test_display_endpoint_as() {
+        let mapped: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+        assert_eq!(display_endpoint_as(mapped, 443, Ipv6Display::Mapped), "10.0.0.1:443");
+        assert_eq!(display_endpoint_as(mapped, 443, Ipv6Display::Compressed), "[::ffff:10.0.0.1]:443");
+        assert_eq!(display_endpoint_as("::1".parse().unwrap(), 22, Ipv6Display::Expanded), "[0000:0000:0000:0000:0000:0000:0000:0001]:22");
+        assert_eq!(display_endpoint_as("::1".parse().unwrap(), 0, Ipv6Display::Expanded), "0000:0000:0000:0000:0000:0000:0000:0001");
+        assert_eq!(display_endpoint_as("1.2.3.4".parse().unwrap(), 80, Ipv6Display::Expanded), "1.2.3.4:80");
+        assert_eq!(parse_ipv6_display("Expanded"), Ok(Ipv6Display::Expanded));
+        assert!(parse_ipv6_display("full").is_err());
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(parse_columns("ip, Port,state"), Ok(vec![Column::Ip, Column::Port, Column::State]));
+        assert!(parse_columns("port,state").unwrap_err().contains("ip column is required"));
+        assert!(parse_columns("ip,latency").unwrap_err().contains("'latency'"));
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let now = Instant::now();
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+        let mut conn = test_conn("8.8.8.8", 443);
+        conn.size = Some(2048);
+        let mut app = App::with_data(String::from("test"), vec![conn.clone()], Vec::new());
+        app.first_seen.insert(conn.key(), now - Duration::from_secs(75));
+        app.geo_cache.insert(ip, String::from("🇺🇸 United States"));
+        app.asn_cache.insert(ip, Some(AsnInfo { asn: 15169, org: String::from("Google LLC") }));
+        app.hostname_cache.insert(ip, (Some(String::from("dns.google")), now));
+
+        let render = |app: &App, columns: &[Column]| render_columns(&conn, columns, &RenderCtx { app, now });
+        assert_eq!(render(&app, &[Column::Ip]), "8.8.8.8");
+        assert_eq!(render(&app, &[Column::Port]), "443");
+        assert_eq!(render(&app, &[Column::Proto]), "TCP");
+        assert_eq!(render(&app, &[Column::State]), "ESTABLISHED");
+        assert_eq!(render(&app, &[Column::Age]), "1m 15s");
+        assert_eq!(render(&app, &[Column::Bytes]), "2.0 KB");
+        assert_eq!(render(&app, &[Column::Country]), "🇺🇸 United States");
+        assert_eq!(render(&app, &[Column::Asn]), AsnInfo { asn: 15169, org: String::from("Google LLC") }.label());
+        assert_eq!(render(&app, &[Column::Hostname]), "dns.google");
+        assert_eq!(render(&app, &[Column::Hostname, Column::Ip, Column::Port]), "dns.google  8.8.8.8  443");
+
+        app.services = Some(ServiceNames::default());
+        app.truncate_ip = 5;
+        assert_eq!(render(&app, &[Column::Ip, Column::Port]), "8.8.…  443/https");
+    }
+
+    #[test]
+    fn test_render_columns_missing_values() {
+        let mut conn = test_conn("10.0.0.1", 0);
+        conn.state.clear();
+        let app = App::new(String::from("test"));
+        let columns = [Column::Ip, Column::Port, Column::State, Column::Bytes, Column::Country, Column::Asn, Column::Hostname];
+        assert_eq!(render_columns(&conn, &columns, &RenderCtx { app: &app, now: Instant::now() }), "10.0.0.1  -  -  -  -  -  -");
+    }
+
+    #[test]
+    fn test_label_with_service() {
+        assert_eq!(test_conn("10.0.0.1", 8080).label_with_service("http-alt"), "10.0.0.1:8080/http-alt [TCP/ESTABLISHED]");
+        assert_eq!(test_conn("::1", 22).label_with_service("ssh"), "[::1]:22/ssh [TCP/ESTABLISHED]");
+    }
+
+    #[test]
+    fn test_display_connection_full() {
+        let conn = ConnectionEntry { size: Some(2048), rate: Some(10.0), ..test_conn("1.1.1.1", 22) };
+        assert_eq!(conn.to_string(), "1.1.1.1:22 [TCP/ESTABLISHED]");
+        assert_eq!(conn.key().to_string(), "192.168.1.2:50000 -> 1.1.1.1:22");
+    }
+
+    #[test]
+    fn test_age_color() {
+        assert_eq!(age_color(Duration::from_secs(9)), Color::Green);
+        assert_eq!(age_color(Duration::from_secs(10)), Color::Yellow);
+        assert_eq!(age_color(Duration::from_secs(60)), Color::Rgb(255, 165, 0));
+        assert_eq!(age_color(Duration::from_secs(300)), Color::Red);
+        assert_eq!(age_color_basic(Duration::from_secs(120)), Color::Yellow);
+        assert_eq!(age_color_basic(Duration::from_secs(5)), Color::Green);
+    }
+
+    #[test]
+    fn test_connection_age_tracks_first_seen() {
+        let mut app = App::new(String::from("test"));
+        let now = Instant::now();
+        app.active_connections = vec![test_conn("1.1.1.1", 443)];
+        app.track_first_seen(now);
+        app.active_connections.push(test_conn("2.2.2.2", 443));
+        app.track_first_seen(now + Duration::from_secs(30));
+
+        let later = now + Duration::from_secs(90);
+        assert_eq!(app.connection_age(&app.active_connections[0], later), Duration::from_secs(90));
+        assert_eq!(app.connection_age(&app.active_connections[1], later), Duration::from_secs(60));
+
+        app.active_connections.remove(0);
+        app.track_first_seen(later);
+        assert_eq!(app.first_seen.len(), 1);
+    }
+
+    #[test]
+    fn test_truncate_ip_display() {
+        assert_eq!(truncate_ip_display("192.168.1.1", 15), "192.168.1.1");
+        assert!(matches!(truncate_ip_display("192.168.1.1", 11), Cow::Borrowed(_)));
+        assert_eq!(truncate_ip_display("::1", 5), "::1");
+        assert_eq!(truncate_ip_display("fe80::1", 4), "fe8…");
+
+        let long = "2001:db8:85a3::8a2e:370:7334";
+        assert_eq!(truncate_ip_display(long, 10), "2001:db8:…");
+        assert_eq!(truncate_ip_display(long, 1), "…");
+        assert_eq!(truncate_ip_display(long, 0), long);
+        assert_eq!(truncate_ip_display(long, long.len()), long);
+    }
+
+    #[test]
+    fn test_lingering_connections_expire() {
+        let mut app = App::new(String::from("test"));
+        app.linger = Duration::from_secs(5);
+        let now = Instant::now();
+        app.seen_ips.insert(String::from("2.2.2.2"));
+
+        let previous = vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443)];
+        app.active_connections = vec![test_conn("1.1.1.1", 443)];
+        app.record_lingering(previous, now);
+        let lingering: Vec<String> = app.lingering_connections().iter().map(|(c, _)| c.ip.to_string()).collect();
+        assert_eq!(lingering, vec!["2.2.2.2"]);
+        assert_eq!(gone_suffix(Duration::from_secs(3)), "(gone 3s ago)");
+
+        app.prune_lingering(now + Duration::from_secs(4));
+        assert_eq!(app.lingering.len(), 1);
+        app.prune_lingering(now + Duration::from_secs(5));
+        assert!(app.lingering.is_empty());
+        assert!(app.seen_ips.contains("2.2.2.2"));
+    }
+
+    #[test]
+    fn test_reappearing_connection_stops_lingering() {
+        let mut app = App::new(String::from("test"));
+        app.linger = Duration::from_secs(5);
+        let now = Instant::now();
+
+        app.record_lingering(vec![test_conn("2.2.2.2", 443)], now);
+        assert_eq!(app.lingering.len(), 1);
+        app.active_connections = vec![test_conn("2.2.2.2", 443)];
+        app.record_lingering(Vec::new(), now + Duration::from_secs(1));
+        assert!(app.lingering.is_empty());
+    }
+
+    #[test]
+    fn test_new_connection_highlight_expires() {
+        let mut app = App::new(String::from("test"));
+        app.new_highlight = Duration::from_secs(3);
+        let now = Instant::now();
+
+        let previous = vec![test_conn("1.1.1.1", 443)];
+        app.active_connections = vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443)];
+        app.mark_new_connections(&previous, now);
+        assert!(!app.is_new(&app.active_connections[0]));
+        assert!(app.is_new(&app.active_connections[1]));
+
+        app.prune_new_since(now + Duration::from_secs(2));
+        assert!(app.is_new(&app.active_connections[1]));
+        app.prune_new_since(now + Duration::from_secs(3));
+        assert!(app.new_since.is_empty());
+
+        app.new_highlight = Duration::ZERO;
+        app.mark_new_connections(&[], now);
+        assert!(app.new_since.is_empty());
+    }
+
+    #[test]
+    fn test_geo_rows_collapse() {
+        let mut app = App::with_data(String::from("test"), vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443), test_conn("3.3.3.3", 80)], Vec::new());
+        app.geo_cache.insert("1.1.1.1".parse().unwrap(), String::from("🇺🇸 United States"));
+        app.geo_cache.insert("2.2.2.2".parse().unwrap(), String::from("🇺🇸 United States"));
+        app.group_by_geo = true;
+
+        assert_eq!(app.active_len(), 5);
+        app.active_state.select(Some(1));
+        assert_eq!(app.selected_ip().as_deref(), Some("1.1.1.1"));
+
+        app.active_state.select(Some(0));
+        assert_eq!(app.selected_ip(), None);
+        app.toggle_selected_country();
+        assert_eq!(app.active_len(), 3);
+        assert!(matches!(app.geo_rows()[1], GeoRow::Header { ref country, count: 1, .. } if country == geo::UNKNOWN_COUNTRY));
+
+        app.toggle_selected_country();
+        assert_eq!(app.active_len(), 5);
+    }
+
+    #[test]
+    fn test_group_history_by_ip() {
+        use chrono::TimeZone;
+        let t = |secs| Local.with_ymd_and_hms(2024, 1, 1, 12, 0, secs).unwrap();
+        let log = vec![
+            (t(0), String::from("1.1.1.1")),
+            (t(1), String::from("2.2.2.2")),
+            (t(2), format!("{} nginx (1234)", RESTART_MARKER)),
+            (t(3), String::from("CLOSED 1.1.1.1:443 after 3s")),
+        ];
+        let groups = group_history_by_ip(&log);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].ip, "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!((groups[0].first_seen, groups[0].last_seen, groups[0].entries.len()), (t(0), t(3), 2));
+        assert_eq!(groups[1].ip, "2.2.2.2".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_grouped_history_collapse() {
+        let mut app = App::new(String::from("test"));
+        let now = Local::now();
+        app.history_log = vec![
+            (now - chrono::Duration::seconds(2), String::from("1.1.1.1")),
+            (now - chrono::Duration::seconds(1), String::from("CLOSED 1.1.1.1:443 after 3s")),
+            (now, String::from("2.2.2.2")),
+        ];
+        app.history_format = HistoryFormat::Grouped;
+        app.focus = Focus::HistoryList;
+
+        assert_eq!(app.history_len(), 5);
+        app.history_state.select(Some(1));
+        assert_eq!(app.selected_ip().as_deref(), Some("2.2.2.2"));
+
+        app.history_state.select(Some(0));
+        app.toggle_selected_history_group();
+        assert_eq!(app.history_len(), 4);
+        app.toggle_selected_history_group();
+        assert_eq!(app.history_len(), 5);
+    }
+
+    #[test]
+    fn test_parse_history_format() {
+        assert_eq!(parse_history_format("Grouped"), Ok(HistoryFormat::Grouped));
+        assert_eq!(parse_history_format("flat"), Ok(HistoryFormat::Flat));
+        assert!(parse_history_format("tree").is_err());
+    }
+
+    #[test]
+    fn test_read_pid_file() {
+        let path = std::env::temp_dir().join(format!("netmonrs-pidfile-test-{}.pid", std::process::id()));
+
+        assert!(matches!(read_pid_file(&path), Err(MonitorError::PidFileMissing(_))));
+
+        fs::write(&path, "").unwrap();
+        assert!(matches!(read_pid_file(&path), Err(MonitorError::PidFileMissing(_))));
+
+        fs::write(&path, "1234\n").unwrap();
+        assert_eq!(read_pid_file(&path).unwrap(), 1234);
+
+        fs::write(&path, "nginx").unwrap();
+        assert!(matches!(read_pid_file(&path), Err(MonitorError::ParseError { .. })));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_closed_connection_sets_record() {
+        let path = std::env::temp_dir().join(format!("netmonrs-all-time-record-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut app = App::new(String::from("nginx"));
+        app.record_path = Some(path.clone());
+        app.record = Some(DurationRecord { ip: String::from("1.1.1.1"), port: 80, duration_secs: 3600 });
+
+        let closed = |ip, secs| BackgroundEvent::ConnectionClosed { connection: test_conn(ip, 443), duration: Duration::from_secs(secs) };
+        handle_event(&mut app, closed("2.2.2.2", 1800));
+        assert!(app.flash_msg.is_empty());
+        assert_eq!(record::load_record(&path), None);
+
+        handle_event(&mut app, closed("93.184.216.34", 11645));
+        assert_eq!(app.flash_msg, "🏆 New longest connection: 93.184.216.34:443 (3h 14m 5s)");
+        assert_eq!(record::load_record(&path), app.record);
+        assert_eq!(app.record.as_ref().map(|r| r.duration_secs), Some(11645));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compute_window_diff() {
+        let now = Local::now();
+        let at = |mins_ago: i64, entry: &str| (now - chrono::Duration::minutes(mins_ago), entry.to_string());
+        // One new-connection entry per IP, as the monitor thread writes them.
+        let history = vec![
+            at(12, "9.9.9.9"),
+            at(9, "1.1.1.1"),
+            at(8, "2.2.2.2 (cdn)"),
+            at(7, "CLOSED 9.9.9.9:443 after 5m 0s"),
+            at(6, "[2001:db8::1]"),
+            at(4, "CLOSED 1.1.1.1:443 after 5m 0s"),
+            at(3, "4.4.4.4"),
+            at(3, "CLOSED [2001:db8::1]:443 after 3m 0s"),
+            at(2, "RESTART 10 -> 20"),
+            at(1, "2001:db8::2"),
+            at(1, "CLOSED 2.2.2.2:443 after 7m 0s"),
+            at(0, "5.5.5.5"),
+        ];
+        // 2.2.2.2 closed one connection but still has another open.
+        let active = vec![test_conn("2.2.2.2", 80), test_conn("4.4.4.4", 443), test_conn("5.5.5.5", 443)];
+        let window = Duration::from_secs(300);
+        let ips = |list: &[&str]| list.iter().map(|ip| ip.parse::<IpAddr>().unwrap()).collect::<Vec<_>>();
+        let (added, removed) = compute_window_diff(&history, window, &active, now);
+        assert_eq!(added, ips(&["4.4.4.4", "5.5.5.5", "2001:db8::2"]));
+        assert_eq!(removed, ips(&["1.1.1.1", "2001:db8::1"]));
+
+        let (added, removed) = compute_window_diff(&history, window, &active, now - chrono::Duration::minutes(5));
+        assert_eq!(added, ips(&["1.1.1.1", "2.2.2.2", "2001:db8::1"]));
+        assert_eq!(removed, ips(&["9.9.9.9"]));
+    }
+
+    #[test]
+    fn test_compute_window_diff_empty_windows() {
+        let now = Local::now();
+        assert_eq!(compute_window_diff(&[], DIFF_WINDOW, &[], now), (Vec::new(), Vec::new()));
+        let old = vec![(now - chrono::Duration::minutes(30), String::from("1.1.1.1"))];
+        assert_eq!(compute_window_diff(&old, DIFF_WINDOW, &[], now), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_handle_event_process_restart() {
+        let mut app = App::new(String::from("nginx"));
+        handle_event(&mut app, BackgroundEvent::ProcessRestart { old_pid: 10, new_pid: 20 });
+        assert_eq!(app.history_log.last().unwrap().1, "RESTART 10 -> 20");
+        app.update_seen_ips();
+        assert!(app.seen_ips.is_empty());
+    }
+
+    #[test]
+    fn test_reset_clears_state() {
+        let mut app = App::new(String::from("nginx"));
+        app.new_highlight = Duration::from_secs(5);
+        app.linger = Duration::from_secs(5);
+        app.bookmarks.insert(String::from("1.1.1.1"));
+        let conn = test_conn("1.1.1.1", 443);
+        let ip = conn.ip;
+        let start = Instant::now();
+        handle_event(&mut app, data_update(vec![conn.clone()], &["1.1.1.1"]));
+        handle_event(&mut app, data_update(Vec::new(), &[]));
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::PgrepNoPid(String::from("nginx"))));
+        handle_event(&mut app, BackgroundEvent::Warning(String::from(LSOF_TIMEOUT_WARNING)));
+        handle_event(&mut app, BackgroundEvent::ConnectionClosed { connection: conn.clone(), duration: Duration::from_secs(3) });
+        app.active_connections = vec![conn.clone()];
+        app.active_total = 1;
+        app.listening_sockets = parse_listening_sockets("COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME\nnginx 1234 root 6u IPv4 0x1 0t0 TCP *:80 (LISTEN)");
+        app.collapsed_history_ips.insert(ip);
+        app.conn_rate = Some(1.0);
+        app.fd_count = Some(10);
+        app.count_spike = true;
+        app.asn_cache.insert(ip, None);
+        app.geo_cache.insert(ip, String::from("🇺🇸 United States"));
+        app.collapsed_countries.insert(String::from("🇺🇸 United States"));
+        app.timeline.push_back(1);
+        app.new_since.insert(conn.key(), start);
+        app.recent_unique_ips.push_back((start, ip));
+        app.lingering.insert(conn.key(), (conn.clone(), start));
+        app.first_seen.insert(conn.key(), start);
+        app.seen_on_arrival.insert(conn.key(), false);
+        app.public_connection_count = 1;
+        app.hostname_cache.insert(ip, (Some(String::from("one.one.one.one")), start));
+        app.pending_dns.insert(ip);
+        app.alerts.push((start, String::from("1.1.1.1:443")));
+        app.churn_samples.push_back(1);
+        app.churn_rate = 1.0;
+        app.active_state.select(Some(0));
+        app.history_state.select(Some(1));
+        assert!(!app.history_log.is_empty() && !app.seen_ips.is_empty());
+        assert!(app.poll_count > 0 && app.closed_count > 0 && app.error_count > 0 && app.lsof_timeout_count > 0);
+
+        app.reset(start);
+        assert!(app.active_connections.is_empty());
+        assert_eq!(app.active_total, 0);
+        assert!(app.active_unix_sockets.is_empty());
+        assert!(app.listening_sockets.is_empty());
+        assert!(app.history_log.is_empty());
+        assert!(app.collapsed_history_ips.is_empty());
+        assert_eq!(app.closed_count, 0);
+        assert_eq!(app.conn_rate, None);
+        assert!(app.seen_ips.is_empty());
+        assert_eq!(app.last_error, None);
+        assert_eq!(app.started, start);
+        assert_eq!(app.poll_count, 0);
+        assert_eq!(app.error_count, 0);
+        assert_eq!(app.lsof_timeout_count, 0);
+        assert_eq!(app.error_flash_until, None);
+        assert_eq!(app.fd_count, None);
+        assert_eq!(app.prev_active_count, None);
+        assert!(!app.count_spike);
+        assert!(app.asn_cache.is_empty());
+        assert!(app.geo_cache.is_empty());
+        assert!(app.collapsed_countries.is_empty());
+        assert!(app.timeline.is_empty());
+        assert!(app.new_since.is_empty());
+        assert!(app.recent_unique_ips.is_empty());
+        assert!(app.lingering.is_empty());
+        assert!(app.first_seen.is_empty());
+        assert!(app.seen_on_arrival.is_empty());
+        assert_eq!(app.public_connection_count, 0);
+        assert!(app.hostname_cache.is_empty());
+        assert!(app.pending_dns.is_empty());
+        assert!(app.alerts.is_empty());
+        assert!(app.churn_samples.is_empty());
+        assert_eq!(app.churn_rate, 0.0);
+        assert_eq!(app.active_state, ListState::default());
+        assert_eq!(app.history_state, ListState::default());
+        assert_eq!(app.last_status_msg, "Reset — monitoring resumed");
+        assert!(app.bookmarks.contains("1.1.1.1"));
+    }
+
+    #[test]
+    fn test_format_interface_rates() {
+        let rate = |name: &str, rx, tx| InterfaceRate { name: name.to_string(), rx_per_sec: rx, tx_per_sec: tx };
+        let rates = vec![rate("eth0", 1.2 * 1024.0 * 1024.0, 45.0 * 1024.0), rate("lo", 100.0, 100.0), rate("wlan0", 0.0, 512.0)];
+        assert_eq!(format_interface_rates(&rates), "eth0: ↓ 1.2 MB/s ↑ 45.0 KB/s | wlan0: ↓ 0 B/s ↑ 512 B/s");
+    }
+
+    #[test]
+    fn test_stalled_for() {
+        let mut app = App::new(String::from("nginx"));
+        let start = Instant::now();
+        assert_eq!(app.stalled_for(start + Duration::from_secs(60)), None);
+
+        handle_event(&mut app, BackgroundEvent::Heartbeat { ts: start });
+        assert_eq!(app.stalled_for(start + HEARTBEAT_STALL), None);
+        assert_eq!(app.stalled_for(start + Duration::from_secs(12)), Some(Duration::from_secs(12)));
+
+        handle_event(&mut app, BackgroundEvent::Heartbeat { ts: start + Duration::from_secs(11) });
+        assert_eq!(app.stalled_for(start + Duration::from_secs(12)), None);
+    }
+
+    #[test]
+    fn test_lsof_timeout_count() {
+        let mut app = App::new(String::from("nginx"));
+        handle_event(&mut app, BackgroundEvent::Warning(String::from(LSOF_TIMEOUT_WARNING)));
+        handle_event(&mut app, BackgroundEvent::Warning(String::from("lsof failed 3 times in a row")));
+        handle_event(&mut app, BackgroundEvent::Warning(String::from(LSOF_TIMEOUT_WARNING)));
+        assert_eq!(app.lsof_timeout_count, 2);
+        assert_eq!(app.last_status_msg, "Warning: lsof timed out");
+
+        handle_event(&mut app, data_update(vec![test_conn("1.1.1.1", 443)], &[]));
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234) | lsof timeouts: 2");
+    }
+
+    #[test]
+    fn test_status_shows_restart_count() {
+        let mut app = App::new(String::from("nginx"));
+        let mut event = data_update(vec![test_conn("1.1.1.1", 443)], &[]);
+        if let BackgroundEvent::DataUpdate { restart_count, .. } = &mut event {
+            *restart_count = 2;
+        }
+        handle_event(&mut app, event);
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234) | Restarted 2 times");
+    }
+
+    #[test]
+    fn test_run_restarting_after_panic() {
+        let mut runs = Vec::new();
+        let mut panics = Vec::new();
+        run_restarting(Duration::ZERO, |restarts| {
+            runs.push(restarts);
+            if restarts < 2 {
+                panic!("poll failed");
+            }
+        }, |restarts| {
+            panics.push(restarts);
+            true
+        });
+        assert_eq!(runs, [0, 1, 2]);
+        assert_eq!(panics, [1, 2]);
+
+        let mut runs = 0;
+        run_restarting(Duration::ZERO, |_| {
+            runs += 1;
+            panic!("poll failed");
+        }, |_| false);
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn test_wait_timed_out() {
+        let now = Instant::now();
+        let mut wait_start = None;
+        assert!(!wait_timed_out(&mut wait_start, None, now + Duration::from_secs(3600)));
+        assert_eq!(wait_start, None);
+
+        let limit = Some(Duration::from_secs(5));
+        assert!(!wait_timed_out(&mut wait_start, limit, now));
+        assert!(!wait_timed_out(&mut wait_start, limit, now + Duration::from_secs(5)));
+        assert!(wait_timed_out(&mut wait_start, limit, now + Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn test_toggle_bookmark() {
+        let mut app = App::with_data(String::from("test"), vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 80)], Vec::new());
+        app.toggle_bookmark();
+        assert!(app.bookmarks.is_empty());
+
+        app.active_state.select(Some(1));
+        app.toggle_bookmark();
+        assert_eq!(app.bookmarks, string_set(&["2.2.2.2"]));
+        assert_eq!(app.active_connections[0].ip.to_string(), "2.2.2.2");
+        assert_eq!(app.active_state.selected(), Some(0));
+
+        app.active_state.select(Some(0));
+        app.toggle_bookmark();
+        assert!(app.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_bookmarks_sorted_first_on_update() {
+        let mut app = App::new(String::from("test"));
+        app.bookmarks = string_set(&["3.3.3.3", "2.2.2.2"]);
+        let active = vec![test_conn("1.1.1.1", 1), test_conn("2.2.2.2", 2), test_conn("3.3.3.3", 3), test_conn("4.4.4.4", 4)];
+        handle_event(&mut app, data_update(active, &[]));
+
+        let order: Vec<u16> = app.active_connections.iter().map(|c| c.port).collect();
+        assert_eq!(order, vec![2, 3, 1, 4]);
+
+        // The selection follows its connection when a new bookmark moves it.
+        app.active_state.select(Some(3));
+        app.bookmarks.insert(String::from("4.4.4.4"));
+        let active = app.active_connections.clone();
+        handle_event(&mut app, data_update(active, &[]));
+        assert_eq!(app.active_connections[app.active_state.selected().unwrap()].port, 4);
+    }
+
+    #[test]
+    fn test_listen_addr() {
+        assert_eq!(listen_addr("9999"), "0.0.0.0:9999");
+        assert_eq!(listen_addr("127.0.0.1:9000"), "127.0.0.1:9000");
+    }
+
+    #[test]
+    fn test_parse_column_layout() {
+        assert_eq!(parse_column_layout("70:30"), Ok((70, 30)));
+        assert_eq!(parse_column_layout("50:50"), Ok((50, 50)));
+        assert_eq!(parse_column_layout("100:0"), Ok((100, 0)));
+        assert!(parse_column_layout("70:40").is_err());
+        assert!(parse_column_layout("70").is_err());
+        assert!(parse_column_layout("a:b").is_err());
+        assert!(parse_column_layout("-10:110").is_err());
+        assert!(parse_column_layout("60000:60000").is_err());
+    }
+
+    #[test]
+    fn test_parse_border_style() {
+        assert_eq!(parse_border_style("rounded"), Ok(Some(BorderType::Rounded)));
+        assert_eq!(parse_border_style("THICK"), Ok(Some(BorderType::Thick)));
+        assert_eq!(parse_border_style("none"), Ok(None));
+        assert!(parse_border_style("dotted").is_err());
+    }
+
+    #[test]
+    fn test_pane_block_renders_every_style() {
+        for style in ["plain", "rounded", "double", "thick", "none"] {
+            let block = pane_block(parse_border_style(style).unwrap()).title(" Test ");
+            let area = Rect::new(0, 0, 20, 5);
+            let mut buf = Buffer::empty(area);
+            block.render(area, &mut buf);
+        }
+    }
+
+    #[test]
+    fn test_adjust_layout_clamps() {
+        let mut app = App::new(String::from("test"));
+        app.adjust_layout(5);
+        assert_eq!(app.layout_ratio, (55, 45));
+        for _ in 0..10 {
+            app.adjust_layout(5);
+        }
+        assert_eq!(app.layout_ratio, (80, 20));
+        for _ in 0..20 {
+            app.adjust_layout(-5);
+        }
+        assert_eq!(app.layout_ratio, (20, 80));
+
+        app.layout_ratio = (90, 10);
+        app.adjust_layout(-5);
+        assert_eq!(app.layout_ratio, (80, 20));
+    }
+
+    #[test]
+    fn test_history_pct_for_churn() {
+        assert_eq!(history_pct_for_churn(0.0), 30);
+        assert_eq!(history_pct_for_churn(0.9), 30);
+        assert_eq!(history_pct_for_churn(1.0), 40);
+        assert_eq!(history_pct_for_churn(4.9), 40);
+        assert_eq!(history_pct_for_churn(5.0), 50);
+    }
+
+    #[test]
+    fn test_record_churn_moves_pane_gradually() {
+        let mut app = App::new(String::from("test"));
+        app.auto_layout = true;
+
+        app.record_churn(0);
+        assert_eq!(app.layout_ratio.1, 48);
+        for _ in 0..20 {
+            app.record_churn(0);
+        }
+        assert_eq!(app.layout_ratio.1, 30);
+        assert_eq!(app.churn_samples.len(), CHURN_WINDOW);
+
+        for _ in 0..CHURN_WINDOW {
+            app.record_churn(3);
+        }
+        assert_eq!(app.churn_rate, 3.0);
+        assert_eq!(app.layout_ratio.1, 40);
+    }
+
+    #[test]
+    fn test_timed_out() {
+        let mut app = App::new(String::from("test"));
+        let now = app.launched;
+        assert!(!app.timed_out(now));
+
+        app.timeout = Some(Duration::ZERO);
+        assert!(app.timed_out(now));
+
+        app.timeout = Some(Duration::from_secs(60));
+        assert!(!app.timed_out(now + Duration::from_secs(59)));
+        assert!(app.timed_out(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_take_until_count_stops_mid_batch() {
+        let mut app = App::new(String::from("test"));
+        app.count_target = Some(2);
+        app.seen_ips = string_set(&["1.1.1.1"]);
+
+        let taken = app.take_until_count(vec![entry("2.2.2.2"), entry("3.3.3.3"), entry("4.4.4.4")]);
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].1, "2.2.2.2");
+        assert!(app.count_reached());
+    }
+
+    #[test]
+    fn test_take_until_count_canonicalizes_ips() {
+        let mut app = App::new(String::from("test"));
+        app.count_target = Some(2);
+        app.seen_ips = string_set(&["2001:db8::1"]);
+
+        let taken = app.take_until_count(vec![entry("2001:DB8:0::1")]);
+        assert_eq!(taken.len(), 1);
+        assert_eq!(app.seen_ips, string_set(&["2001:db8::1"]));
+        assert!(!app.count_reached());
+    }
+
+    #[test]
+    fn test_connections_from_output_parse_error() {
+        let header = "COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME\n";
+        let listen = format!("{}nginx 1 root 6u IPv4 0x1 0t0 TCP *:80 (LISTEN)\n", header);
+        assert!(connections_from_output(&listen).unwrap().is_empty());
+
+        let garbled = format!("{}nginx 1 root 7u IPv4 0x1 0t0 TCP garbage->nonsense (ESTABLISHED)\n", header);
+        match connections_from_output(&garbled) {
+            Err(MonitorError::ParseError { line, .. }) => assert!(line.contains("garbage->nonsense")),
+            other => panic!("expected parse error, got {:?}", other.map(|c| c.len())),
+        }
+    }
+
+    #[test]
+    fn test_get_lsof_output_from_file() {
+        let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lsof.txt"));
+        let source = LsofSource::File(path.clone());
+
+        let output = get_lsof_output(&source, "ignored").unwrap();
+        let conns = parse_connections(&output);
+        assert_eq!(conns.iter().map(format_connection).collect::<Vec<_>>(),
+            vec!["8.8.8.8:53", "93.184.216.34:51234", "[2001:db8::1]:52345"]);
+
+        let mut cache = None;
+        let listing = read_input_file(&path, &mut cache).unwrap();
+        assert_eq!(listing.connections.len(), 3);
+        assert_eq!(listing.listening.iter().map(|l| l.port).collect::<Vec<_>>(), vec![80]);
+        assert!(cache.is_some());
+        assert_eq!(read_input_file(&path, &mut cache).unwrap().connections.len(), 3);
+
+        let missing = LsofSource::File(PathBuf::from("/nonexistent/lsof.txt"));
+        assert!(get_lsof_output(&missing, "1").is_err());
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let mut calls = 0;
+        let result: Result<u32, ()> = with_retries(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 { Err(()) } else { Ok(calls) }
+        });
+        assert_eq!(result, Ok(3));
+
+        let mut calls = 0;
+        let result: Result<(), u32> = with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            Err(calls)
+        });
+        assert_eq!(result, Err(3));
+
+        let mut calls = 0;
+        let result: Result<(), ()> = with_retries(0, Duration::ZERO, || {
+            calls += 1;
+            Err(())
+        });
+        assert_eq!((result, calls), (Err(()), 1));
+    }
+
+    #[test]
+    fn test_poll_jitter_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let delays: Vec<Duration> = (0..100).map(|_| poll_jitter(&mut rng, 250)).collect();
+        assert!(delays.iter().all(|d| *d <= Duration::from_millis(250)));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+
+        let mut again = StdRng::seed_from_u64(42);
+        assert_eq!(poll_jitter(&mut again, 250), delays[0]);
+        assert_eq!(poll_jitter(&mut again, 0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_should_sample() {
+        assert!((0..10).all(|i| should_sample(i, 1)));
+
+        let sampled: Vec<u64> = (0..10).filter(|i| should_sample(*i, 3)).collect();
+        assert_eq!(sampled, vec![0, 3, 6, 9]);
+
+        assert!(should_sample(0, 100));
+        assert!(!should_sample(1, 100));
+        assert!(!should_sample(99, 100));
+        assert!(should_sample(200, 100));
+    }
+
+    #[test]
+    fn test_count_lsof_rows() {
+        let output = "COMMAND  PID USER   FD   TYPE DEVICE SIZE/OFF NODE NAME\n\
+nginx   1234 root  cwd    DIR    8,1     4096    2 /\n\
+nginx   1234 root    3u  IPv4  12345      0t0  TCP 10.0.0.1:80->1.1.1.1:5000 (ESTABLISHED)\n";
+        assert_eq!(count_lsof_rows(output), 2);
+        assert_eq!(count_lsof_rows(""), 0);
+    }
+
+    #[test]
+    fn test_count_proc_fds() {
+        let root = std::env::temp_dir().join(format!("netmonrs-proc-{}", std::process::id()));
+        for (pid, fds) in [("1", 3), ("2", 2)] {
+            let dir = root.join(pid).join("fd");
+            fs::create_dir_all(&dir).unwrap();
+            for fd in 0..fds {
+                fs::write(dir.join(fd.to_string()), "").unwrap();
+            }
+        }
+        assert_eq!(count_proc_fds(&root, "1"), Some(3));
+        assert_eq!(count_proc_fds(&root, "1,2"), Some(5));
+        assert_eq!(count_proc_fds(&root, "1,3"), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut app = App::with_data(String::from("nginx"), vec![test_conn("1.1.1.1", 443)], Vec::new());
+        let now = Instant::now();
+        app.started = now - Duration::from_secs(65);
+        app.active_total = 1;
+        app.poll_count = 7;
+        app.process_label = String::from("Monitoring: nginx (1234)");
+        let tokens = template::parse_status_template("{pid} | Active: {active_count} | History: {history_count} | {uptime} | {poll_count} | {rate}{error}").unwrap();
+        let text = |app: &App| status_line(app, &tokens, now).spans.iter().map(|s| s.content.to_string()).collect::<String>();
+        assert_eq!(text(&app), "Monitoring: nginx (1234) | Active: 1 | History: 0 | 1m 5s | 7 | N/A");
+
+        handle_event(&mut app, BackgroundEvent::Error(MonitorError::PgrepNoPid(String::from("nginx"))));
+        let line = status_line(&app, &tokens, now);
+        let error = line.spans.last().unwrap();
+        assert_eq!(error.content, "Waiting for process 'nginx'...");
+        assert_eq!(error.style.fg, Some(Color::Red));
+        assert_eq!(line.spans[0].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_collect_subcommand() {
+        let args = Args::parse_from(["netmonrs", "collect", "--listen", "9000"]);
+        assert!(matches!(&args.command, Some(CliCommand::Collect(c)) if c.listen == "9000"));
+        let args = Args::parse_from(["netmonrs", "--", "collect"]);
+        assert!(args.command.is_none());
+        assert_eq!(args.target(), "collect");
+        let args = Args::parse_from(["netmonrs", "--quiet", "collect"]);
+        assert!(args.command.is_none());
+        assert_eq!(args.target(), "collect");
+    }
+
+    #[test]
+    fn test_check_subcommand() {
+        let args = Args::parse_from(["netmonrs", "check", "nginx"]);
+        assert!(matches!(&args.command, Some(CliCommand::Check(c)) if c.target == "nginx"));
+        assert!(Args::try_parse_from(["netmonrs", "check"]).is_err());
+        assert_eq!(Args::parse_from(["netmonrs", "--", "check"]).target(), "check");
+    }
+
+    #[test]
+    fn test_format_status_flag_default() {
+        let args = Args::parse_from(["netmonrs", "--format-status", "nginx"]);
+        assert_eq!(args.target(), "nginx");
+        assert_eq!(args.format_status.as_deref(), Some(template::DEFAULT_STATUS_TEMPLATE));
+        let args = Args::parse_from(["netmonrs", "--format-status={uptime}", "nginx"]);
+        assert_eq!(args.format_status.as_deref(), Some("{uptime}"));
+    }
+
+    #[test]
+    fn test_fd_count_status() {
+        let mut app = App::new(String::from("nginx"));
+        let update = |fds| BackgroundEvent::DataUpdate {
+            active: vec![test_conn("1.1.1.1", 443)],
+            new_history_entries: Vec::new(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::from("Monitoring: nginx (1234)"),
+            fd_count: Some(fds),
+            restart_count: 0,
+        };
+        handle_event(&mut app, update(87));
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234) | FDs: 87 | Net: 1");
+        assert!(!app.fd_count_high());
+
+        app.fd_alert = Some(100);
+        handle_event(&mut app, update(101));
+        assert!(app.fd_count_high());
+        assert_eq!(app.last_status_msg, "⚠ High FD count: Monitoring: nginx (1234) | FDs: 101 | Net: 1");
+    }
+
+    #[test]
+    fn test_recent_unique_count_dedups() {
+        let mut app = App::new(String::from("nginx"));
+        let now = Instant::now();
+        app.active_connections = vec![test_conn("1.1.1.1", 443), test_conn("1.1.1.1", 80), test_conn("2.2.2.2", 443)];
+        app.track_recent_ips(&[], now);
+        assert_eq!(app.recent_unique_ips.len(), 3);
+        assert_eq!(recent_unique_count(&app), 2);
+
+        // Connections still open from the previous update aren't counted again.
+        let previous = app.active_connections.clone();
+        app.active_connections.push(test_conn("3.3.3.3", 443));
+        app.track_recent_ips(&previous, now + Duration::from_secs(1));
+        assert_eq!(app.recent_unique_ips.len(), 4);
+        assert_eq!(recent_unique_count(&app), 3);
+    }
+
+    #[test]
+    fn test_prune_recent_ips() {
+        let mut app = App::new(String::from("nginx"));
+        let start = Instant::now();
+        app.active_connections = vec![test_conn("1.1.1.1", 443)];
+        app.track_recent_ips(&[], start);
+        app.active_connections = vec![test_conn("1.1.1.1", 80), test_conn("2.2.2.2", 443)];
+        app.track_recent_ips(&[], start + Duration::from_secs(30));
+
+        app.prune_recent_ips(start + Duration::from_secs(59));
+        assert_eq!(app.recent_unique_ips.len(), 3);
+        app.prune_recent_ips(start + Duration::from_secs(60));
+        assert_eq!(app.recent_unique_ips.len(), 2);
+        assert_eq!(recent_unique_count(&app), 2);
+        app.prune_recent_ips(start + Duration::from_secs(90));
+        assert_eq!(recent_unique_count(&app), 0);
+    }
+
+    #[test]
+    fn test_seen_color_uses_seen_ips_on_arrival() {
+        let mut app = App::new(String::from("nginx"));
+        app.seen_ips.insert(String::from("1.1.1.1"));
+        let update = |conns: Vec<ConnectionEntry>, new: &[&str]| BackgroundEvent::DataUpdate {
+            active: conns,
+            new_history_entries: new.iter().map(|ip| (Local::now(), ip.to_string())).collect(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::new(),
+            fd_count: None,
+            restart_count: 0,
+        };
+        handle_event(&mut app, update(vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443)], &["2.2.2.2"]));
+        assert!(app.seen_ips.contains("2.2.2.2"));
+        assert_eq!(app.seen_color(&test_conn("1.1.1.1", 443)), Color::DarkGray);
+        assert_eq!(app.seen_color(&test_conn("2.2.2.2", 443)), Color::White);
+
+        handle_event(&mut app, update(vec![test_conn("2.2.2.2", 443), test_conn("2.2.2.2", 80)], &[]));
+        assert_eq!(app.seen_color(&test_conn("2.2.2.2", 443)), Color::White);
+        assert_eq!(app.seen_color(&test_conn("2.2.2.2", 80)), Color::DarkGray);
+        assert!(!app.seen_on_arrival.contains_key(&test_conn("1.1.1.1", 443).key()));
+    }
 
-use chrono::Local;
-use clap::Parser;
-use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use ratatui::{
-    prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState},
-};
+    #[test]
+    fn test_public_connection_count() {
+        let mut app = App::new(String::from("nginx"));
+        let update = || BackgroundEvent::DataUpdate {
+            active: vec![test_conn("10.0.0.5", 5432), test_conn("93.184.216.34", 443), test_conn("8.8.8.8", 53)],
+            new_history_entries: Vec::new(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::from("Monitoring: nginx (1234)"),
+            fd_count: None,
+            restart_count: 0,
+        };
+        handle_event(&mut app, update());
+        assert_eq!(app.public_connection_count, 0);
 
+        app.warn_public = true;
+        app.allowed_public_ips.insert("8.8.8.8".parse().unwrap());
+        handle_event(&mut app, update());
+        assert_eq!(app.public_connection_count, 1);
+        assert!(app.is_unexpected_public("93.184.216.34".parse().unwrap()));
+        assert!(!app.is_unexpected_public("10.0.0.5".parse().unwrap()));
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234) | External: 1");
+    }
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    target: String,
-}
+    #[test]
+    fn test_count_file_written_and_removed() {
+        let path = std::env::temp_dir().join(format!("netmonrs-count-file-{}", std::process::id()));
+        let mut app = App::new(String::from("nginx"));
+        app.count_file = Some(CleanupGuard::new(path.clone()));
+        handle_event(&mut app, BackgroundEvent::DataUpdate {
+            active: vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443)],
+            new_history_entries: Vec::new(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::from("Monitoring: nginx (1234)"),
+            fd_count: None,
+            restart_count: 0,
+        });
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2\n");
+        drop(app);
+        assert!(!path.exists());
+    }
 
+    #[test]
+    fn test_check_count_spike() {
+        let mut app = App::new(String::from("nginx"));
+        app.count_delta_alert = Some(10);
+        let mut poll = |count| {
+            app.active_total = count;
+            app.check_count_spike()
+        };
+        assert_eq!(poll(40), None);
+        assert_eq!(poll(50), None);
+        assert_eq!(poll(61), Some((50, 61)));
+        assert_eq!(poll(61), None);
+        assert_eq!(poll(45), Some((61, 45)));
+        assert_eq!(poll(35), None);
+    }
 
-#[derive(PartialEq)]
-enum Focus {
-    ActiveList,
-    HistoryList,
-}
+    #[test]
+    fn test_count_spike_status_and_history() {
+        let mut app = App::new(String::from("nginx"));
+        app.count_delta_alert = Some(10);
+        let update = |n: u8| BackgroundEvent::DataUpdate {
+            active: (0..n).map(|i| test_conn(&format!("10.0.0.{}", i), 443)).collect(),
+            new_history_entries: Vec::new(),
+            unix_sockets: Vec::new(),
+            listening: Vec::new(),
+            pid_msg: String::from("Monitoring: nginx (1234)"),
+            fd_count: None,
+            restart_count: 0,
+        };
+        handle_event(&mut app, update(10));
+        assert!(!app.count_spike);
+        handle_event(&mut app, update(25));
+        assert!(app.count_spike);
+        assert_eq!(app.last_status_msg, "⚠ Connection count changed by +15 (10→25)");
+        assert_eq!(app.history_log.last().unwrap().1, "── count spike: 10→25 ──");
+        assert_eq!(history_entry_ip("── count spike: 10→25 ──"), None);
 
+        handle_event(&mut app, update(12));
+        assert_eq!(app.last_status_msg, "⚠ Connection count changed by -13 (25→12)");
+        handle_event(&mut app, update(12));
+        assert!(!app.count_spike);
+        assert_eq!(app.last_status_msg, "Monitoring: nginx (1234)");
+    }
 
-enum BackgroundEvent {
-    DataUpdate {
-        active: Vec<String>,
-        new_history_entries: Vec<String>,
-        pid_msg: String,
-    },
-    Error(String),
-}
+    #[test]
+    fn test_canonicalize_ip() {
+        assert_eq!(canonicalize_ip("2001:db8::1"), "2001:db8::1");
+        assert_eq!(canonicalize_ip("2001:0db8:0000:0000:0000:0000:0000:0001"), "2001:db8::1");
+        assert_eq!(canonicalize_ip("0:0:0:0:0:ffff:c0a8:0101"), "::ffff:192.168.1.1");
+        assert_eq!(canonicalize_ip("0000:0000:0000:0000:0000:0000:0000:0001"), "::1");
+        assert_eq!(canonicalize_ip("::1"), "::1");
+        assert_eq!(canonicalize_ip("192.168.1.1"), "192.168.1.1");
+        assert_eq!(canonicalize_ip("CLOSED"), "CLOSED");
+    }
 
+    #[test]
+    fn test_update_seen_ips_canonicalizes_ipv6() {
+        let mut app = App::new(String::from("test"));
+        app.history_log = vec![entry("2001:0db8:0000:0000:0000:0000:0000:0001"), entry("2001:db8::1")];
+        app.update_seen_ips();
+        assert_eq!(app.seen_ips, string_set(&["2001:db8::1"]));
+    }
 
-struct App {
-    target_name: String,
+    #[test]
+    fn test_history_fields() {
+        let f = history_fields("CLOSED [2001:db8::1]:443 after 1m 5s");
+        assert_eq!((f.ip, f.port, f.direction, f.duration), ("2001:db8::1", "443", "closed", "1m 5s"));
+        let f = history_fields("CLOSED 10.0.0.1:22 after 3s");
+        assert_eq!((f.ip, f.port), ("10.0.0.1", "22"));
+        let f = history_fields("93.184.216.34 (cdn)");
+        assert_eq!((f.ip, f.port, f.direction), ("93.184.216.34", "", "new"));
+    }
 
-    active_connections: Vec<String>,
-    history_log: Vec<String>,
-    seen_ips: HashSet<String>,
-    last_status_msg: String,
+    #[test]
+    fn test_format_history_template() {
+        let mut app = App::new(String::from("test"));
+        let item = entry("CLOSED 10.0.0.1:22 after 3s");
+        let now = Local::now();
+        assert_eq!(app.format_history(&item, now), format_history_entry(&item));
 
-    focus: Focus,
-    active_state: ListState,
-    history_state: ListState,
-}
+        app.history_template = Some(template::parse_template("{direction} {ip} port {port} ({duration})").unwrap());
+        assert_eq!(app.format_history(&item, now), "closed 10.0.0.1 port 22 (3s)");
+    }
 
-impl App {
-    fn new(target: String) -> Self {
-        Self {
-            target_name: target,
-            active_connections: Vec::new(),
-            history_log: Vec::new(),
-            seen_ips: HashSet::new(),
-            last_status_msg: String::from("Initializing..."),
+    #[test]
+    fn test_export_on_exit() {
+        use chrono::TimeZone;
+        let mut app = App::with_data(String::from("nginx"), Vec::new(), vec![entry("1.1.1.1"), entry("2.2.2.2")]);
+        let now = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(app.export_on_exit(now), None);
 
-            focus: Focus::ActiveList,
-            active_state: ListState::default(),
-            history_state: ListState::default(),
-        }
+        let dir = std::env::temp_dir().join(format!("netmonrs-exit-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        app.export_dir = Some(dir.clone());
+        let path = dir.join("netmonrs_export_20240101_120000.csv");
+        assert_eq!(app.export_on_exit(now), Some(format!("History exported to {} (2 entries)", path.display())));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+
+        app.export_dir = Some(dir.join("missing"));
+        assert!(app.export_on_exit(now).unwrap().starts_with("Failed to export history"));
     }
 
-    fn next(&mut self) {
-        let (state, len) = match self.focus {
-            Focus::ActiveList => (&mut self.active_state, self.active_connections.len()),
-            Focus::HistoryList => (&mut self.history_state, self.history_log.len()),
-        };
-        if len == 0 { return; }
+    #[test]
+    fn test_count_reached() {
+        assert!(!count_reached(10, None));
+        assert!(!count_reached(4, Some(5)));
+        assert!(count_reached(5, Some(5)));
+    }
 
-        let i = match state.selected() {
-            Some(i) => if i >= len - 1 { 0 } else { i + 1 },
-            None => 0,
-        };
-        state.select(Some(i));
+    #[test]
+    fn test_page_size_for_height() {
+        assert_eq!(page_size_for_height(0), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size_for_height(20), 20);
+        assert_eq!(page_size_for_height(1), 1);
     }
 
-    fn previous(&mut self) {
-        let (state, len) = match self.focus {
-            Focus::ActiveList => (&mut self.active_state, self.active_connections.len()),
-            Focus::HistoryList => (&mut self.history_state, self.history_log.len()),
+    #[test]
+    fn test_page_size_follows_border_style() {
+        let page_size = |style: &str| {
+            let mut app = App::new(String::from("test"));
+            app.border_type = parse_border_style(style).unwrap();
+            let mut terminal = Terminal::new(backend::TestBackend::new(80, 24)).unwrap();
+            terminal.draw(|f| {
+                let area = f.size();
+                ui(f, &mut app, area)
+            }).unwrap();
+            app.page_size()
         };
-        if len == 0 { return; }
+        // Without a border only the title row is lost, not two border rows.
+        assert_eq!(page_size("none"), page_size("plain") + 1);
+    }
 
-        let i = match state.selected() {
-            Some(i) => if i == 0 { len - 1 } else { i - 1 },
-            None => 0,
-        };
-        state.select(Some(i));
+    #[test]
+    fn test_page_down_uses_pane_height() {
+        let mut app = App::new(String::from("test"));
+        app.active_connections = (0..50).map(|p| test_conn("1.1.1.1", p)).collect();
+        app.active_pane_height = 5;
+        app.page_down();
+        assert_eq!(app.active_state.selected(), Some(4));
+        app.page_up();
+        assert_eq!(app.active_state.selected(), Some(49));
     }
 
-    fn toggle_focus(&mut self) {
-        self.focus = match self.focus {
-            Focus::ActiveList => Focus::HistoryList,
-            Focus::HistoryList => Focus::ActiveList,
-        };
+    #[test]
+    fn test_parse_unix_socket() {
+        let line = "nginx 1234 root 3u unix 0xffff888003 0t0 12345 /run/nginx.sock type=STREAM (LISTEN)";
+        assert_eq!(parse_unix_socket(line), Some(UnixSocketEntry {
+            path: String::from("/run/nginx.sock"),
+            type_: String::from("STREAM"),
+            state: String::from("LISTEN"),
+        }));
+
+        let unnamed = "nginx 1234 root 4u unix 0xffff888004 0t0 12346 type=DGRAM";
+        let entry = parse_unix_socket(unnamed).unwrap();
+        assert_eq!(entry.path, "socket");
+        assert_eq!(entry.type_, "DGRAM");
+        assert_eq!(entry.state, "");
+
+        let inet = "nginx 1234 root 6u IPv4 0x3 0t0 TCP *:80 (LISTEN)";
+        assert!(parse_unix_socket(inet).is_none());
     }
 
-    fn update_seen_ips(&mut self) {
-        let mut seen_ips = HashSet::new();
-        let history_to_check = if self.history_log.len() > 1000 {
-            &self.history_log[self.history_log.len() - 1000..]
-        } else {
-            &self.history_log
-        };
+    #[test]
+    fn test_parse_listening_sockets() {
+        let output = "\
+COMMAND  PID USER   FD   TYPE DEVICE SIZE/OFF NODE NAME
+nginx   1234 root    6u  IPv4 0x3         0t0  TCP *:8080 (LISTEN)
+nginx   1234 root    7u  IPv6 0x4         0t0  TCP *:8080 (LISTEN)
+nginx   1234 root    8u  IPv4 0x5         0t0  TCP 127.0.0.1:80 (LISTEN)
+nginx   1234 root    9u  IPv4 0x6         0t0  TCP 127.0.0.1:80 (LISTEN)
+nginx   1234 root   10u  IPv4 0x7         0t0  TCP 10.0.0.5:80->10.0.0.9:51000 (ESTABLISHED)
+nginx   1234 root   11u  unix 0xffff888003 0t0 12345 /run/nginx.sock type=STREAM (LISTEN)
+";
+        let sockets = parse_listening_sockets(output);
+        let shown: Vec<String> = sockets.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["127.0.0.1:80 LISTEN", "0.0.0.0:8080 LISTEN", "[::]:8080 LISTEN"]);
+        assert!(parse_connections(output).iter().all(|c| c.port == 51000));
+    }
 
-        for h in history_to_check {
-             if let Some(ip) = h.split_whitespace().last() {
-                 if !ip.is_empty() {
-                     seen_ips.insert(ip.to_string());
-                 }
-             }
-        }
-        self.seen_ips = seen_ips;
+    #[test]
+    fn test_parse_connection_listen_line() {
+        let line = "nginx 1234 root 6u IPv4 0x3 0t0 TCP *:80 (LISTEN)";
+        assert!(parse_connection(line).is_none());
     }
-}
 
+    #[test]
+    fn test_asn_groups_unknown_last() {
+        let mut app = App::new(String::from("test"));
+        let conn = |ip: &str| test_conn(ip, 443);
+        app.active_connections = vec![conn("10.0.0.1"), conn("8.8.8.8"), conn("8.8.4.4")];
+        app.asn_cache.insert("8.8.8.8".parse().unwrap(), Some(AsnInfo { asn: 15169, org: String::from("Google LLC") }));
+        app.asn_cache.insert("8.8.4.4".parse().unwrap(), Some(AsnInfo { asn: 15169, org: String::from("Google LLC") }));
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
+        let groups = app.asn_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0.map(|a| a.asn), Some(15169));
+        assert_eq!(groups[0].1.len(), 2);
+        assert!(groups[1].0.is_none());
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+        app.group_by_asn = true;
+        assert_eq!(app.active_len(), 5);
+    }
 
-    let res = run_app(&mut stdout, args.target);
+    #[test]
+    fn test_push_timeline_keeps_last_30() {
+        let mut app = App::new(String::from("test"));
+        for i in 0..35 {
+            app.push_timeline(i);
+        }
+        assert_eq!(app.timeline.len(), TIMELINE_LEN);
+        assert_eq!(app.timeline.front(), Some(&5));
+        assert_eq!(app.timeline.back(), Some(&34));
+    }
 
-    disable_raw_mode()?;
-    execute!(stdout, LeaveAlternateScreen)?;
+    #[test]
+    fn test_ui_renders_timeline() {
+        let mut app = App::new(String::from("test"));
+        for i in 0..10 {
+            app.push_timeline(i);
+        }
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| {
+            let area = f.size();
+            ui(f, &mut app, area)
+        }).unwrap();
+        let content: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(content.contains("last 30s"));
+    }
 
-    if let Err(err) = res {
-        println!("App Error: {:?}", err);
+    fn multi_app(targets: &[&str]) -> MultiApp {
+        let args = Args::parse_from(["netmonrs", targets[0]]);
+        let mut multi = MultiApp::new(args, None, Arc::new(Blacklist::default()));
+        for target in targets {
+            multi.add_tab(App::new(target.to_string()), None);
+        }
+        multi
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_signal_sets_flag() {
+        let flag = register_flag(&[signal_hook::consts::SIGUSR2]).unwrap();
+        assert!(!flag.load(Ordering::Relaxed));
+        signal_hook::low_level::raise(signal_hook::consts::SIGUSR2).unwrap();
+        assert!(flag.load(Ordering::Relaxed));
+    }
 
-fn run_app(terminal: &mut Stdout, target: String) -> io::Result<()> {
-    let mut terminal = Terminal::new(CrosstermBackend::new(terminal))?;
-    let mut app = App::new(target.clone());
+    #[test]
+    fn test_exit_on_shutdown() {
+        let mut multi = multi_app(&["nginx"]);
+        let shutdown = AtomicBool::new(false);
+        assert!(multi.exit_on_shutdown(&shutdown).is_none());
 
-    let (tx, rx) = mpsc::channel::<BackgroundEvent>();
+        shutdown.store(true, Ordering::Relaxed);
+        assert!(matches!(multi.exit_on_shutdown(&shutdown), Some(AppExit::Quit(None))));
 
-    thread::spawn(move || {
-        let mut seen_ips_thread_copy = HashSet::new();
-        let lsof_line_pattern = "->";
-
-        loop {
-            let start_time = Instant::now();
-
-            let pgrep = Command::new("pgrep").arg("-f").arg(&target).output();
-
-            match pgrep {
-                Ok(out) => {
-                    let pid_str = String::from_utf8_lossy(&out.stdout);
-                    if let Some(pid_line) = pid_str.lines().next() {
-                        let pid = pid_line.trim();
-
-                        let lsof = Command::new("sudo")
-                            .arg("lsof").arg("-i").arg("-P").arg("-n").arg("-p").arg(pid)
-                            .output();
-
-                        match lsof {
-                            Ok(lsof_out) => {
-                                let output_str = String::from_utf8_lossy(&lsof_out.stdout);
-                                let mut active = HashSet::new();
-                                let mut new_entries = Vec::new();
-
-                                for line in output_str.lines().skip(1) {
-                                    if let Some(pos) = line.find(lsof_line_pattern) {
-                                        let ip_start = pos + 2;
-                                        if ip_start < line.len() {
-                                            let ip_part = &line[ip_start..];
-                                            let ip_end = ip_part.find(|c: char| c.is_whitespace() || c == ':').unwrap_or(ip_part.len());
-                                            let ip = &ip_part[..ip_end];
-
-                                            if !ip.is_empty() && (ip.contains('.') || ip.contains(':')) {
-                                                let final_ip = if ip.starts_with('[') && ip.ends_with(']') {
-                                                    &ip[1..ip.len()-1]
-                                                } else {
-                                                    ip
-                                                };
-
-                                                let s = final_ip.to_string();
-                                                active.insert(s.clone());
-
-                                                if !seen_ips_thread_copy.contains(&s) {
-                                                    seen_ips_thread_copy.insert(s.clone());
-                                                    let ts = Local::now().format("%H:%M:%S");
-                                                    new_entries.push(format!("[{}] {}", ts, s));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+        let dir = std::env::temp_dir().join(format!("netmonrs-shutdown-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        multi.current().export_dir = Some(dir.clone());
+        let Some(AppExit::Quit(Some(msg))) = multi.exit_on_shutdown(&shutdown) else { panic!("expected an export message") };
+        assert!(msg.contains(&dir.display().to_string()), "{}", msg);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
+    #[test]
+    fn test_exit_on_timeout() {
+        let mut multi = multi_app(&["nginx"]);
+        let now = multi.current().launched;
+        assert!(multi.exit_on_timeout(now).is_none());
 
-                                let mut sorted_connections: Vec<String> = active.iter().cloned().collect();
-                                sorted_connections.sort_by(|a, b| {
-                                    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
-                                        (Ok(ip_a), Ok(ip_b)) => ip_a.cmp(&ip_b),
-                                        _ => a.cmp(b),
-                                    }
-                                });
+        multi.current().timeout = Some(Duration::ZERO);
+        assert!(matches!(multi.exit_on_timeout(now), Some(AppExit::Quit(None))));
+    }
 
-                                let _ = tx.send(BackgroundEvent::DataUpdate {
-                                    active: sorted_connections,
-                                    new_history_entries: new_entries,
-                                    pid_msg: format!("Monitoring PID: {}", pid),
-                                });
-                            }
-                            Err(e) => { let _ = tx.send(BackgroundEvent::Error(format!("LSOF Error: {}", e))); }
-                        }
-                    } else {
-                        let _ = tx.send(BackgroundEvent::Error(format!("Waiting for process '{}'...", target)));
-                    }
-                }
-                Err(e) => { let _ = tx.send(BackgroundEvent::Error(format!("PGREP Error: {}", e))); }
-            }
+    #[test]
+    fn test_multi_app_switches_tabs() {
+        let mut multi = multi_app(&["nginx", "redis", "postgres"]);
+        assert_eq!(multi.current_tab, 2);
+        multi.next_tab();
+        assert_eq!(multi.current().target_name, "nginx");
+        multi.previous_tab();
+        assert_eq!(multi.current().target_name, "postgres");
+        multi.select_tab(1);
+        assert_eq!(multi.current().target_name, "redis");
+        multi.select_tab(7);
+        assert_eq!(multi.current_tab, 1);
+    }
 
-            let elapsed = start_time.elapsed();
-            if elapsed < Duration::from_secs(1) {
-                thread::sleep(Duration::from_secs(1) - elapsed);
-            }
-        }
-    });
+    #[test]
+    fn test_multi_app_keeps_scroll_per_tab() {
+        let mut multi = multi_app(&["nginx", "redis"]);
+        multi.select_tab(0);
+        multi.current().active_connections = vec![test_conn("1.1.1.1", 443), test_conn("2.2.2.2", 443)];
+        multi.current().next();
+        multi.current().next();
+        multi.next_tab();
+        assert_eq!(multi.current().active_state.selected(), None);
+        multi.next_tab();
+        assert_eq!(multi.current().active_state.selected(), Some(1));
+    }
 
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
-
-        if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Tab | KeyCode::Left | KeyCode::Right => app.toggle_focus(),
-                        KeyCode::Down | KeyCode::Char('j') => app.next(),
-                        KeyCode::Up | KeyCode::Char('k') => app.previous(),
-                        KeyCode::PageDown => { for _ in 0..10 { app.next(); } },
-                        KeyCode::PageUp => { for _ in 0..10 { app.previous(); } },
-                        _ => {}
-                    }
-                }
+    #[test]
+    fn test_reset_current_keeps_timeout_and_resets_monitor() {
+        let mut multi = multi_app(&["nginx"]);
+        let launched = multi.current().launched;
+        multi.reset_current(launched + Duration::from_secs(30));
+        assert_eq!(multi.current().launched, launched);
+        assert_eq!(multi.current().started, launched + Duration::from_secs(30));
+        assert!(multi.tabs[0].monitor_reset.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_monitor_reports_seen_ips_again_after_reset() {
+        let mut config = mock_config(&[]);
+        config.source = LsofSource::File(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lsof.txt")));
+        let reset = Arc::clone(&config.reset);
+        let (tx, rx) = mpsc::sync_channel(config.event_queue_depth);
+        spawn_monitor(config, tx);
+        let next_new_entries = || loop {
+            match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+                BackgroundEvent::DataUpdate { new_history_entries, .. } => return new_history_entries.len(),
+                _ => continue,
             }
-        }
+        };
 
-        while let Ok(msg) = rx.try_recv() {
-            match msg {
-                BackgroundEvent::DataUpdate { active, new_history_entries, pid_msg } => {
-                    app.active_connections = active;
-                    app.last_status_msg = pid_msg;
+        assert_eq!(next_new_entries(), 3);
+        assert_eq!(next_new_entries(), 0);
+        reset.store(true, Ordering::Relaxed);
+        assert_eq!(next_new_entries(), 3);
+    }
 
-                    for entry in new_history_entries {
-                        app.history_log.push(entry);
-                    }
-                    app.update_seen_ips();
-                }
-                BackgroundEvent::Error(msg) => {
-                    app.last_status_msg = msg;
-                    app.active_connections.clear();
-                }
+    #[test]
+    fn test_monitor_resends_new_ips_from_dropped_update() {
+        let mut config = mock_config(&[]);
+        config.source = LsofSource::File(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lsof.txt")));
+        config.event_queue_depth = 1;
+        let (tx, rx) = mpsc::sync_channel(config.event_queue_depth);
+        spawn_monitor(config, tx);
+        // The first heartbeat fills the queue, so the first update is dropped.
+        thread::sleep(Duration::from_millis(1500));
+
+        let entries = loop {
+            match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+                BackgroundEvent::DataUpdate { new_history_entries, .. } => break new_history_entries,
+                _ => continue,
             }
-        }
+        };
+        assert_eq!(entries.len(), 3);
     }
-}
 
-fn ui(f: &mut Frame, app: &mut App) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(1),
-            Constraint::Length(1),
-        ])
-        .split(f.size());
-
-    let list_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(main_chunks[0]);
+    #[test]
+    fn test_multi_app_close_tab() {
+        let mut multi = multi_app(&["nginx", "redis"]);
+        multi.close_tab();
+        assert_eq!(multi.tabs.len(), 1);
+        assert_eq!(multi.current().target_name, "nginx");
+        multi.close_tab();
+        assert_eq!(multi.tabs.len(), 1);
+        assert!(!multi.show_tab_bar());
+    }
 
-    let active_style = if app.focus == Focus::ActiveList {
-        Style::default().fg(Color::Cyan)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+    #[test]
+    fn test_multi_ui_renders_tab_bar() {
+        let mut multi = multi_app(&["nginx", "redis"]);
+        multi.tabs[0].app.active_total = 12;
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| multi_ui(f, &mut multi)).unwrap();
+        let content: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(content.starts_with(" nginx (12) │ redis (0) "));
 
-    let active_items: Vec<ListItem> = app.active_connections.iter()
-        .map(|i| ListItem::new(format!("🚀 {}", i)))
-        .collect();
+        multi.new_tab_input = Some(String::from("mysql"));
+        terminal.draw(|f| multi_ui(f, &mut multi)).unwrap();
+        let content: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(content.starts_with("New tab target: mysql_"));
+    }
 
-    let list_active = List::new(active_items)
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .title(format!(" Active Connections [{}] ", app.target_name))
-            .border_style(active_style))
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ");
+    #[test]
+    fn test_glob_to_pattern() {
+        assert_eq!(glob_to_pattern("nginx"), ".*nginx.*");
+        assert_eq!(glob_to_pattern("ngin?-*.conf"), ".*ngin.-.*\\.conf.*");
+    }
 
-    f.render_stateful_widget(list_active, list_chunks[0], &mut app.active_state);
+    fn mock_config(pids: &[&str]) -> MonitorConfig {
+        let args = Args::parse_from(["netmonrs", "nginx"]);
+        let mut config = monitor_config(&args, "nginx", &App::new(String::from("nginx")), None);
+        config.finder = Box::new(process::MockFinder { pids: pids.iter().map(|p| p.to_string()).collect() });
+        config
+    }
 
+    #[test]
+    fn test_discover_processes_uses_finder() {
+        let config = mock_config(&["1234", "5678"]);
+        let procs = discover_processes(&config, "nginx").unwrap();
+        assert_eq!(join_pids(&procs), "1234,5678");
+        assert_eq!(format_matched_processes(&procs), "Monitoring: nginx (1234, 5678)");
 
-    let history_style = if app.focus == Focus::HistoryList {
-        Style::default().fg(Color::Cyan)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+        assert!(discover_processes(&mock_config(&[]), "nginx").unwrap().is_empty());
+    }
 
-    let history_items: Vec<ListItem> = app.history_log.iter().rev()
-        .map(|i| ListItem::new(i.clone()))
-        .collect();
+    #[test]
+    fn test_format_pid_msg() {
+        let procs = vec![(String::from("1234"), String::from("nginx"))];
+        assert_eq!(format_pid_msg(&procs, 1, 0), "Monitoring: nginx (1234)");
+        assert_eq!(format_pid_msg(&procs, 4, 2), "Sampling 1:4 | Monitoring: nginx (1234) | Excluded: 2");
+    }
 
-    let list_history = List::new(history_items)
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .title(" Connection History ")
-            .border_style(history_style))
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ");
+    #[test]
+    fn test_discover_processes_prefers_pid_file() {
+        let path = std::env::temp_dir().join(format!("netmonrs-finder-test-{}.pid", std::process::id()));
+        fs::write(&path, "4321\n").unwrap();
+        let mut config = mock_config(&["1234"]);
+        config.pid_file = Some(path.clone());
+        assert_eq!(discover_processes(&config, "nginx").unwrap(), vec![(String::from("4321"), String::from("nginx"))]);
+        fs::remove_file(path).unwrap();
+    }
 
-    f.render_stateful_widget(list_history, list_chunks[1], &mut app.history_state);
+    #[test]
+    fn test_format_matched_processes_groups_by_name() {
+        let procs = vec![
+            (String::from("1234"), String::from("nginx")),
+            (String::from("1235"), String::from("nginx-worker")),
+            (String::from("1236"), String::from("nginx-worker")),
+        ];
+        assert_eq!(format_matched_processes(&procs), "Monitoring: nginx (1234), nginx-worker (1235, 1236)");
+    }
 
+    #[test]
+    fn test_format_matched_processes_truncates() {
+        let procs: Vec<(String, String)> = (0..7)
+            .map(|i| (format!("{}", 100 + i), format!("proc{}", i)))
+            .collect();
+        let msg = format_matched_processes(&procs);
+        assert!(msg.contains("proc4 (104)"));
+        assert!(!msg.contains("proc5"));
+        assert!(msg.ends_with(" and 2 more"));
+    }
 
-    let status_style = if app.last_status_msg.contains("Error") || app.last_status_msg.contains("Wait") {
-        Style::default().fg(Color::Red)
-    } else {
-        Style::default().fg(Color::Green)
-    };
+    fn string_set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
 
-    let status_bar = ratatui::widgets::Paragraph::new(app.last_status_msg.as_str())
-        .style(status_style);
+    #[test]
+    fn test_compute_diff_empty_previous() {
+        let (added, removed) = compute_diff(&HashSet::new(), &string_set(&["10.0.0.1:80", "10.0.0.2:443"]));
+        assert_eq!(added, vec!["10.0.0.1:80", "10.0.0.2:443"]);
+        assert!(removed.is_empty());
+    }
 
-    f.render_widget(status_bar, main_chunks[1]);
-}
+    #[test]
+    fn test_compute_diff_empty_current() {
+        let (added, removed) = compute_diff(&string_set(&["10.0.0.1:80"]), &HashSet::new());
+        assert!(added.is_empty());
+        assert_eq!(removed, vec!["10.0.0.1:80"]);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_compute_diff_partial_overlap() {
+        let prev = string_set(&["10.0.0.1:80", "10.0.0.2:443"]);
+        let curr = string_set(&["10.0.0.2:443", "10.0.0.3:22"]);
+        let (added, removed) = compute_diff(&prev, &curr);
+        assert_eq!(added, vec!["10.0.0.3:22"]);
+        assert_eq!(removed, vec!["10.0.0.1:80"]);
+    }
 
     #[test]
-    fn test_update_seen_ips_empty_history() {
+    fn test_tag_history_entry() {
         let mut app = App::new(String::from("test"));
-        app.update_seen_ips();
-        assert!(app.seen_ips.is_empty());
+        app.tags.insert("93.184.216.34".parse().unwrap(), String::from("example.com CDN"));
+        assert_eq!(app.tag_history_entry(String::from("93.184.216.34")), "93.184.216.34 (example.com CDN)");
+        assert_eq!(app.tag_history_entry(String::from("10.0.0.1")), "10.0.0.1");
     }
 
     #[test]
-    fn test_update_seen_ips_single_entry() {
+    fn test_update_seen_ips_tagged_entry() {
         let mut app = App::new(String::from("test"));
-        app.history_log.push("[12:00:00] 192.168.1.1".to_string());
+        app.history_log.push(entry("93.184.216.34 (example.com CDN)"));
         app.update_seen_ips();
-        assert!(app.seen_ips.contains("192.168.1.1"));
+        assert!(app.seen_ips.contains("93.184.216.34"));
         assert_eq!(app.seen_ips.len(), 1);
     }
 
     #[test]
-    fn test_update_seen_ips_multiple_entries() {
+    fn test_prune_history_by_age() {
         let mut app = App::new(String::from("test"));
-        app.history_log.push("[12:00:00] 192.168.1.1".to_string());
-        app.history_log.push("[12:00:01] 10.0.0.1".to_string());
-        app.history_log.push("[12:00:02] 172.16.0.1".to_string());
-        app.update_seen_ips();
-        assert!(app.seen_ips.contains("192.168.1.1"));
-        assert!(app.seen_ips.contains("10.0.0.1"));
-        assert!(app.seen_ips.contains("172.16.0.1"));
-        assert_eq!(app.seen_ips.len(), 3);
+        let now = Local::now();
+        app.max_history_age = Some(chrono::Duration::seconds(60));
+        app.history_log.push((now - chrono::Duration::seconds(120), String::from("10.0.0.1")));
+        app.history_log.push((now - chrono::Duration::seconds(61), String::from("10.0.0.2")));
+        app.history_log.push((now - chrono::Duration::seconds(30), String::from("10.0.0.3")));
+        app.history_log.push((now, String::from("10.0.0.4")));
+
+        app.prune_history(now);
+        let ips: Vec<&str> = app.history_log.iter().map(|(_, ip)| ip.as_str()).collect();
+        assert_eq!(ips, vec!["10.0.0.3", "10.0.0.4"]);
     }
 
     #[test]
-    fn test_update_seen_ips_duplicate_ips() {
+    fn test_prune_history_stricter_limit_wins() {
         let mut app = App::new(String::from("test"));
-        app.history_log.push("[12:00:00] 192.168.1.1".to_string());
-        app.history_log.push("[12:00:01] 192.168.1.1".to_string());
-        app.history_log.push("[12:00:02] 10.0.0.1".to_string());
-        app.update_seen_ips();
-        assert!(app.seen_ips.contains("192.168.1.1"));
-        assert!(app.seen_ips.contains("10.0.0.1"));
-        assert_eq!(app.seen_ips.len(), 2);
+        let now = Local::now();
+        app.max_history_age = Some(chrono::Duration::seconds(60));
+        app.max_history = Some(1);
+        for secs in [90, 20, 10] {
+            app.history_log.push((now - chrono::Duration::seconds(secs), format!("10.0.0.{}", secs)));
+        }
+        app.history_state.select(Some(2));
+
+        app.prune_history(now);
+        assert_eq!(app.history_log.len(), 1);
+        assert_eq!(app.history_log[0].1, "10.0.0.10");
+        assert_eq!(app.history_state.selected(), Some(0));
     }
 
     #[test]
-    fn test_update_seen_ips_ipv6() {
-        let mut app = App::new(String::from("test"));
-        app.history_log.push("[12:00:00] 2001:db8::1".to_string());
-        app.history_log.push("[12:00:01] [::1]".to_string());
-        app.update_seen_ips();
-        assert!(app.seen_ips.contains("2001:db8::1"));
-        assert!(app.seen_ips.contains("[::1]"));
-        assert_eq!(app.seen_ips.len(), 2);
+    fn test_format_history_entry() {
+        let ts = Local::now();
+        let expected = format!("[{}] 10.0.0.1", ts.format("%H:%M:%S"));
+        assert_eq!(format_history_entry(&(ts, String::from("10.0.0.1"))), expected);
     }
 
     #[test]
-    fn test_update_seen_ips_limited_history() {
-        let mut app = App::new(String::from("test"));
-        for i in 0..1001 {
-            app.history_log.push(format!("[12:00:{:02}] 192.168.1.{}", i, i));
-        }
+    fn test_app_snapshot_fields() {
+        let mut app = App::new(String::from("nginx"));
+        app.active_connections = vec![test_conn("93.184.216.34", 443)];
+        app.history_log.push(entry("93.184.216.34"));
         app.update_seen_ips();
-        assert_eq!(app.seen_ips.len(), 1000);
-        assert!(!app.seen_ips.contains("192.168.1.0"));
-        assert!(app.seen_ips.contains("192.168.1.1000"));
-    }
-
 
+        let snap = app.snapshot();
+        assert_eq!(snap.target, "nginx");
+        assert_eq!(snap.active_connections, vec!["93.184.216.34:443"]);
+        assert_eq!(snap.history_count, 1);
+        assert_eq!(snap.seen_ips_count, 1);
+        assert_eq!(snap.last_status, "Initializing...");
+    }
 
     #[test]
     fn test_update_seen_ips_complex_format() {
         let mut app = App::new(String::from("test"));
-        app.history_log.push("[12:00:00] 192.168.1.1:80".to_string());
-        app.history_log.push("[12:00:01] 10.0.0.1:443".to_string());
+        app.history_log.push(entry("192.168.1.1:80"));
+        app.history_log.push(entry("10.0.0.1:443"));
         app.update_seen_ips();
         assert!(app.seen_ips.contains("192.168.1.1:80"));
         assert!(app.seen_ips.contains("10.0.0.1:443"));
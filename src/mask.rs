@@ -0,0 +1,126 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    net::IpAddr,
+    sync::OnceLock,
+};
+
+/// Whether IP addresses are hidden in debug output and the status bar (`--mask-ips`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaskMode {
+    #[default]
+    Plain,
+    Masked,
+}
+
+static MASK_MODE: OnceLock<MaskMode> = OnceLock::new();
+
+/// Set once at startup; later calls are ignored.
+pub fn set_mode(mode: MaskMode) {
+    let _ = MASK_MODE.set(mode);
+}
+
+pub fn mode() -> MaskMode {
+    MASK_MODE.get().copied().unwrap_or_default()
+}
+
+pub fn mask_ip(ip: &IpAddr, mode: MaskMode) -> String {
+    match (mode, ip) {
+        (MaskMode::Plain, ip) => ip.to_string(),
+        (MaskMode::Masked, IpAddr::V4(_)) => String::from("x.x.x.x"),
+        (MaskMode::Masked, IpAddr::V6(_)) => String::from("x:x:x:x:x:x:x:x"),
+    }
+}
+
+/// Masks every IP address in free text, including `ip:port` forms.
+pub fn mask_text(text: &str, mode: MaskMode) -> Cow<'_, str> {
+    if mode == MaskMode::Plain {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = match rest.find(|c: char| !is_ip_char(c)) {
+            Some(0) => rest.chars().next().map_or(1, char::len_utf8),
+            Some(end) => end,
+            None => rest.len(),
+        };
+        let (word, tail) = rest.split_at(end);
+        match mask_word(word, mode) {
+            Some(masked) => {
+                out.push_str(&masked);
+                changed = true;
+            }
+            None => out.push_str(word),
+        }
+        rest = tail;
+    }
+    if changed { Cow::Owned(out) } else { Cow::Borrowed(text) }
+}
+
+fn is_ip_char(c: char) -> bool {
+    c.is_ascii_hexdigit() || c == ':' || c == '.'
+}
+
+fn mask_word(word: &str, mode: MaskMode) -> Option<String> {
+    // Sentence punctuation isn't part of the address.
+    let trimmed = word.trim_end_matches(['.', ':']);
+    let suffix = &word[trimmed.len()..];
+    if let Ok(ip) = trimmed.parse::<IpAddr>() {
+        return Some(format!("{}{}", mask_ip(&ip, mode), suffix));
+    }
+    // An IPv4 address followed by a port.
+    let (host, port) = trimmed.rsplit_once(':')?;
+    let ip = host.parse::<IpAddr>().ok().filter(IpAddr::is_ipv4)?;
+    port.parse::<u16>().ok()?;
+    Some(format!("{}:{}{}", mask_ip(&ip, mode), port, suffix))
+}
+
+/// Debug-formats the wrapped value with IP addresses masked per `--mask-ips`.
+pub struct MaskedDebug<'a, T: fmt::Debug>(pub &'a T);
+
+impl<T: fmt::Debug> fmt::Debug for MaskedDebug<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&mask_text(&format!("{:?}", self.0), mode()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_ip_v4() {
+        let ip: IpAddr = "93.184.216.34".parse().unwrap();
+        assert_eq!(mask_ip(&ip, MaskMode::Plain), "93.184.216.34");
+        assert_eq!(mask_ip(&ip, MaskMode::Masked), "x.x.x.x");
+    }
+
+    #[test]
+    fn test_mask_ip_v6() {
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(mask_ip(&ip, MaskMode::Plain), "2001:db8::1");
+        assert_eq!(mask_ip(&ip, MaskMode::Masked), "x:x:x:x:x:x:x:x");
+        let mapped: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+        assert_eq!(mask_ip(&mapped, MaskMode::Masked), "x:x:x:x:x:x:x:x");
+    }
+
+    #[test]
+    fn test_mask_text() {
+        let text = "Copied 93.184.216.34 to clipboard; 10.0.0.1:443 -> [2001:db8::1]:8080 at 12:00:05, from 10.0.0.2.";
+        assert_eq!(mask_text(text, MaskMode::Plain), text);
+        assert_eq!(
+            mask_text(text, MaskMode::Masked),
+            "Copied x.x.x.x to clipboard; x.x.x.x:443 -> [x:x:x:x:x:x:x:x]:8080 at 12:00:05, from x.x.x.x.",
+        );
+        assert!(matches!(mask_text("Monitoring: nginx (1234) | Net: 5", MaskMode::Masked), Cow::Borrowed(_)));
+        assert_eq!(mask_text("🏆 New longest connection: 1.1.1.1:443 (1s)", MaskMode::Masked), "🏆 New longest connection: x.x.x.x:443 (1s)");
+    }
+
+    #[test]
+    fn test_masked_debug_plain_by_default() {
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+        assert_eq!(format!("{:?}", MaskedDebug(&Some(ip))), "Some(10.1.2.3)");
+    }
+}
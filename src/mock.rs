@@ -0,0 +1,139 @@
+use std::{
+    fmt::Write,
+    net::{IpAddr, Ipv4Addr},
+    time::Instant,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{localips, ConnectionEntry};
+
+/// New connections are opened on every Nth poll.
+const SPAWN_EVERY: u64 = 2;
+const CLOSE_PROBABILITY: f64 = 0.15;
+const LOCAL_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+
+/// Port 0 stands for a random unprivileged port.
+const PORTS: [(u16, u32); 7] = [(443, 50), (80, 20), (8080, 10), (22, 5), (5432, 3), (3306, 2), (0, 10)];
+const STATES: [(&str, u32); 4] = [("ESTABLISHED", 80), ("SYN_SENT", 5), ("CLOSE_WAIT", 10), ("TIME_WAIT", 5)];
+
+/// Synthetic connections for `--mock`, printed as `lsof` output so they go
+/// through the same parsing as real data.
+pub struct MockNetwork {
+    rng: StdRng,
+    active: Vec<ConnectionEntry>,
+    cycle: u64,
+}
+
+impl MockNetwork {
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { rng, active: Vec::new(), cycle: 0 }
+    }
+
+    /// Advances one poll: closes some connections and maybe opens new ones.
+    pub fn tick(&mut self) -> &[ConnectionEntry] {
+        let rng = &mut self.rng;
+        self.active.retain(|_| !rng.gen_bool(CLOSE_PROBABILITY));
+        if self.cycle.is_multiple_of(SPAWN_EVERY) {
+            for _ in 0..self.rng.gen_range(1..=3) {
+                let conn = self.random_connection();
+                if !self.active.iter().any(|c| c.key() == conn.key()) {
+                    self.active.push(conn);
+                }
+            }
+        }
+        self.cycle += 1;
+        &self.active
+    }
+
+    pub fn lsof_output(&mut self) -> String {
+        let mut out = String::from("COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME\n");
+        for (fd, c) in self.tick().iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "mock 1 user {}u IPv4 0x0 0t0 {} {}:{}->{}:{} ({})",
+                fd + 3, c.proto, c.local_ip, c.local_port, c.ip, c.port, c.state,
+            );
+        }
+        out
+    }
+
+    fn random_connection(&mut self) -> ConnectionEntry {
+        let port = match PORTS.choose_weighted(&mut self.rng, |(_, w)| *w).map_or(443, |(p, _)| *p) {
+            0 => self.rng.gen_range(1024..=65535),
+            port => port,
+        };
+        let state = STATES.choose_weighted(&mut self.rng, |(_, w)| *w).map_or("ESTABLISHED", |(s, _)| *s);
+        ConnectionEntry {
+            local_ip: IpAddr::V4(LOCAL_IP),
+            local_port: self.rng.gen_range(32768..=60999),
+            ip: IpAddr::V4(random_public_ip(&mut self.rng)),
+            port,
+            proto: String::from("TCP"),
+            state: state.to_string(),
+            size: None,
+            rate: None,
+            last_seen: Instant::now(),
+        }
+    }
+}
+
+fn random_public_ip(rng: &mut impl Rng) -> Ipv4Addr {
+    loop {
+        let ip = Ipv4Addr::from(rng.r#gen::<u32>());
+        if is_public(ip) {
+            return ip;
+        }
+    }
+}
+
+fn is_public(ip: Ipv4Addr) -> bool {
+    localips::is_global(IpAddr::V4(ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_mock_cycles_are_consistent() {
+        let mut net = MockNetwork::new(Some(7));
+        let mut ever_seen = 0;
+        for _ in 0..10 {
+            let output = net.lsof_output();
+            let parsed = crate::parse_connections(&output);
+            assert_eq!(parsed.len(), net.active.len());
+
+            let keys: HashSet<_> = net.active.iter().map(ConnectionEntry::key).collect();
+            assert_eq!(keys.len(), net.active.len());
+            assert!(parsed.iter().all(|c| keys.contains(&c.key())));
+            assert!(net.active.iter().all(|c| matches!(c.ip, IpAddr::V4(ip) if is_public(ip)) && c.port > 0));
+            ever_seen = ever_seen.max(net.active.len());
+        }
+        assert!(ever_seen > 0);
+        assert_eq!(net.cycle, 10);
+    }
+
+    #[test]
+    fn test_mock_seed_is_reproducible() {
+        let mut a = MockNetwork::new(Some(42));
+        let mut b = MockNetwork::new(Some(42));
+        for _ in 0..5 {
+            assert_eq!(a.lsof_output(), b.lsof_output());
+        }
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(is_public(Ipv4Addr::new(93, 184, 216, 34)));
+        assert!(!is_public(Ipv4Addr::new(10, 1, 2, 3)));
+        assert!(!is_public(Ipv4Addr::new(100, 64, 0, 1)));
+        assert!(!is_public(Ipv4Addr::new(239, 0, 0, 1)));
+        assert!(!is_public(Ipv4Addr::new(192, 0, 2, 1)));
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A network namespace to run `lsof` in, e.g. a Docker container's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetNamespace {
+    path: PathBuf,
+}
+
+impl NetNamespace {
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn for_pid(pid: u32) -> Self {
+        Self::from_path(format!("/proc/{}/ns/net", pid))
+    }
+
+    /// Looks up the container's init PID with `docker inspect`.
+    pub fn for_container(name: &str) -> io::Result<Self> {
+        let out = Command::new("docker")
+            .args(["inspect", "--format", "{{.State.Pid}}", name])
+            .output()?;
+        if !out.status.success() {
+            return Err(io::Error::other(String::from_utf8_lossy(&out.stderr).trim().to_string()));
+        }
+        match parse_container_pid(&String::from_utf8_lossy(&out.stdout)) {
+            Some(pid) => Ok(Self::for_pid(pid)),
+            None => Err(io::Error::other(format!("container '{}' is not running", name))),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// `docker inspect` reports PID 0 for stopped containers.
+fn parse_container_pid(output: &str) -> Option<u32> {
+    output.trim().parse().ok().filter(|pid| *pid != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_container_pid() {
+        assert_eq!(parse_container_pid("4321\n"), Some(4321));
+        assert_eq!(parse_container_pid("0\n"), None);
+        assert_eq!(parse_container_pid("Error: No such object"), None);
+    }
+
+    #[test]
+    fn test_for_pid_path() {
+        assert_eq!(NetNamespace::for_pid(4321).path(), Path::new("/proc/4321/ns/net"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{net::IpAddr, time::Instant};
+#[cfg(target_os = "windows")]
+use std::{io, process::Command};
+
+use crate::ConnectionEntry;
+
+fn split_addr(addr: &str) -> Option<(IpAddr, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next().unwrap_or(host);
+    Some((host.parse().ok()?, port.parse().ok()?))
+}
+
+pub fn parse_netstat_windows(output: &str, pid: u32) -> Vec<ConnectionEntry> {
+    output.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (proto, local, remote, state, row_pid) = match fields.as_slice() {
+                [proto, local, remote, state, pid] => (*proto, *local, *remote, *state, *pid),
+                [proto, local, remote, pid] => (*proto, *local, *remote, "", *pid),
+                _ => return None,
+            };
+            if row_pid.parse::<u32>().ok()? != pid || !(proto == "TCP" || proto == "UDP") {
+                return None;
+            }
+
+            let (local_ip, local_port) = split_addr(local)?;
+            let (ip, port) = split_addr(remote)?;
+            if ip.is_unspecified() || port == 0 {
+                return None;
+            }
+
+            Some(ConnectionEntry {
+                local_ip,
+                local_port,
+                ip,
+                port,
+                proto: proto.to_string(),
+                state: state.to_string(),
+                size: None,
+                rate: None,
+                last_seen: Instant::now(),
+            })
+        })
+        .collect()
+}
+
+pub fn parse_tasklist_csv(output: &str, matches: impl Fn(&str) -> bool) -> Vec<(String, String)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(output.as_bytes());
+
+    reader.records()
+        .filter_map(|r| r.ok())
+        .filter_map(|r| Some((r.get(1)?.to_string(), r.get(0)?.to_string())))
+        .filter(|(pid, name)| pid.parse::<u32>().is_ok() && matches(name))
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+pub fn find_processes(pattern: &str) -> io::Result<Vec<(String, String)>> {
+    let out = Command::new("tasklist").args(["/FO", "CSV", "/NH"]).output()?;
+    let own_pid = std::process::id().to_string();
+    let re = regex::RegexBuilder::new(pattern).case_insensitive(true).build().ok();
+    let processes = parse_tasklist_csv(&String::from_utf8_lossy(&out.stdout), |name| match &re {
+        Some(re) => re.is_match(name),
+        None => name.to_lowercase().contains(&pattern.to_lowercase()),
+    });
+    Ok(processes.into_iter().filter(|(pid, _)| *pid != own_pid).collect())
+}
+
+#[cfg(target_os = "windows")]
+pub fn list_connections(pids: &str) -> io::Result<Vec<ConnectionEntry>> {
+    let out = Command::new("netstat").arg("-ano").output()?;
+    let output = String::from_utf8_lossy(&out.stdout);
+    Ok(pids.split(',')
+        .filter_map(|pid| pid.parse().ok())
+        .flat_map(|pid| parse_netstat_windows(&output, pid))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETSTAT: &str = "
+Active Connections
+
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1024
+  TCP    192.168.1.2:54321      93.184.216.34:443      ESTABLISHED     1234
+  TCP    192.168.1.2:54322      10.0.0.5:22            TIME_WAIT       5678
+  TCP    [2001:db8::2]:50000    [2001:db8::1]:443      ESTABLISHED     1234
+  TCP    [::]:445               [::]:0                 LISTENING       4
+  UDP    0.0.0.0:5353           *:*                                    1234
+";
+
+    #[test]
+    fn test_parse_netstat_windows_filters_by_pid() {
+        let conns = parse_netstat_windows(NETSTAT, 1234);
+        assert_eq!(conns.len(), 2);
+        assert_eq!(conns[0].ip, "93.184.216.34".parse::<IpAddr>().unwrap());
+        assert_eq!(conns[0].port, 443);
+        assert_eq!(conns[0].state, "ESTABLISHED");
+        assert_eq!(conns[1].ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(conns[1].local_ip, "2001:db8::2".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_parse_netstat_windows_skips_listening() {
+        assert!(parse_netstat_windows(NETSTAT, 1024).is_empty());
+        assert!(parse_netstat_windows(NETSTAT, 4).is_empty());
+    }
+
+    #[test]
+    fn test_parse_tasklist_csv() {
+        let output = "\"nginx.exe\",\"1234\",\"Services\",\"0\",\"10,240 K\"\r\n\"explorer.exe\",\"4321\",\"Console\",\"1\",\"98,000 K\"\r\n";
+        let procs = parse_tasklist_csv(output, |name| name.contains("nginx"));
+        assert_eq!(procs, vec![(String::from("1234"), String::from("nginx.exe"))]);
+    }
+}
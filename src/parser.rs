@@ -0,0 +1,212 @@
+/// The fields of one `lsof -i` connection line, before any IP parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawConnection {
+    /// `host:port`, with IPv6 hosts in brackets as lsof prints them.
+    pub local: String,
+    pub remote: String,
+    pub proto: String,
+    /// Empty when lsof prints no `(STATE)`, e.g. for UDP.
+    pub state: String,
+    pub size: Option<u64>,
+}
+
+/// Returns `None` for lines without a remote endpoint: the header, LISTEN
+/// sockets, UNIX sockets and anything malformed.
+pub fn parse_lsof_line(line: &str) -> Option<RawConnection> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let name_idx = fields.iter().position(|f| f.contains("->"))?;
+    let (local, remote) = fields[name_idx].split_once("->")?;
+    split_endpoint(local)?;
+    split_endpoint(remote)?;
+
+    let proto = if name_idx > 0 { fields[name_idx - 1] } else { "" };
+    let size = if name_idx > 1 { parse_size_off(fields[name_idx - 2]) } else { None };
+    let state = fields.get(name_idx + 1)
+        .map(|s| s.trim_start_matches('(').trim_end_matches(')'))
+        .unwrap_or("");
+
+    Some(RawConnection {
+        local: local.to_string(),
+        remote: remote.to_string(),
+        proto: proto.to_string(),
+        state: state.to_string(),
+        size,
+    })
+}
+
+/// The fields of one `lsof -i` listening socket line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawListener {
+    /// `host:port`; the host is `*` when bound to every interface.
+    pub local: String,
+    pub proto: String,
+    pub ipv6: bool,
+}
+
+/// Returns `None` for anything but a `(LISTEN)` line.
+pub fn parse_listen_line(line: &str) -> Option<RawListener> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let state_idx = fields.iter().position(|f| *f == "(LISTEN)")?;
+    let local = fields[..state_idx].last()?;
+    split_endpoint(local)?;
+    let proto = if state_idx > 1 { fields[state_idx - 2] } else { "" };
+
+    Some(RawListener {
+        local: local.to_string(),
+        proto: proto.to_string(),
+        ipv6: fields.contains(&"IPv6"),
+    })
+}
+
+/// Splits `host:port` or `[v6host]:port`, dropping the brackets.
+pub fn split_endpoint(endpoint: &str) -> Option<(&str, &str)> {
+    let (host, port) = endpoint.rsplit_once(':')?;
+    let host = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    if host.is_empty() || port.is_empty() {
+        return None;
+    }
+    Some((host, port))
+}
+
+// SIZE/OFF is printed as a plain size, a `0t` decimal offset or a `0x` hex offset.
+fn parse_size_off(field: &str) -> Option<u64> {
+    if let Some(dec) = field.strip_prefix("0t") {
+        dec.parse().ok()
+    } else if let Some(hex) = field.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        field.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(local: &str, remote: &str, proto: &str, state: &str, size: Option<u64>) -> RawConnection {
+        RawConnection {
+            local: local.to_string(),
+            remote: remote.to_string(),
+            proto: proto.to_string(),
+            state: state.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_ipv4_tcp_established() {
+        let line = "firefox 1234 user 45u IPv4 0x1 0t0 TCP 192.168.1.2:54321->93.184.216.34:443 (ESTABLISHED)";
+        assert_eq!(parse_lsof_line(line), Some(raw("192.168.1.2:54321", "93.184.216.34:443", "TCP", "ESTABLISHED", Some(0))));
+    }
+
+    #[test]
+    fn test_ipv4_tcp_close_wait() {
+        let line = "nginx 88 www 12u IPv4 0x5 0t0 TCP 10.0.0.5:80->10.0.0.9:51000 (CLOSE_WAIT)";
+        assert_eq!(parse_lsof_line(line).unwrap().state, "CLOSE_WAIT");
+    }
+
+    #[test]
+    fn test_ipv4_udp_without_state() {
+        let line = "dnsmasq 77 root 4u IPv4 0x9 0t0 UDP 10.0.0.2:53000->8.8.8.8:53";
+        assert_eq!(parse_lsof_line(line), Some(raw("10.0.0.2:53000", "8.8.8.8:53", "UDP", "", Some(0))));
+    }
+
+    #[test]
+    fn test_ipv6_tcp() {
+        let line = "firefox 1234 user 46u IPv6 0x2 0t0 TCP [2001:db8::2]:40000->[2001:db8::1]:443 (ESTABLISHED)";
+        let conn = parse_lsof_line(line).unwrap();
+        assert_eq!(conn.remote, "[2001:db8::1]:443");
+        assert_eq!(split_endpoint(&conn.remote), Some(("2001:db8::1", "443")));
+        assert_eq!(split_endpoint(&conn.local), Some(("2001:db8::2", "40000")));
+    }
+
+    #[test]
+    fn test_ipv6_loopback() {
+        let line = "redis 5 redis 7u IPv6 0x3 0t0 TCP [::1]:6379->[::1]:41234 (ESTABLISHED)";
+        assert_eq!(split_endpoint(&parse_lsof_line(line).unwrap().remote), Some(("::1", "41234")));
+    }
+
+    #[test]
+    fn test_ipv6_mapped_ipv4() {
+        let line = "java 300 app 90u IPv6 0x4 0t0 TCP [::ffff:10.0.0.2]:8080->[::ffff:10.0.0.7]:52000 (ESTABLISHED)";
+        let conn = parse_lsof_line(line).unwrap();
+        assert_eq!(split_endpoint(&conn.remote), Some(("::ffff:10.0.0.7", "52000")));
+    }
+
+    #[test]
+    fn test_size_off_formats() {
+        let line = "curl 99 user 5u IPv4 0x1 0t2048 TCP 10.0.0.2:40000->10.0.0.1:80 (ESTABLISHED)";
+        assert_eq!(parse_lsof_line(line).unwrap().size, Some(2048));
+        assert_eq!(parse_size_off("0x10"), Some(16));
+        assert_eq!(parse_size_off("512"), Some(512));
+        assert_eq!(parse_size_off("n/a"), None);
+    }
+
+    #[test]
+    fn test_extra_whitespace() {
+        let line = "  curl   99  user  5u  IPv4  0x1  0t0   TCP   10.0.0.2:40000->10.0.0.1:80   (ESTABLISHED)  ";
+        assert_eq!(parse_lsof_line(line), Some(raw("10.0.0.2:40000", "10.0.0.1:80", "TCP", "ESTABLISHED", Some(0))));
+    }
+
+    #[test]
+    fn test_tab_separated() {
+        let line = "curl\t99\tuser\t5u\tIPv4\t0x1\t0t0\tTCP\t10.0.0.2:40000->10.0.0.1:80\t(ESTABLISHED)";
+        assert_eq!(parse_lsof_line(line).unwrap().remote, "10.0.0.1:80");
+    }
+
+    #[test]
+    fn test_header_line() {
+        assert_eq!(parse_lsof_line("COMMAND   PID USER   FD   TYPE DEVICE SIZE/OFF NODE NAME"), None);
+    }
+
+    #[test]
+    fn test_listen_socket() {
+        assert_eq!(parse_lsof_line("nginx 1234 root 6u IPv4 0x3 0t0 TCP *:80 (LISTEN)"), None);
+        assert_eq!(parse_lsof_line("nginx 1234 root 7u IPv6 0x4 0t0 TCP [::]:80 (LISTEN)"), None);
+    }
+
+    #[test]
+    fn test_udp_without_remote() {
+        assert_eq!(parse_lsof_line("dnsmasq 77 root 4u IPv4 0x9 0t0 UDP *:53"), None);
+    }
+
+    #[test]
+    fn test_unix_socket() {
+        assert_eq!(parse_lsof_line("nginx 1234 root 3u unix 0xffff 0t0 12345 /run/nginx.sock type=STREAM"), None);
+        assert_eq!(parse_lsof_line("app 1 user 5u unix 0xffff 0t0 ->0xfffe"), None);
+    }
+
+    #[test]
+    fn test_regular_file() {
+        assert_eq!(parse_lsof_line("nginx 1234 root 4w REG 8,1 1048576 393 /var/log/nginx/access.log"), None);
+    }
+
+    #[test]
+    fn test_malformed_lines() {
+        assert_eq!(parse_lsof_line(""), None);
+        assert_eq!(parse_lsof_line("->"), None);
+        assert_eq!(parse_lsof_line("TCP a->b"), None);
+        assert_eq!(parse_lsof_line("TCP 10.0.0.1:->10.0.0.2:80"), None);
+        assert_eq!(parse_lsof_line("TCP 10.0.0.1:5000->:80"), None);
+    }
+
+    #[test]
+    fn test_listen_lines() {
+        let line = "nginx 88 root 6u IPv4 0x5 0t0 TCP *:8080 (LISTEN)";
+        assert_eq!(parse_listen_line(line), Some(RawListener { local: String::from("*:8080"), proto: String::from("TCP"), ipv6: false }));
+        let line = "redis 5 redis 7u IPv6 0x3 0t0 TCP [::1]:6379 (LISTEN)";
+        let listener = parse_listen_line(line).unwrap();
+        assert!(listener.ipv6);
+        assert_eq!(split_endpoint(&listener.local), Some(("::1", "6379")));
+        assert_eq!(parse_listen_line("(LISTEN)"), None);
+        assert_eq!(parse_listen_line("firefox 1234 user 45u IPv4 0x1 0t0 TCP 192.168.1.2:54321->93.184.216.34:443 (ESTABLISHED)"), None);
+    }
+
+    #[test]
+    fn test_bare_name_field() {
+        let conn = parse_lsof_line("10.0.0.1:5000->10.0.0.2:80").unwrap();
+        assert_eq!(conn.proto, "");
+        assert_eq!(conn.state, "");
+        assert_eq!(conn.size, None);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    io::{self, Write},
+    net::IpAddr,
+};
+
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_SNAPLEN: u32 = 65535;
+const LINKTYPE_RAW: u32 = 101;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+
+pub fn write_pcap_global_header(w: &mut impl Write) -> io::Result<()> {
+    w.write_all(&PCAP_MAGIC.to_le_bytes())?;
+    w.write_all(&2u16.to_le_bytes())?;
+    w.write_all(&4u16.to_le_bytes())?;
+    w.write_all(&0i32.to_le_bytes())?;
+    w.write_all(&0u32.to_le_bytes())?;
+    w.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+    w.write_all(&LINKTYPE_RAW.to_le_bytes())
+}
+
+pub fn write_synthetic_syn(w: &mut impl Write, src: (IpAddr, u16), dst: (IpAddr, u16), ts: u64) -> io::Result<()> {
+    write_synthetic_packet(w, TCP_SYN, src, dst, ts)
+}
+
+pub fn write_synthetic_fin(w: &mut impl Write, src: (IpAddr, u16), dst: (IpAddr, u16), ts: u64) -> io::Result<()> {
+    write_synthetic_packet(w, TCP_FIN, src, dst, ts)
+}
+
+// `ts` is in microseconds since the Unix epoch. Fails with `InvalidInput`
+// when one endpoint is IPv4 and the other a real IPv6 address.
+fn write_synthetic_packet(w: &mut impl Write, flags: u8, src: (IpAddr, u16), dst: (IpAddr, u16), ts: u64) -> io::Result<()> {
+    let packet = build_packet(flags, src, dst)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "mixed IPv4/IPv6 endpoints"))?;
+
+    w.write_all(&((ts / 1_000_000) as u32).to_le_bytes())?;
+    w.write_all(&((ts % 1_000_000) as u32).to_le_bytes())?;
+    w.write_all(&(packet.len() as u32).to_le_bytes())?;
+    w.write_all(&(packet.len() as u32).to_le_bytes())?;
+    w.write_all(&packet)
+}
+
+fn build_packet(flags: u8, (src, src_port): (IpAddr, u16), (dst, dst_port): (IpAddr, u16)) -> Option<Vec<u8>> {
+    let mut tcp = tcp_header(flags, src_port, dst_port);
+    let mut packet = Vec::new();
+
+    // A dual-stack socket reports IPv4 peers as `::ffff:a.b.c.d`.
+    match (src.to_canonical(), dst.to_canonical()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let mut pseudo = Vec::new();
+            pseudo.extend_from_slice(&s.octets());
+            pseudo.extend_from_slice(&d.octets());
+            pseudo.extend_from_slice(&[0, 6]);
+            pseudo.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+            set_tcp_checksum(&mut tcp, &pseudo);
+
+            let total_len = (20 + tcp.len()) as u16;
+            let mut ip = vec![0x45, 0];
+            ip.extend_from_slice(&total_len.to_be_bytes());
+            ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+            ip.extend_from_slice(&s.octets());
+            ip.extend_from_slice(&d.octets());
+            let sum = checksum(&ip);
+            ip[10..12].copy_from_slice(&sum.to_be_bytes());
+            packet.extend_from_slice(&ip);
+        }
+        (IpAddr::V6(s), IpAddr::V6(d)) => {
+            let mut pseudo = Vec::new();
+            pseudo.extend_from_slice(&s.octets());
+            pseudo.extend_from_slice(&d.octets());
+            pseudo.extend_from_slice(&(tcp.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, 6]);
+            set_tcp_checksum(&mut tcp, &pseudo);
+
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&[6, 64]);
+            packet.extend_from_slice(&s.octets());
+            packet.extend_from_slice(&d.octets());
+        }
+        _ => return None,
+    }
+
+    packet.extend_from_slice(&tcp);
+    Some(packet)
+}
+
+fn tcp_header(flags: u8, src_port: u16, dst_port: u16) -> Vec<u8> {
+    let mut tcp = Vec::with_capacity(20);
+    tcp.extend_from_slice(&src_port.to_be_bytes());
+    tcp.extend_from_slice(&dst_port.to_be_bytes());
+    tcp.extend_from_slice(&0u32.to_be_bytes());
+    tcp.extend_from_slice(&0u32.to_be_bytes());
+    tcp.extend_from_slice(&[0x50, flags]);
+    tcp.extend_from_slice(&65535u16.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp
+}
+
+fn set_tcp_checksum(tcp: &mut [u8], pseudo: &[u8]) {
+    let mut data = pseudo.to_vec();
+    data.extend_from_slice(tcp);
+    let sum = checksum(&data);
+    tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+}
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_header_layout() {
+        let mut buf = Vec::new();
+        write_pcap_global_header(&mut buf).unwrap();
+        assert_eq!(buf.len(), 24);
+        assert_eq!(&buf[0..4], &[0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(u32::from_le_bytes(buf[20..24].try_into().unwrap()), LINKTYPE_RAW);
+    }
+
+    #[test]
+    fn test_synthetic_syn_ipv4() {
+        let mut buf = Vec::new();
+        let src = "192.168.1.2".parse().unwrap();
+        let dst = "93.184.216.34".parse().unwrap();
+        write_synthetic_syn(&mut buf, (src, 50000), (dst, 443), 1_700_000_000_500_000).unwrap();
+
+        assert_eq!(u32::from_le_bytes(buf[0..4].try_into().unwrap()), 1_700_000_000);
+        assert_eq!(u32::from_le_bytes(buf[4..8].try_into().unwrap()), 500_000);
+        assert_eq!(u32::from_le_bytes(buf[8..12].try_into().unwrap()), 40);
+
+        let ip = &buf[16..36];
+        assert_eq!(ip[0], 0x45);
+        assert_eq!(checksum(ip), 0);
+        assert_eq!(&ip[16..20], &[93, 184, 216, 34]);
+
+        let tcp = &buf[36..];
+        assert_eq!(u16::from_be_bytes([tcp[0], tcp[1]]), 50000);
+        assert_eq!(u16::from_be_bytes([tcp[2], tcp[3]]), 443);
+        assert_eq!(tcp[13], TCP_SYN);
+    }
+
+    #[test]
+    fn test_synthetic_fin_ipv6() {
+        let mut buf = Vec::new();
+        let src = "2001:db8::2".parse().unwrap();
+        let dst = "2001:db8::1".parse().unwrap();
+        write_synthetic_fin(&mut buf, (src, 40000), (dst, 80), 0).unwrap();
+
+        assert_eq!(u32::from_le_bytes(buf[8..12].try_into().unwrap()), 60);
+        assert_eq!(buf[16] >> 4, 6);
+        assert_eq!(buf[16 + 40 + 13], TCP_FIN);
+    }
+
+    #[test]
+    fn test_mixed_families_rejected() {
+        let mut buf = Vec::new();
+        let src = "192.168.1.2".parse().unwrap();
+        let dst = "2001:db8::1".parse().unwrap();
+        let err = write_synthetic_syn(&mut buf, (src, 50000), (dst, 443), 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_ipv4_mapped_peer_written_as_ipv4() {
+        let mut buf = Vec::new();
+        let src = "192.168.1.2".parse().unwrap();
+        let dst = "::ffff:93.184.216.34".parse().unwrap();
+        write_synthetic_syn(&mut buf, (src, 50000), (dst, 443), 0).unwrap();
+        assert_eq!(buf[16], 0x45);
+        assert_eq!(&buf[16 + 16..16 + 20], &[93, 184, 216, 34]);
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+
+use crate::error::MonitorError;
+
+/// Looks up the processes to monitor; swapped out for a mock in tests.
+pub trait ProcessFinder: Send {
+    /// Returns `(pid, name)` pairs matching `target`, excluding netmonrs itself
+    /// and the process that started it (e.g. `sudo netmonrs ...`).
+    /// The names feed the "Monitoring: ..." status line.
+    fn find_pids(&self, target: &str) -> Result<Vec<(String, String)>, MonitorError>;
+}
+
+/// Matches full command lines with `pgrep -lf` (`tasklist` on Windows).
+pub struct PgrepFinder {
+    pub use_regex: bool,
+}
+
+impl ProcessFinder for PgrepFinder {
+    fn find_pids(&self, target: &str) -> Result<Vec<(String, String)>, MonitorError> {
+        let pattern = if self.use_regex { target.to_string() } else { regex::escape(target) };
+        run_pgrep(&pattern).map_err(MonitorError::PgrepFailed)
+    }
+}
+
+/// Picks the single process whose command line best matches the target.
+pub struct FuzzyFinder;
+
+impl ProcessFinder for FuzzyFinder {
+    fn find_pids(&self, target: &str) -> Result<Vec<(String, String)>, MonitorError> {
+        let processes = run_pgrep(".").map_err(MonitorError::PgrepFailed)?;
+        Ok(best_fuzzy_match(target, processes).into_iter().collect())
+    }
+}
+
+/// The name of the returned process carries its score for the status bar.
+fn best_fuzzy_match(target: &str, processes: Vec<(String, String)>) -> Option<(String, String)> {
+    processes.into_iter()
+        .filter_map(|(pid, name)| Some((fuzzy_score(target, &name)?, pid, name)))
+        .max_by_key(|(score, pid, _)| (*score, std::cmp::Reverse(pid.parse::<u32>().unwrap_or(u32::MAX))))
+        .map(|(score, pid, name)| (pid, format!("{} [score: {}]", name, score)))
+}
+
+/// Scores `pattern` as a case-insensitive subsequence of `candidate`, in the
+/// spirit of sublime_fuzzy: consecutive characters and characters at the
+/// start of a word earn bonuses, skipped characters cost a little.
+/// Returns the score of the best placement, not just the leftmost one.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    const MATCH: i64 = 10;
+    const CONSECUTIVE: i64 = 15;
+    const WORD_START: i64 = 10;
+    const MAX_GAP_PENALTY: usize = 5;
+
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let (first, rest) = pattern.split_first()?;
+    let bonus = |i: usize| MATCH + if i == 0 || !candidate[i - 1].is_alphanumeric() { WORD_START } else { 0 };
+    // row[i]: best score with the current pattern character matched at i.
+    let mut row: Vec<Option<i64>> = candidate.iter().enumerate()
+        .map(|(i, c)| (c == first).then(|| bonus(i)))
+        .collect();
+    for pc in rest {
+        let mut next = vec![None; candidate.len()];
+        // Best of the previous row far enough back to pay the full gap penalty.
+        let mut far: Option<i64> = None;
+        for i in 0..candidate.len() {
+            if i > MAX_GAP_PENALTY {
+                far = far.max(row[i - 1 - MAX_GAP_PENALTY]);
+            }
+            if candidate[i] != *pc {
+                continue;
+            }
+            let near = (i.saturating_sub(MAX_GAP_PENALTY)..i)
+                .filter_map(|p| {
+                    let gap = (i - p - 1) as i64;
+                    Some(row[p]? - gap + if gap == 0 { CONSECUTIVE } else { 0 })
+                })
+                .max();
+            next[i] = near.max(far.map(|s| s - MAX_GAP_PENALTY as i64)).map(|s| s + bonus(i));
+        }
+        row = next;
+    }
+    row.into_iter().flatten().max()
+}
+
+/// Drops processes whose short name matches one of `excludes`, exactly or as
+/// a glob. `names_of` looks all PIDs up at once; PIDs it can't name are kept.
+/// Returns the remaining processes and how many were dropped.
+pub fn exclude_processes(
+    processes: Vec<(String, String)>,
+    excludes: &[String],
+    glob: bool,
+    names_of: impl FnOnce(&[&str]) -> HashMap<String, String>,
+) -> (Vec<(String, String)>, usize) {
+    if excludes.is_empty() || processes.is_empty() {
+        return (processes, 0);
+    }
+    let pids: Vec<&str> = processes.iter().map(|(pid, _)| pid.as_str()).collect();
+    let names = names_of(&pids);
+    let before = processes.len();
+    let kept: Vec<_> = processes.into_iter()
+        .filter(|(pid, _)| !names.get(pid).is_some_and(|name| is_excluded(name, excludes, glob)))
+        .collect();
+    let excluded = before - kept.len();
+    (kept, excluded)
+}
+
+fn is_excluded(name: &str, excludes: &[String], glob: bool) -> bool {
+    excludes.iter().any(|pattern| if glob { glob_match(pattern, name) } else { pattern == name })
+}
+
+/// Whole-string glob match with `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The short command names of `pids`, keyed by PID, from a single
+/// `ps -o pid=,comm= -p <pid>,<pid>...`.
+#[cfg(not(target_os = "windows"))]
+pub fn process_names(pids: &[&str]) -> HashMap<String, String> {
+    match Command::new("ps").arg("-o").arg("pid=,comm=").arg("-p").arg(pids.join(",")).output() {
+        Ok(out) => parse_ps_comm(&String::from_utf8_lossy(&out.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn process_names(_pids: &[&str]) -> HashMap<String, String> {
+    HashMap::new()
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn parse_ps_comm(output: &str) -> HashMap<String, String> {
+    output.lines()
+        .filter_map(|line| {
+            let (pid, name) = line.trim().split_once(char::is_whitespace)?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (pid.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run_pgrep(pattern: &str) -> std::io::Result<Vec<(String, String)>> {
+    let out = Command::new("pgrep").arg("-lf").arg(pattern).output()?;
+    Ok(parse_pgrep_output(&String::from_utf8_lossy(&out.stdout)))
+}
+
+#[cfg(target_os = "windows")]
+fn run_pgrep(pattern: &str) -> std::io::Result<Vec<(String, String)>> {
+    crate::netstat::find_processes(pattern)
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+pub fn parse_pgrep_output(output: &str) -> Vec<(String, String)> {
+    let own_pids = [Some(std::process::id()), parent_pid()].map(|pid| pid.map(|pid| pid.to_string()));
+    output.lines()
+        .filter_map(|line| {
+            let (pid, name) = line.trim().split_once(char::is_whitespace)?;
+            if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit()) || own_pids.iter().flatten().any(|own| own == pid) {
+                return None;
+            }
+            Some((pid.to_string(), name.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn parent_pid() -> Option<u32> {
+    Some(std::os::unix::process::parent_id())
+}
+
+#[cfg(not(unix))]
+fn parent_pid() -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+pub struct MockFinder {
+    pub pids: Vec<String>,
+}
+
+#[cfg(test)]
+impl ProcessFinder for MockFinder {
+    fn find_pids(&self, target: &str) -> Result<Vec<(String, String)>, MonitorError> {
+        Ok(self.pids.iter().map(|pid| (pid.clone(), target.to_string())).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranks_higher(pattern: &str, better: &str, worse: &str) -> bool {
+        fuzzy_score(pattern, better) > fuzzy_score(pattern, worse)
+    }
+
+    #[test]
+    fn test_fuzzy_score_requires_subsequence() {
+        assert!(fuzzy_score("ngx", "nginx").is_some());
+        assert_eq!(fuzzy_score("xgn", "nginx"), None);
+        assert_eq!(fuzzy_score("", "nginx"), None);
+        assert_eq!(fuzzy_score("NGINX", "/usr/sbin/nginx"), fuzzy_score("nginx", "/usr/sbin/nginx"));
+    }
+
+    #[test]
+    fn test_fuzzy_score_ranking() {
+        assert!(ranks_higher("nginx", "nginx", "n-g-i-n-x"));
+        assert!(ranks_higher("nginx", "nginx: master process", "engine-x"));
+        assert!(ranks_higher("pgb", "pgbouncer", "pg_dumpbackup"));
+        assert!(ranks_higher("redis", "redis-server *:6379", "red-dist"));
+        assert!(ranks_higher("node", "node server.js", "no_de"));
+        assert!(ranks_higher("ssh", "sshd: root", "systemd-hashd"));
+        assert!(ranks_higher("fire", "/usr/lib/firefox/firefox", "/opt/fix-iredis"));
+        assert!(ranks_higher("sql", "mysqld", "s-q-l"));
+        // The best placement counts, not the first character that fits.
+        assert_eq!(fuzzy_score("nginx", "sudo netmonrs --fuzzy nginx"), fuzzy_score("nginx", "nginx"));
+        assert_eq!(fuzzy_score("nginx", "nxx nginx"), fuzzy_score("nginx", "nginx"));
+    }
+
+    #[test]
+    fn test_best_fuzzy_match() {
+        let processes = vec![
+            (String::from("300"), String::from("nginx: worker process")),
+            (String::from("100"), String::from("nginx: master process")),
+            (String::from("200"), String::from("engine-x")),
+            (String::from("400"), String::from("bash")),
+        ];
+        let (pid, name) = best_fuzzy_match("nginx", processes).unwrap();
+        assert_eq!(pid, "100");
+        assert!(name.starts_with("nginx: master process [score: "));
+        assert_eq!(best_fuzzy_match("zzz", vec![(String::from("1"), String::from("bash"))]), None);
+    }
+
+    fn procs(pids: &[&str]) -> Vec<(String, String)> {
+        pids.iter().map(|pid| (pid.to_string(), String::from("nginx"))).collect()
+    }
+
+    fn mock_ps(pids: &[&str]) -> HashMap<String, String> {
+        let output = "    1 nginx\n    2 fluent-bit\n    3 healthcheck-agent\n";
+        parse_ps_comm(output).into_iter().filter(|(pid, _)| pids.contains(&pid.as_str())).collect()
+    }
+
+    #[test]
+    fn test_exclude_processes_exact() {
+        let excludes = vec![String::from("fluent-bit"), String::from("healthcheck")];
+        let (kept, excluded) = exclude_processes(procs(&["1", "2", "3", "4"]), &excludes, false, mock_ps);
+        assert_eq!(kept, procs(&["1", "3", "4"]));
+        assert_eq!(excluded, 1);
+
+        let (kept, excluded) = exclude_processes(procs(&["1", "2"]), &[], false, mock_ps);
+        assert_eq!((kept.len(), excluded), (2, 0));
+    }
+
+    #[test]
+    fn test_exclude_processes_glob() {
+        let excludes = vec![String::from("health*"), String::from("fluent-?it")];
+        let (kept, excluded) = exclude_processes(procs(&["1", "2", "3"]), &excludes, true, mock_ps);
+        assert_eq!(kept, procs(&["1"]));
+        assert_eq!(excluded, 2);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("nginx", "nginx"));
+        assert!(!glob_match("nginx", "nginx-worker"));
+        assert!(glob_match("*worker", "nginx-worker"));
+        assert!(glob_match("n*x*r", "nginx-worker"));
+        assert!(!glob_match("?ginx", "ginx"));
+    }
+
+    #[test]
+    fn test_parse_ps_comm() {
+        let names = parse_ps_comm("  812 fluent-bit\n 1204 /usr/sbin/nginx\n\n 99 \n");
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["812"], "fluent-bit");
+        assert_eq!(names["1204"], "/usr/sbin/nginx");
+        assert!(parse_ps_comm("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_pgrep_output() {
+        let out = "1234 nginx\n1235 nginx-worker\ngarbage\n\n";
+        let procs = parse_pgrep_output(out);
+        assert_eq!(procs, vec![
+            (String::from("1234"), String::from("nginx")),
+            (String::from("1235"), String::from("nginx-worker")),
+        ]);
+        let own = format!("{} netmonrs --fuzzy nginx\n{} sudo netmonrs --fuzzy nginx\n", std::process::id(), parent_pid().unwrap());
+        assert!(parse_pgrep_output(&own).is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    sync::mpsc::{SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::BackgroundEvent;
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sends on a bounded channel, waiting at most `timeout` for the UI to make
+/// room. Dropped events are reported with a `Warning` once room frees up.
+pub struct EventSender {
+    tx: SyncSender<BackgroundEvent>,
+    timeout: Duration,
+    dropped: u64,
+    closed: bool,
+}
+
+impl EventSender {
+    pub fn new(tx: SyncSender<BackgroundEvent>, timeout: Duration) -> Self {
+        Self { tx, timeout, dropped: 0, closed: false }
+    }
+
+    /// True once the receiving side has been dropped, e.g. its tab was closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns `false` if the event was dropped.
+    pub fn send(&mut self, event: BackgroundEvent) -> bool {
+        if self.dropped > 0 {
+            let msg = format!("Event queue full, dropped {} update(s)", self.dropped);
+            if self.send_with_timeout(BackgroundEvent::Warning(msg)) {
+                self.dropped = 0;
+            }
+        }
+        let sent = self.send_with_timeout(event);
+        if !sent {
+            self.dropped += 1;
+        }
+        sent
+    }
+
+    fn send_with_timeout(&mut self, mut event: BackgroundEvent) -> bool {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match self.tx.try_send(event) {
+                Ok(()) => return true,
+                Err(TrySendError::Disconnected(_)) => {
+                    self.closed = true;
+                    return false;
+                }
+                Err(TrySendError::Full(e)) => {
+                    if Instant::now() >= deadline {
+                        return false;
+                    }
+                    event = e;
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn warning(msg: &str) -> BackgroundEvent {
+        BackgroundEvent::Warning(msg.to_string())
+    }
+
+    #[test]
+    fn test_slow_consumer_drops_and_warns() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let mut sender = EventSender::new(tx, Duration::from_millis(20));
+        assert!(sender.send(warning("first")));
+        assert!(!sender.send(warning("second")));
+
+        let consumer = thread::spawn(move || {
+            rx.iter()
+                .map(|event| {
+                    thread::sleep(Duration::from_millis(30));
+                    match event {
+                        BackgroundEvent::Warning(msg) => msg,
+                        _ => panic!("unexpected event"),
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+
+        sender.timeout = Duration::from_secs(1);
+        assert!(sender.send(warning("third")));
+        drop(sender);
+
+        assert_eq!(consumer.join().unwrap(), vec!["first", "Event queue full, dropped 1 update(s)", "third"]);
+    }
+
+    #[test]
+    fn test_closed_after_receiver_dropped() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let mut sender = EventSender::new(tx, Duration::from_millis(20));
+        assert!(sender.send(warning("first")));
+        assert!(!sender.is_closed());
+        drop(rx);
+        assert!(!sender.send(warning("second")));
+        assert!(sender.is_closed());
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+pub const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Column separator for `--rate-file-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateFormat {
+    Tsv,
+    Csv,
+    #[default]
+    Space,
+}
+
+impl RateFormat {
+    fn separator(self) -> &'static str {
+        match self {
+            RateFormat::Tsv => "\t",
+            RateFormat::Csv => ",",
+            RateFormat::Space => " ",
+        }
+    }
+}
+
+pub fn parse_rate_format(s: &str) -> Result<RateFormat, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "tsv" => Ok(RateFormat::Tsv),
+        "csv" => Ok(RateFormat::Csv),
+        "space" => Ok(RateFormat::Space),
+        _ => Err(format!("expected tsv, csv or space, got '{}'", s)),
+    }
+}
+
+pub fn format_header(format: RateFormat) -> String {
+    format!("# {}", ["ts", "active_count", "new_per_sec", "total_unique"].join(format.separator()))
+}
+
+pub fn format_rate_line(format: RateFormat, ts: i64, active: usize, new_per_sec: f64, unique: usize) -> String {
+    let sep = format.separator();
+    format!("{ts}{sep}{active}{sep}{new_per_sec:.2}{sep}{unique}")
+}
+
+pub fn append_rate_line(path: &Path, format: RateFormat, ts: i64, active: usize, new_per_sec: f64, unique: usize) -> io::Result<()> {
+    append_line(path, &format_rate_line(format, ts, active, new_per_sec, unique))
+}
+
+fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    file.flush()
+}
+
+/// The `--rate-file` and the new IPs counted since its last line.
+#[derive(Debug)]
+pub struct RateFile {
+    path: PathBuf,
+    format: RateFormat,
+    last_write: Instant,
+    new_since_write: usize,
+}
+
+impl RateFile {
+    /// Appends the comment header, so every run starts a new block.
+    pub fn create(path: PathBuf, format: RateFormat, now: Instant) -> io::Result<Self> {
+        append_line(&path, &format_header(format))?;
+        Ok(Self { path, format, last_write: now, new_since_write: 0 })
+    }
+
+    pub fn record_new(&mut self, count: usize) {
+        self.new_since_write += count;
+    }
+
+    /// Writes a line once `RATE_INTERVAL` has passed since the previous one.
+    pub fn write_if_due(&mut self, now: Instant, ts: i64, active: usize, unique: usize) -> io::Result<()> {
+        let elapsed = now.duration_since(self.last_write);
+        if elapsed < RATE_INTERVAL {
+            return Ok(());
+        }
+        let new_per_sec = self.new_since_write as f64 / elapsed.as_secs_f64();
+        self.last_write = now;
+        self.new_since_write = 0;
+        append_rate_line(&self.path, self.format, ts, active, new_per_sec, unique)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_format_rate_line() {
+        assert_eq!(format_rate_line(RateFormat::Space, 1704110400, 12, 0.5, 40), "1704110400 12 0.50 40");
+        assert_eq!(format_rate_line(RateFormat::Tsv, 1704110400, 12, 0.5, 40), "1704110400\t12\t0.50\t40");
+        assert_eq!(format_rate_line(RateFormat::Csv, 1704110400, 12, 0.5, 40), "1704110400,12,0.50,40");
+    }
+
+    #[test]
+    fn test_format_header() {
+        assert_eq!(format_header(RateFormat::Space), "# ts active_count new_per_sec total_unique");
+        assert_eq!(format_header(RateFormat::Tsv), "# ts\tactive_count\tnew_per_sec\ttotal_unique");
+        assert_eq!(format_header(RateFormat::Csv), "# ts,active_count,new_per_sec,total_unique");
+    }
+
+    #[test]
+    fn test_parse_rate_format() {
+        assert_eq!(parse_rate_format("CSV"), Ok(RateFormat::Csv));
+        assert_eq!(parse_rate_format("tsv"), Ok(RateFormat::Tsv));
+        assert_eq!(parse_rate_format("space"), Ok(RateFormat::Space));
+        assert!(parse_rate_format("json").is_err());
+    }
+
+    #[test]
+    fn test_rate_file_writes_once_per_interval() {
+        let path = std::env::temp_dir().join(format!("netmonrs-rate-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let start = Instant::now();
+
+        let mut rate = RateFile::create(path.clone(), RateFormat::Space, start).unwrap();
+        rate.record_new(3);
+        rate.write_if_due(start + Duration::from_millis(500), 100, 5, 3).unwrap();
+        rate.record_new(1);
+        rate.write_if_due(start + Duration::from_secs(2), 102, 6, 4).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# ts active_count new_per_sec total_unique\n102 6 2.00 4\n");
+        fs::remove_file(&path).unwrap();
+    }
+}
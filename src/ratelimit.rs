@@ -0,0 +1,86 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_NOTIFY_RATE_LIMIT: usize = 1;
+
+const GLOBAL_WINDOW: Duration = Duration::from_secs(1);
+const PER_KEY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Allows at most `limit` events per second overall and `limit` per key
+/// (usually an IP) per minute.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: usize,
+    global: VecDeque<Instant>,
+    per_key: HashMap<String, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, global: VecDeque::new(), per_key: HashMap::new() }
+    }
+
+    /// Records the event and returns `true` if it is within both limits.
+    pub fn check(&mut self, key: &str) -> bool {
+        self.check_at(key, Instant::now())
+    }
+
+    pub fn check_at(&mut self, key: &str, now: Instant) -> bool {
+        expire(&mut self.global, now, GLOBAL_WINDOW);
+        self.per_key.retain(|_, times| {
+            expire(times, now, PER_KEY_WINDOW);
+            !times.is_empty()
+        });
+
+        let key_count = self.per_key.get(key).map_or(0, VecDeque::len);
+        if self.global.len() >= self.limit || key_count >= self.limit {
+            return false;
+        }
+        self.global.push_back(now);
+        self.per_key.entry(key.to_string()).or_default().push_back(now);
+        true
+    }
+}
+
+fn expire(times: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+    while times.front().is_some_and(|at| now.duration_since(*at) >= window) {
+        times.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_limit_per_second() {
+        let mut limiter = RateLimiter::new(2);
+        let now = Instant::now();
+        assert!(limiter.check_at("10.0.0.1", now));
+        assert!(limiter.check_at("10.0.0.2", now));
+        assert!(!limiter.check_at("10.0.0.3", now));
+        assert!(!limiter.check_at("10.0.0.3", now + Duration::from_millis(999)));
+        assert!(limiter.check_at("10.0.0.3", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_per_key_limit_per_minute() {
+        let mut limiter = RateLimiter::new(1);
+        let now = Instant::now();
+        assert!(limiter.check_at("10.0.0.1", now));
+        assert!(!limiter.check_at("10.0.0.1", now + Duration::from_secs(59)));
+        assert!(limiter.check_at("10.0.0.2", now + Duration::from_secs(59)));
+        assert!(limiter.check_at("10.0.0.1", now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_rejected_events_are_not_counted() {
+        let mut limiter = RateLimiter::new(1);
+        let now = Instant::now();
+        assert!(limiter.check_at("a", now));
+        assert!(!limiter.check_at("b", now));
+        assert!(limiter.check_at("b", now + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// The longest connection seen across runs, kept in `--all-time-record`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationRecord {
+    pub ip: String,
+    #[serde(default)]
+    pub port: u16,
+    pub duration_secs: u64,
+}
+
+/// A missing or unreadable file means there is no record yet.
+pub fn load_record(path: &Path) -> Option<DurationRecord> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_record(path: &Path, record: &DurationRecord) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(record).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+/// Only whole seconds count, so a tie with the record doesn't replace it.
+pub fn is_new_record(current: Option<&DurationRecord>, duration: Duration) -> bool {
+    duration.as_secs() > current.map_or(0, |r| r.duration_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(duration_secs: u64) -> DurationRecord {
+        DurationRecord { ip: String::from("93.184.216.34"), port: 443, duration_secs }
+    }
+
+    #[test]
+    fn test_is_new_record() {
+        assert!(is_new_record(None, Duration::from_secs(1)));
+        assert!(!is_new_record(None, Duration::from_millis(999)));
+        assert!(is_new_record(Some(&record(60)), Duration::from_secs(61)));
+        assert!(!is_new_record(Some(&record(60)), Duration::from_millis(60_900)));
+        assert!(!is_new_record(Some(&record(60)), Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let path = std::env::temp_dir().join(format!("netmonrs-record-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(load_record(&path), None);
+
+        save_record(&path, &record(11640)).unwrap();
+        assert_eq!(load_record(&path), Some(record(11640)));
+
+        fs::write(&path, r#"{ "ip": "10.0.0.1", "duration_secs": 5 }"#).unwrap();
+        assert_eq!(load_record(&path), Some(DurationRecord { ip: String::from("10.0.0.1"), port: 0, duration_secs: 5 }));
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(load_record(&path), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, net::IpAddr, time::Duration};
+
+use chrono::{DateTime, Local};
+
+use crate::ConnectionEntry;
+
+const TOP_IPS: usize = 10;
+
+pub struct Stats {
+    pub elapsed: Duration,
+}
+
+pub fn generate_report(history: &[(DateTime<Local>, ConnectionEntry)], stats: &Stats) -> String {
+    let mut counts: HashMap<IpAddr, usize> = HashMap::new();
+    for (_, conn) in history {
+        *counts.entry(conn.ip).or_default() += 1;
+    }
+    let mut top: Vec<(IpAddr, usize)> = counts.iter().map(|(ip, n)| (*ip, *n)).collect();
+    top.sort_by_key(|(ip, n)| (std::cmp::Reverse(*n), *ip));
+    top.truncate(TOP_IPS);
+
+    let secs = stats.elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { history.len() as f64 / secs } else { 0.0 };
+    let timestamp = |entry: Option<&(DateTime<Local>, ConnectionEntry)>| {
+        entry.map(|(ts, _)| ts.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| String::from("-"))
+    };
+
+    let mut out = format!("=== Report ({}s) ===\n", stats.elapsed.as_secs());
+    out.push_str(&format!("Unique IPs: {}\n", counts.len()));
+    out.push_str(&format!("Connections opened: {}\n", history.len()));
+    out.push_str(&format!("Average: {:.2} connections/s\n", rate));
+    out.push_str(&format!("First connection: {}\n", timestamp(history.first())));
+    out.push_str(&format!("Last connection: {}\n", timestamp(history.last())));
+    out.push_str("Top IPs:\n");
+    for (ip, n) in top {
+        out.push_str(&format!("  {:<39} {}\n", ip, n));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_generate_report() {
+        let start = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let ips = ["1.1.1.1", "2.2.2.2", "1.1.1.1", "3.3.3.3", "1.1.1.1", "2.2.2.2", "4.4.4.4", "5.5.5.5", "1.1.1.1", "6.6.6.6"];
+        let history: Vec<(DateTime<Local>, ConnectionEntry)> = ips.iter().enumerate()
+            .map(|(i, ip)| {
+                let ts = start + chrono::Duration::seconds(i as i64 * 2);
+                (ts, crate::tests::test_conn(ip, 443))
+            })
+            .collect();
+
+        let report = generate_report(&history, &Stats { elapsed: Duration::from_secs(20) });
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines[0], "=== Report (20s) ===");
+        assert_eq!(lines[1], "Unique IPs: 6");
+        assert_eq!(lines[2], "Connections opened: 10");
+        assert_eq!(lines[3], "Average: 0.50 connections/s");
+        assert_eq!(lines[4], "First connection: 2024-05-01 12:00:00");
+        assert_eq!(lines[5], "Last connection: 2024-05-01 12:00:18");
+        assert_eq!(lines[6], "Top IPs:");
+        assert_eq!(lines[7].split_whitespace().collect::<Vec<_>>(), vec!["1.1.1.1", "4"]);
+        assert_eq!(lines[8].split_whitespace().collect::<Vec<_>>(), vec!["2.2.2.2", "2"]);
+        assert_eq!(lines[9].split_whitespace().collect::<Vec<_>>(), vec!["3.3.3.3", "1"]);
+        assert_eq!(lines.len(), 13);
+    }
+
+    #[test]
+    fn test_generate_report_empty() {
+        let report = generate_report(&[], &Stats { elapsed: Duration::ZERO });
+        assert!(report.contains("Unique IPs: 0"));
+        assert!(report.contains("Average: 0.00 connections/s"));
+        assert!(report.contains("First connection: -"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    net::IpAddr,
+    sync::mpsc::{self, Receiver, Sender, SyncSender},
+    thread,
+};
+
+use crate::BackgroundEvent;
+
+pub const MAX_CONCURRENT_LOOKUPS: usize = 5;
+
+/// Spawns the reverse DNS thread. Lookups are requested by sending an
+/// `IpAddr`; the thread exits once every request sender is dropped.
+pub fn spawn_resolver(results: SyncSender<BackgroundEvent>) -> Sender<IpAddr> {
+    let (tx, rx) = mpsc::channel::<IpAddr>();
+    thread::spawn(move || run_resolver(rx, results, dns_lookup::lookup_addr));
+    tx
+}
+
+fn run_resolver<F>(requests: Receiver<IpAddr>, results: SyncSender<BackgroundEvent>, lookup: F)
+where
+    F: Fn(&IpAddr) -> std::io::Result<String> + Copy + Send + 'static,
+{
+    // A channel pre-filled with one token per permitted lookup acts as a semaphore.
+    let (permit_tx, permits) = mpsc::sync_channel::<()>(MAX_CONCURRENT_LOOKUPS);
+    for _ in 0..MAX_CONCURRENT_LOOKUPS {
+        let _ = permit_tx.send(());
+    }
+
+    for ip in requests {
+        if permits.recv().is_err() {
+            break;
+        }
+        let results = results.clone();
+        let permit_tx = permit_tx.clone();
+        thread::spawn(move || {
+            let hostname = lookup(&ip).ok().filter(|h| *h != ip.to_string());
+            let _ = results.send(BackgroundEvent::DnsResult { ip, hostname });
+            let _ = permit_tx.send(());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fake_lookup(ip: &IpAddr) -> std::io::Result<String> {
+        match ip.to_string().as_str() {
+            "10.0.0.1" => Ok(String::from("host.example")),
+            "10.0.0.2" => Ok(String::from("10.0.0.2")),
+            _ => Err(std::io::Error::other("no PTR record")),
+        }
+    }
+
+    #[test]
+    fn test_resolver_reports_results_and_exits() {
+        let (results_tx, results_rx) = mpsc::sync_channel(3);
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || run_resolver(rx, results_tx, fake_lookup));
+
+        for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+            tx.send(ip.parse().unwrap()).unwrap();
+        }
+        drop(tx);
+        handle.join().unwrap();
+
+        let mut got: Vec<(String, Option<String>)> = (0..3)
+            .map(|_| match results_rx.recv_timeout(Duration::from_secs(1)).unwrap() {
+                BackgroundEvent::DnsResult { ip, hostname } => (ip.to_string(), hostname),
+                _ => panic!("unexpected event"),
+            })
+            .collect();
+        got.sort();
+        assert_eq!(got, vec![
+            (String::from("10.0.0.1"), Some(String::from("host.example"))),
+            (String::from("10.0.0.2"), None),
+            (String::from("10.0.0.3"), None),
+        ]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+pub const ETC_SERVICES: &str = "/etc/services";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Proto {
+    Tcp,
+    Udp,
+}
+
+impl Proto {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "tcp" => Some(Proto::Tcp),
+            "udp" => Some(Proto::Udp),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the first name listed for each port and protocol.
+pub fn parse_etc_services(content: &str) -> HashMap<(u16, Proto), String> {
+    let mut services = HashMap::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else { continue };
+        let Some((port, proto)) = port_proto.split_once('/') else { continue };
+        if let (Ok(port), Some(proto)) = (port.parse(), Proto::from_name(proto)) {
+            services.entry((port, proto)).or_insert_with(|| name.to_string());
+        }
+    }
+    services
+}
+
+/// Built-in names used when `/etc/services` has no entry for a port.
+pub fn port_to_service(port: u16) -> Option<&'static str> {
+    Some(match port {
+        21 => "ftp",
+        22 => "ssh",
+        25 => "smtp",
+        53 => "domain",
+        80 => "http",
+        110 => "pop3",
+        143 => "imap",
+        443 => "https",
+        587 => "submission",
+        993 => "imaps",
+        995 => "pop3s",
+        3306 => "mysql",
+        5432 => "postgresql",
+        6379 => "redis",
+        8080 => "http-alt",
+        27017 => "mongodb",
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct ServiceNames {
+    names: HashMap<(u16, Proto), String>,
+}
+
+impl ServiceNames {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self { names: parse_etc_services(&fs::read_to_string(path)?) })
+    }
+
+    /// Prefers the connection's own protocol, then TCP, then UDP.
+    pub fn lookup(&self, port: u16, proto: &str) -> Option<&str> {
+        Proto::from_name(proto).into_iter()
+            .chain([Proto::Tcp, Proto::Udp])
+            .find_map(|p| self.names.get(&(port, p)))
+            .map(String::as_str)
+            .or_else(|| port_to_service(port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICES: &str = "\
+# Network services, Internet style
+ssh             22/tcp                          # SSH Remote Login Protocol
+domain          53/tcp
+domain          53/udp
+http            80/tcp          www             # WorldWideWeb HTTP
+http-alt        8080/tcp        webcache
+syslog          514/udp
+bogus           abc/tcp
+";
+
+    #[test]
+    fn test_parse_etc_services() {
+        let services = parse_etc_services(SERVICES);
+        assert_eq!(services.get(&(22, Proto::Tcp)).map(String::as_str), Some("ssh"));
+        assert_eq!(services.get(&(53, Proto::Udp)).map(String::as_str), Some("domain"));
+        assert_eq!(services.get(&(8080, Proto::Tcp)).map(String::as_str), Some("http-alt"));
+        assert_eq!(services.len(), 6);
+    }
+
+    #[test]
+    fn test_lookup_falls_back() {
+        let names = ServiceNames { names: parse_etc_services(SERVICES) };
+        assert_eq!(names.lookup(514, "TCP"), Some("syslog"));
+        assert_eq!(names.lookup(80, "UDP"), Some("http"));
+        assert_eq!(names.lookup(6379, "TCP"), Some("redis"));
+        assert_eq!(names.lookup(49152, "TCP"), None);
+        assert_eq!(ServiceNames::default().lookup(443, "TCP"), Some("https"));
+    }
+}
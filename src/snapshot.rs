@@ -0,0 +1,163 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ConnectionEntry, HistoryEntry};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppSnapshot {
+    pub target: String,
+    pub timestamp: String,
+    pub active_connections: Vec<String>,
+    pub history_count: usize,
+    pub seen_ips_count: usize,
+    pub last_status: String,
+    pub state: AppState,
+}
+
+/// The part of `App` that can be saved and restored, e.g. for `--replay`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AppState {
+    pub target_name: String,
+    pub active_connections: Vec<ConnectionEntry>,
+    pub history_log: Vec<HistoryEntry>,
+    pub seen_ips: BTreeSet<String>,
+    pub connection_counts: BTreeMap<String, u64>,
+}
+
+pub fn sanitize_target(target: &str) -> String {
+    target.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+pub fn snapshot_path(target: &str) -> PathBuf {
+    PathBuf::from(format!("/tmp/netmonrs-{}-snapshot.json", sanitize_target(target)))
+}
+
+/// Serializes with object keys sorted so snapshots diff cleanly.
+pub fn to_stable_json<T: Serialize>(value: &T) -> io::Result<String> {
+    let value = serde_json::to_value(value).map_err(io::Error::other)?;
+    serde_json::to_string_pretty(&value).map_err(io::Error::other)
+}
+
+pub fn read_snapshot(path: &Path) -> io::Result<AppSnapshot> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes to a fresh temp file next to `path` and renames it into place.
+/// The temp file is opened with `create_new`, so a symlink planted under
+/// its name in a shared directory like /tmp is never followed.
+pub fn write_snapshot(path: &Path, snapshot: &AppSnapshot) -> io::Result<()> {
+    let json = to_stable_json(snapshot)?;
+    let tmp = tmp_path(path);
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .and_then(|mut f| {
+            f.write_all(json.as_bytes())?;
+            f.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{:016x}.tmp", std::process::id(), rand::random::<u64>()));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_path_sanitizes_target() {
+        assert_eq!(snapshot_path("nginx"), PathBuf::from("/tmp/netmonrs-nginx-snapshot.json"));
+        assert_eq!(snapshot_path("python app.py"), PathBuf::from("/tmp/netmonrs-python_app.py-snapshot.json"));
+        assert_eq!(snapshot_path("../etc"), PathBuf::from("/tmp/netmonrs-.._etc-snapshot.json"));
+    }
+
+    #[test]
+    fn test_write_snapshot_atomic() {
+        let path = std::env::temp_dir().join(format!("netmonrs-snapshot-test-{}.json", std::process::id()));
+        let snapshot = AppSnapshot {
+            target: String::from("nginx"),
+            timestamp: String::from("2024-01-01T12:00:00+00:00"),
+            active_connections: vec![String::from("93.184.216.34:443")],
+            history_count: 3,
+            seen_ips_count: 2,
+            last_status: String::from("Monitoring: nginx (1234)"),
+            state: AppState::default(),
+        };
+        write_snapshot(&path, &snapshot).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(value["target"], "nginx");
+        assert_eq!(value["active_connections"][0], "93.184.216.34:443");
+        assert_eq!(value["history_count"], 3);
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let leftovers = fs::read_dir(path.parent().unwrap()).unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_str().is_some_and(|n| n.starts_with(name) && n.ends_with(".tmp")))
+            .count();
+        assert_eq!(leftovers, 0);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_tmp_path_is_unique_sibling() {
+        let path = Path::new("/tmp/netmonrs-nginx-snapshot.json");
+        let (a, b) = (tmp_path(path), tmp_path(path));
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), path.parent());
+        let name = a.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(&format!("netmonrs-nginx-snapshot.json.{}.", std::process::id())));
+        assert!(name.ends_with(".tmp"));
+    }
+
+    #[test]
+    fn test_snapshot_file_round_trip() {
+        use chrono::{Local, TimeZone};
+
+        let ts = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let conn = ConnectionEntry { size: Some(2048), rate: Some(1.5), ..crate::tests::test_conn("2001:db8::1", 443) };
+        let state = AppState {
+            target_name: String::from("nginx"),
+            active_connections: vec![conn.clone()],
+            history_log: vec![(ts, String::from("2001:db8::1"))],
+            seen_ips: BTreeSet::from([String::from("2001:db8::1")]),
+            connection_counts: BTreeMap::from([(String::from("2001:db8::1"), 1)]),
+        };
+
+        let json = to_stable_json(&state).unwrap();
+        assert!(json.contains("\"2024-01-01T12:00:00"));
+        let restored: AppState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.target_name, state.target_name);
+        assert_eq!(restored.history_log, state.history_log);
+        assert_eq!(restored.seen_ips, state.seen_ips);
+        assert_eq!(restored.connection_counts, state.connection_counts);
+        let c = &restored.active_connections[0];
+        assert_eq!(c.key(), conn.key());
+        assert_eq!((&c.proto, &c.state, c.size, c.rate), (&conn.proto, &conn.state, conn.size, conn.rate));
+    }
+
+    #[test]
+    fn test_stable_json_sorts_keys() {
+        let json = to_stable_json(&AppState { target_name: String::from("nginx"), ..Default::default() }).unwrap();
+        let keys: Vec<&str> = json.lines()
+            .filter_map(|l| l.trim().strip_prefix('"')?.split_once('"').map(|(k, _)| k))
+            .collect();
+        assert_eq!(keys, vec!["active_connections", "connection_counts", "history_log", "seen_ips", "target_name"]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Deserialize;
+
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+struct TagsFile {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+pub fn parse_tags(content: &str) -> Result<HashMap<IpAddr, String>, String> {
+    let file: TagsFile = toml::from_str(content).map_err(|e| e.to_string())?;
+    file.tags.into_iter()
+        .map(|(ip, label)| {
+            ip.parse::<IpAddr>()
+                .map(|ip| (ip, label))
+                .map_err(|e| format!("invalid IP '{}': {}", ip, e))
+        })
+        .collect()
+}
+
+pub fn load_tags(path: &Path) -> Result<HashMap<IpAddr, String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_tags(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub struct TagsWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl TagsWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        Self { path, modified, last_check: Instant::now() }
+    }
+
+    // Returns freshly loaded tags when the file changed since the last check.
+    pub fn poll(&mut self) -> Option<Result<HashMap<IpAddr, String>, String>> {
+        if self.last_check.elapsed() < RELOAD_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(load_tags(&self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        let content = r#"
+[tags]
+"93.184.216.34" = "example.com CDN"
+"2001:db8::1" = "lab router"
+"#;
+        let tags = parse_tags(content).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[&"93.184.216.34".parse::<IpAddr>().unwrap()], "example.com CDN");
+        assert_eq!(tags[&"2001:db8::1".parse::<IpAddr>().unwrap()], "lab router");
+    }
+
+    #[test]
+    fn test_parse_tags_invalid_ip() {
+        let err = parse_tags("[tags]\n\"example.com\" = \"nope\"\n").unwrap_err();
+        assert!(err.contains("example.com"));
+    }
+
+    #[test]
+    fn test_parse_tags_missing_section() {
+        assert!(parse_tags("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+
+/// Appends the raw `lsof` output of every poll to the `--tee` file.
+#[derive(Debug)]
+pub struct Tee {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    writer: BufWriter<File>,
+}
+
+impl Tee {
+    pub fn open(path: PathBuf, max_bytes: Option<u64>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, max_bytes, writer: BufWriter::new(file) })
+    }
+
+    pub fn write(&mut self, pid: &str, output: &str, now: DateTime<Utc>) -> io::Result<()> {
+        if let Some(max_bytes) = self.max_bytes {
+            rotate_if_needed(&self.path, max_bytes)?;
+        }
+        writeln!(self.writer, "=== {} pid={} ===", now.format("%Y-%m-%dT%H:%M:%SZ"), pid)?;
+        self.writer.write_all(output.as_bytes())?;
+        if !output.ends_with('\n') {
+            writeln!(self.writer)?;
+        }
+        self.writer.flush()
+    }
+}
+
+/// Truncates `path` once it has grown past `max_bytes`. Writers that opened
+/// it in append mode carry on from the new end.
+pub fn rotate_if_needed(path: &Path, max_bytes: u64) -> io::Result<()> {
+    if path.metadata()?.len() > max_bytes {
+        OpenOptions::new().write(true).open(path)?.set_len(0)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("netmonrs-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_rotate_if_needed() {
+        let path = test_path("tee-rotate");
+        fs::write(&path, vec![b'x'; 100]).unwrap();
+
+        rotate_if_needed(&path, 100).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 100);
+
+        rotate_if_needed(&path, 99).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tee_appends_with_header_and_rotates() {
+        let path = test_path("tee");
+        let _ = fs::remove_file(&path);
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        let mut tee = Tee::open(path.clone(), Some(100)).unwrap();
+        tee.write("1234", "COMMAND PID\nnginx 1234\n", now).unwrap();
+        tee.write("1234", "COMMAND PID", now).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "=== 2024-01-01T12:00:00Z pid=1234 ===\nCOMMAND PID\nnginx 1234\n=== 2024-01-01T12:00:00Z pid=1234 ===\nCOMMAND PID\n",
+        );
+
+        tee.write("5678", "rotated\n", now).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "=== 2024-01-01T12:00:00Z pid=5678 ===\nrotated\n");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatToken {
+    Literal(String),
+    Ts,
+    Ip,
+    Port,
+    Direction,
+    Duration,
+}
+
+/// Tokens of a `--format-status` template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusToken {
+    Literal(String),
+    Pid,
+    ActiveCount,
+    HistoryCount,
+    Uptime,
+    PollCount,
+    Rate,
+    /// Empty unless the last poll failed.
+    Error,
+}
+
+pub const DEFAULT_STATUS_TEMPLATE: &str = "{pid} | Active: {active_count} | History: {history_count}";
+
+/// Values substituted into a history template. Fields that aren't known for
+/// an entry are left empty.
+#[derive(Debug, Default)]
+pub struct HistoryFields<'a> {
+    pub ts: &'a str,
+    pub ip: &'a str,
+    pub port: &'a str,
+    pub direction: &'a str,
+    pub duration: &'a str,
+}
+
+pub fn parse_template(template: &str) -> Result<Vec<FormatToken>, String> {
+    parse_tokens(template, FormatToken::Literal, |name| match name {
+        "ts" => Some(FormatToken::Ts),
+        "ip" => Some(FormatToken::Ip),
+        "port" => Some(FormatToken::Port),
+        "direction" => Some(FormatToken::Direction),
+        "duration" => Some(FormatToken::Duration),
+        _ => None,
+    })
+}
+
+pub fn parse_status_template(template: &str) -> Result<Vec<StatusToken>, String> {
+    parse_tokens(template, StatusToken::Literal, |name| match name {
+        "pid" => Some(StatusToken::Pid),
+        "active_count" => Some(StatusToken::ActiveCount),
+        "history_count" => Some(StatusToken::HistoryCount),
+        "uptime" => Some(StatusToken::Uptime),
+        "poll_count" => Some(StatusToken::PollCount),
+        "rate" => Some(StatusToken::Rate),
+        "error" => Some(StatusToken::Error),
+        _ => None,
+    })
+}
+
+fn parse_tokens<T>(template: &str, literal: fn(String) -> T, token: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            tokens.push(literal(rest[..start].to_string()));
+        }
+        let end = rest[start..].find('}').ok_or_else(|| format!("unclosed '{{' in '{}'", template))?;
+        let name = &rest[start + 1..start + end];
+        tokens.push(token(name).ok_or_else(|| format!("unknown token '{{{}}}'", name))?);
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(literal(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+pub fn render(tokens: &[FormatToken], fields: &HistoryFields) -> String {
+    tokens.iter()
+        .map(|token| match token {
+            FormatToken::Literal(s) => s.as_str(),
+            FormatToken::Ts => fields.ts,
+            FormatToken::Ip => fields.ip,
+            FormatToken::Port => fields.port,
+            FormatToken::Direction => fields.direction,
+            FormatToken::Duration => fields.duration,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> HistoryFields<'static> {
+        HistoryFields {
+            ts: "12:00:00",
+            ip: "93.184.216.34",
+            port: "443",
+            direction: "closed",
+            duration: "1m 5s",
+        }
+    }
+
+    #[test]
+    fn test_default_template() {
+        let tokens = parse_template("[{ts}] {ip}").unwrap();
+        assert_eq!(tokens, vec![
+            FormatToken::Literal(String::from("[")),
+            FormatToken::Ts,
+            FormatToken::Literal(String::from("] ")),
+            FormatToken::Ip,
+        ]);
+        assert_eq!(render(&tokens, &fields()), "[12:00:00] 93.184.216.34");
+    }
+
+    #[test]
+    fn test_all_tokens() {
+        let tokens = parse_template("{ts} {direction} {ip}:{port} ({duration})").unwrap();
+        assert_eq!(render(&tokens, &fields()), "12:00:00 closed 93.184.216.34:443 (1m 5s)");
+    }
+
+    #[test]
+    fn test_missing_fields_render_empty() {
+        let tokens = parse_template("{ip}|{port}|{duration}").unwrap();
+        let f = HistoryFields { ip: "10.0.0.1", ..Default::default() };
+        assert_eq!(render(&tokens, &f), "10.0.0.1||");
+    }
+
+    #[test]
+    fn test_status_default_template() {
+        assert_eq!(parse_status_template(DEFAULT_STATUS_TEMPLATE).unwrap(), vec![
+            StatusToken::Pid,
+            StatusToken::Literal(String::from(" | Active: ")),
+            StatusToken::ActiveCount,
+            StatusToken::Literal(String::from(" | History: ")),
+            StatusToken::HistoryCount,
+        ]);
+    }
+
+    #[test]
+    fn test_status_all_tokens() {
+        let tokens = parse_status_template("{pid}{active_count}{history_count}{uptime}{poll_count}{rate}{error}").unwrap();
+        assert_eq!(tokens, vec![
+            StatusToken::Pid,
+            StatusToken::ActiveCount,
+            StatusToken::HistoryCount,
+            StatusToken::Uptime,
+            StatusToken::PollCount,
+            StatusToken::Rate,
+            StatusToken::Error,
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_status_templates() {
+        assert_eq!(parse_status_template("{ip}"), Err(String::from("unknown token '{ip}'")));
+        assert_eq!(parse_status_template("up {uptime"), Err(String::from("unclosed '{' in 'up {uptime'")));
+        assert_eq!(parse_status_template(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert_eq!(parse_template("{host}"), Err(String::from("unknown token '{host}'")));
+        assert_eq!(parse_template("{proto}"), Err(String::from("unknown token '{proto}'")));
+        assert_eq!(parse_template("[{ts"), Err(String::from("unclosed '{' in '[{ts'")));
+        assert_eq!(parse_template("plain"), Ok(vec![FormatToken::Literal(String::from("plain"))]));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn netmonrs() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_netmonrs"))
+}
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lsof.txt");
+
+#[test]
+fn once_exits_1_when_process_missing() {
+    let status = netmonrs()
+        .args(["--once", "netmonrs-test-no-such-process-0000"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(1));
+}
+
+#[test]
+fn once_against_running_process() {
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let output = netmonrs().args(["--once", "sleep 30"]).output().unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    // 0 when lsof ran, 2 when it could not be invoked (e.g. no sudo available).
+    let code = output.status.code();
+    assert!(matches!(code, Some(0) | Some(2)), "unexpected exit code {:?}", code);
+    if code == Some(0) {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.lines().all(|l| l.contains(':')));
+    }
+}
+
+#[test]
+fn check_fails_when_process_missing() {
+    let output = netmonrs().args(["check", "netmonrs-test-no-such-process-0000"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("✗ process 'netmonrs-test-no-such-process-0000' running: no matching process"));
+}
+
+#[test]
+fn check_prints_one_line_per_check() {
+    let mut child = Command::new("sleep").arg("31").spawn().unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let output = netmonrs().args(["check", "sleep 31"]).output().unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 5, "unexpected output {:?}", stdout);
+    assert!(lines.iter().all(|l| l.starts_with("✓ ") || l.starts_with("✗ ")));
+    assert!(lines[2].starts_with("✓ process 'sleep 31' running: PID "));
+    let expected = if lines.iter().all(|l| l.starts_with('✓')) { 0 } else { 1 };
+    assert_eq!(output.status.code(), Some(expected));
+}
+
+#[test]
+fn timeout_ends_quiet_and_diff_modes() {
+    for mode in ["--quiet", "--diff"] {
+        let start = Instant::now();
+        let output = netmonrs()
+            .args([mode, "--timeout", "1", "--input-file", FIXTURE, "nginx"])
+            .output()
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10), "{} ignored --timeout", mode);
+        assert_eq!(output.status.code(), Some(0), "{}", mode);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("93.184.216.34"), "{}", mode);
+    }
+}
+
+#[test]
+fn help_lists_subcommands() {
+    let output = netmonrs().arg("--help").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("collect"), "{}", stdout);
+    assert!(stdout.contains("check"), "{}", stdout);
+}
+
+#[test]
+fn lifecycle_hooks_run_in_quiet_mode() {
+    let dir = std::env::temp_dir();
+    let started = dir.join(format!("netmonrs-cli-startup-{}", std::process::id()));
+    let exited = dir.join(format!("netmonrs-cli-exit-{}", std::process::id()));
+    let status = netmonrs()
+        .args(["--quiet", "--timeout", "1", "--input-file", FIXTURE, "nginx"])
+        .args(["--exec-on-startup", &format!("echo {{target}} > {}", started.display())])
+        .args(["--exec-on-exit", &format!("echo {{target}} > {}", exited.display())])
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(&started).unwrap(), "nginx\n");
+    assert_eq!(std::fs::read_to_string(&exited).unwrap(), "nginx\n");
+    std::fs::remove_file(started).unwrap();
+    std::fs::remove_file(exited).unwrap();
+}
+
+#[test]
+fn pid_wait_timeout_exits_non_zero() {
+    for mode in [&["--quiet"][..], &["--diff"], &["--report-after", "60"]] {
+        let output = netmonrs()
+            .args(mode)
+            .args(["--pid-wait", "1", "netmonrs-test-pid-wait-0000"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{:?}", mode);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Timed out waiting for process"), "{:?}: {}", mode, stderr);
+    }
+}
+
+#[test]
+fn exec_on_connect_runs_for_each_new_ip() {
+    let path = std::env::temp_dir().join(format!("netmonrs-cli-connect-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let status = netmonrs()
+        .args(["--quiet", "--timeout", "2", "--input-file", FIXTURE, "nginx"])
+        .args(["--exec-on-connect", &format!("echo {{ip}} >> {}", path.display())])
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(0));
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let mut ips: Vec<&str> = contents.lines().collect();
+    ips.sort();
+    assert_eq!(ips, ["2001:db8::1", "8.8.8.8", "93.184.216.34"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn check_matches_targets_like_the_monitor() {
+    let mut child = Command::new("sleep").arg("32").spawn().unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let output = netmonrs().args(["check", "sl[e]+p 32"]).output().unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("✓ process 'sl[e]+p 32' running: PID {}", child.id())), "{}", stdout);
+}
+
+#[test]
+fn once_honours_exclude_self() {
+    let path = std::env::temp_dir().join(format!("netmonrs-cli-once-{}.txt", std::process::id()));
+    let mut fixture = std::fs::read_to_string(FIXTURE).unwrap();
+    fixture.push_str("nginx    1234  root   10u  IPv4 0xffff8880035a3c00      0t0  TCP 127.0.0.1:80->127.0.0.1:53456 (ESTABLISHED)\n");
+    std::fs::write(&path, fixture).unwrap();
+
+    let output = netmonrs()
+        .args(["--once", "--exclude-self", "--input-file", path.to_str().unwrap(), "nginx"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("93.184.216.34"), "{}", stdout);
+    assert!(!stdout.contains("127.0.0.1"), "{}", stdout);
+}